@@ -39,8 +39,8 @@ fn main() -> Result<(), ProcessingErr> {
     screen.space_wait();
     screen.no_cursor()?;
 
-    screen.stroke(&[0.], &[0.], &[0.], &[1.]);
-    screen.fill(&[0.7], &[0.7], &[0.7], &[1.0]);
+    screen.stroke(&[0.], &[0.], &[0.], &[1.])?;
+    screen.fill(&[0.7], &[0.7], &[0.7], &[1.0])?;
     let e1 = Ellipse::new(&screen, &[0.], &[0.], &[0.], &[0.3], &[0.5])?;
     let e2 = Ellipse::new(&screen, &[-0.5], &[0.5], &[0.], &[0.2], &[0.2])?;
     let tr1 = Triangle::new(&screen,
@@ -75,7 +75,7 @@ fn main() -> Result<(), ProcessingErr> {
                            .map(|_| 0.)
                            .collect::<Vec<_>>())?;
     screen.stroke_weight(1f32);
-    screen.fill(&[0.], &[0.], &[0.9], &[1.]);
+    screen.fill(&[0.], &[0.], &[0.9], &[1.])?;
     let q = Quad::new(&screen,
                       &[-0.3],
                       &[-0.75],
@@ -92,7 +92,7 @@ fn main() -> Result<(), ProcessingErr> {
     let l1 = Line::new(&screen, &[0.5], &[-0.4], &[0.], &[0.7], &[-0.5], &[0.])?;
     let r1 = Rect::new(&screen, &[-0.6], &[-0.4], &[0.], &[0.2], &[0.5])?;
     let l2 = Line::new(&screen, &[0.7], &[-0.4], &[0.], &[0.5], &[-0.5], &[0.])?;
-    screen.stroke(&[0.9], &[0.], &[0.], &[1.]);
+    screen.stroke(&[0.9], &[0.], &[0.], &[1.])?;
     let a = Arc::new(&screen,
                      &[-0.6],
                      &[0.6],
@@ -101,18 +101,19 @@ fn main() -> Result<(), ProcessingErr> {
                      &[0.3],
                      &[0.4 * f64::consts::PI],
                      &[f64::consts::PI])?;
-    screen.stroke(&[0.], &[0.9], &[0.], &[1.]);
-    screen.fill(&[1.0], &[1.0], &[1.0], &[1.0]);
+    screen.stroke(&[0.], &[0.9], &[0.], &[1.])?;
+    screen.fill(&[1.0], &[1.0], &[1.0], &[1.0])?;
     let e3 = Ellipse::new(&screen, &[0.], &[0.], &[0.], &[0.2], &[0.2])?;
     screen.fill(&[t.sin() / 2. + 0.5],
                 &[t.cos() / 2. + 0.5],
                 &[(t.sin() * t.cos()) / 2. + 0.5],
-                &[1.0]);
+                &[1.0])?;
     let c = Cube::new(&screen, &[0.15])?;
-    screen.fill(&[1.], &[1.], &[1.], &[1.]);
+    screen.fill(&[1.], &[1.], &[1.], &[1.])?;
     let mut r2 = Rect::new(&screen, &[-0.1], &[0.6], &[0.], &[0.2], &[0.2])?;
     r2.attach_texture(&tex);
 
+    let mut missed = 0;
     while x < 600 {
         let st = time::precise_time_ns();
         screen.background(0.94, 0.92, 0.9, 1.0);
@@ -122,7 +123,8 @@ fn main() -> Result<(), ProcessingErr> {
         screen.draw(&tr1)?;
         screen.draw(&tr2)?;
         screen.draw(&p)?;
-        // screen.text("processing-rs", &[0.25], -&[0.85]);
+        screen.text("processing-rs", 0.25, -0.85)?;
+        screen.ellipse(-0.75, -0.75, 0.1, 0.1)?;
         screen.draw(&q)?;
         screen.draw(&l1)?;
         screen.fill_off();
@@ -148,7 +150,9 @@ fn main() -> Result<(), ProcessingErr> {
             break;
         }
         ftbf[x - 1] = time::precise_time_ns() - st;
-        screen.reveal()?;
+        if screen.reveal()?.missed {
+            missed += 1;
+        }
         ft[x - 1] = time::precise_time_ns() - st;
         t += 1. / 60.;
         x += 1;
@@ -156,13 +160,15 @@ fn main() -> Result<(), ProcessingErr> {
 
     let duration_s = (ft.iter().fold(0, |acc, v| acc + v) as f64) / 1_000_000_000f64;
     let fps = (x as f64) / duration_s;
-    let dropped = (duration_s - (x as f64 * (1f64 / 60f64))) / (1f64 / 60f64);
+    let refresh = screen.refresh_interval().unwrap_or(1f64 / 60f64);
+    let dropped = (duration_s - (x as f64 * refresh)) / refresh;
 
-    println!("{} frames in {:.6} seconds = {:.3} fps (estimated {:.1} frames dropped)",
+    println!("{} frames in {:.6} seconds = {:.3} fps (estimated {:.1} frames dropped, {} frames missed)",
              x,
              duration_s,
              fps,
-             dropped);
+             dropped,
+             missed);
 
     println!("{:?}", &ftbf[..]);
 
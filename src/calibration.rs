@@ -0,0 +1,305 @@
+// Monitor calibration
+use std::io;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use glium;
+use glium::backend::Facade;
+use glium::texture::{Texture1d, UncompressedFloatFormat, MipmapsOption};
+use serde_json;
+use serde_json::Value;
+
+use {Screen, ScreenType};
+use errors::ProcessingErr;
+
+// the allowed sizes of a gamma table
+const MIN_GAMMA_ENTRIES: usize = 256;
+const MAX_GAMMA_ENTRIES: usize = 4096;
+
+// how many entries screen.set_gamma() uses
+const GAMMA_ENTRIES: usize = 1024;
+
+/// A gamma table that leaves every color as it is. Every Screen starts out with one,
+/// so that the final pass always has a table to sample.
+pub fn identity_gamma_table(display: &Facade) -> Result<Texture1d, ProcessingErr> {
+    let ramp: Vec<f32> = (0..MIN_GAMMA_ENTRIES).map(|i| i as f32 / (MIN_GAMMA_ENTRIES - 1) as f32).collect();
+    gamma_texture(display, &ramp, &ramp, &ramp)
+}
+
+fn gamma_texture(display: &Facade, red: &[f32], green: &[f32], blue: &[f32]) -> Result<Texture1d, ProcessingErr> {
+    let entries: Vec<(f32, f32, f32)> = red.iter()
+        .zip(green.iter())
+        .zip(blue.iter())
+        .map(|((&r, &g), &b)| (r, g, b))
+        .collect();
+    Texture1d::with_format(display, entries, UncompressedFloatFormat::F32F32F32, MipmapsOption::NoMipmap)
+        .map_err(|e| ProcessingErr::TextureNoCreate(e))
+}
+
+/// How the final pass of screen.reveal() writes colors to the monitor. The packed
+/// encodings are meant for devices such as the Bits# or the Display++ from Cambridge
+/// Research Systems and the VIEWPixx from VPixx, which read 16 bits per channel from an
+/// ordinary 8-bit video signal. They need an 8-bit display (see textures::ScreenFormat),
+/// since every byte has to arrive exactly as it was written, and they are applied after
+/// the gamma table.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OutputEncoding {
+    /// Write the colors as they are.
+    Plain = 0,
+    /// Mono++: write the luminance, taken from the red channel, with 16 bits, the high
+    /// byte in red and the low byte in green. Blue is 0, which means no overlay.
+    MonoPlusPlus = 1,
+    /// Color++: write every channel with 16 bits, using pairs of horizontally adjacent
+    /// pixels, with the high bytes in the left pixel and the low bytes in the right one.
+    /// This halves the horizontal resolution, since each pair shows the average of the
+    /// two pixels that were drawn there.
+    ColorPlusPlus = 2,
+}
+
+impl Default for OutputEncoding {
+    fn default() -> Self {
+        OutputEncoding::Plain
+    }
+}
+
+impl OutputEncoding {
+	/// Turn pixels that were written with this encoding, e.g., as read back by
+	/// screen.read_display(), into the colors that the device shows, row by row. Mono++
+	/// gives gray colors and Color++ gives one color for every pair of pixels.
+    pub fn decode(&self, pixels: &[[u8; 4]]) -> Vec<[f32; 3]> {
+        let sixteen = |high: u8, low: u8| (high as f32 * 256.0 + low as f32) / 65535.0;
+        match *self {
+            OutputEncoding::Plain => pixels
+                .iter()
+                .map(|p| [p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0])
+                .collect(),
+            OutputEncoding::MonoPlusPlus => pixels
+                .iter()
+                .map(|p| {
+                    let l = sixteen(p[0], p[1]);
+                    [l, l, l]
+                })
+                .collect(),
+            OutputEncoding::ColorPlusPlus => pixels
+                .chunks(2)
+                .filter(|pair| pair.len() == 2)
+                .map(|pair| {
+                    let (h, l) = (pair[0], pair[1]);
+                    [sixteen(h[0], l[0]), sixteen(h[1], l[1]), sixteen(h[2], l[2])]
+                })
+                .collect(),
+        }
+    }
+}
+
+// a number or a list of numbers in a calibration file
+fn numbers(value: &Value) -> Option<Vec<f32>> {
+    match *value {
+        Value::Number(ref n) => n.as_f64().map(|n| vec![n as f32]),
+        Value::Array(ref items) => items.iter().map(|i| i.as_f64().map(|n| n as f32)).collect(),
+        _ => None,
+    }
+}
+
+// the gamma of every channel, as one or three numbers
+fn three(v: Vec<f32>) -> Option<(f32, f32, f32)> {
+    match v.len() {
+        1 => Some((v[0], v[0], v[0])),
+        3 => Some((v[0], v[1], v[2])),
+        _ => None,
+    }
+}
+
+impl<'a> Screen<'a> {
+	/// Correct the colors of everything that is revealed from now on with a lookup table
+	/// for each channel, e.g., to linearize the luminance of a monitor that has been
+	/// measured with a photometer. Each table maps evenly spaced values from 0 to 1 to
+	/// the values that should be sent to the monitor instead, with linear interpolation
+	/// in between. The tables must have the same length, between 256 and 4096 entries.
+	/// The correction is applied in the final pass of screen.reveal(), so it affects
+	/// every shape and shader, but not screen.save() or screen.load_pixels().
+    pub fn load_gamma_table(&mut self, red: &[f32], green: &[f32], blue: &[f32]) -> Result<(), ProcessingErr> {
+        if red.len() != green.len() {
+            return Err(ProcessingErr::LengthMismatch(red.len(), green.len()));
+        }
+        if red.len() != blue.len() {
+            return Err(ProcessingErr::LengthMismatch(red.len(), blue.len()));
+        }
+        if red.len() < MIN_GAMMA_ENTRIES || red.len() > MAX_GAMMA_ENTRIES {
+            return Err(ProcessingErr::GammaTableSize(red.len()));
+        }
+
+        self.gamma_table = match self.display {
+            ScreenType::Window(ref d) => gamma_texture(d, red, green, blue)?,
+            ScreenType::Headless(ref d) => gamma_texture(d, red, green, blue)?,
+        };
+        self.gamma_correction = true;
+
+        Ok(())
+    }
+
+	/// Correct the colors of everything that is revealed from now on for a monitor whose
+	/// channels have the given gammas, i.e., whose luminance goes with the value sent to
+	/// it raised to that power. Each channel is raised to the power of one over its gamma
+	/// beforehand, so that the luminance ends up linear in the drawn colors. The gammas
+	/// have to be positive and finite.
+    pub fn set_gamma(&mut self, r: f32, g: f32, b: f32) -> Result<(), ProcessingErr> {
+        for &gamma in &[r, g, b] {
+            if !(gamma.is_finite() && gamma > 0.0) {
+                return Err(ProcessingErr::InvalidGamma(gamma));
+            }
+        }
+        let table = |gamma: f32| -> Vec<f32> {
+            (0..GAMMA_ENTRIES)
+                .map(|i| (i as f32 / (GAMMA_ENTRIES - 1) as f32).powf(1.0 / gamma))
+                .collect()
+        };
+        self.load_gamma_table(&table(r), &table(g), &table(b))
+    }
+
+	/// Load a calibration file and correct the colors with it from now on. CSV files
+	/// (ending in ".csv") hold a lookup table as in screen.load_gamma_table(), with one
+	/// line per entry and either one value for all channels or a red, green, and blue
+	/// value, separated by commas. A first line that is not made of numbers is taken to
+	/// be a header. JSON files (anything else) hold an object with either a "gamma" that
+	/// is one number or a list of three, as in screen.set_gamma(), or the lists "red",
+	/// "green", and "blue", as in screen.load_gamma_table().
+    pub fn load_calibration(&mut self, filename: &str) -> Result<(), ProcessingErr> {
+        let mut src = String::new();
+        File::open(filename)
+            .and_then(|mut f| f.read_to_string(&mut src))
+            .map_err(|e| ProcessingErr::CalibrationNotFound(e))?;
+        let bad = |line: usize, msg: &str| {
+            ProcessingErr::ErrorReadingCalibration(line, io::Error::new(io::ErrorKind::InvalidData, msg))
+        };
+
+        let ext = Path::new(filename).extension().and_then(|s| s.to_str()).map_or("".to_owned(), |s| s.to_lowercase());
+        if ext == "csv" {
+            let (mut red, mut green, mut blue) = (Vec::new(), Vec::new(), Vec::new());
+            for (i, line) in src.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let values: Result<Vec<f32>, _> = line.split(',').map(|v| v.trim().parse()).collect();
+                match values.ok().and_then(three) {
+                    Some((r, g, b)) => {
+                        red.push(r);
+                        green.push(g);
+                        blue.push(b);
+                    }
+                    None if i == 0 => continue,
+                    None => return Err(bad(i + 1, "expected one or three numbers")),
+                }
+            }
+            return self.load_gamma_table(&red, &green, &blue);
+        }
+
+        let json: Value = serde_json::from_str(&src)
+            .map_err(|e| ProcessingErr::ErrorReadingCalibration(e.line(), io::Error::from(e)))?;
+        let fields = match json {
+            Value::Object(fields) => fields,
+            _ => return Err(bad(1, "expected an object")),
+        };
+        let field = |name: &str| fields.get(name).and_then(numbers);
+        if let Some(gamma) = field("gamma") {
+            let (r, g, b) = three(gamma).ok_or_else(|| bad(1, "\"gamma\" should be one or three numbers"))?;
+            return self.set_gamma(r, g, b);
+        }
+        match (field("red"), field("green"), field("blue")) {
+            (Some(r), Some(g), Some(b)) => self.load_gamma_table(&r, &g, &b),
+            _ => Err(bad(1, "expected \"gamma\" or \"red\", \"green\", and \"blue\"")),
+        }
+    }
+
+	/// Stop correcting the colors, e.g., to check the raw output of the monitor. The
+	/// gamma table is kept, so screen.gamma_correction_on() brings it back.
+    pub fn gamma_correction_off(&mut self) {
+        self.gamma_correction = false;
+    }
+
+	/// Correct the colors again with the last gamma table that was set.
+    pub fn gamma_correction_on(&mut self) {
+        self.gamma_correction = true;
+    }
+
+	/// Choose how the final pass of screen.reveal() writes colors to the monitor (see
+	/// OutputEncoding). The default is OutputEncoding::Plain. The packed encodings only
+	/// work if the display has exactly 8 bits per color channel (see
+	/// screen.display_format()), so they return an error otherwise, e.g., for the
+	/// 10-bit display that the GLFW backend asks for by default.
+    pub fn output_encoding(&mut self, encoding: OutputEncoding) -> Result<(), ProcessingErr> {
+        let f = self.display_format;
+        if encoding != OutputEncoding::Plain && (f.red_bits != 8 || f.green_bits != 8 || f.blue_bits != 8) {
+            return Err(ProcessingErr::EncodingNeeds8BitDisplay(f.red_bits, f.green_bits, f.blue_bits));
+        }
+        self.output_encoding = encoding;
+
+        Ok(())
+    }
+
+	/// Read back what the last screen.reveal() actually wrote to the monitor, after the
+	/// gamma table and the output encoding, as 8-bit RGBA row by row from the top left.
+	/// This works for headless screens as well, so that the output can be checked with
+	/// OutputEncoding::decode() without any hardware.
+    pub fn read_display(&self) -> Vec<[u8; 4]> {
+        let img: glium::texture::RawImage2d<u8> = match self.display {
+            ScreenType::Window(ref d) => d.read_front_buffer(),
+            ScreenType::Headless(ref d) => d.read_front_buffer(),
+        };
+
+        // OpenGL starts at the bottom row
+        let mut pixels = Vec::with_capacity(img.width as usize * img.height as usize);
+        for row in img.data.chunks(img.width as usize * 4).rev() {
+            pixels.extend(row.chunks(4).map(|p| [p[0], p[1], p[2], p[3]]));
+        }
+
+        pixels
+    }
+
+	/// The sampled gamma table for the final pass of screen.reveal().
+    pub fn gamma_table_sampler<'s>(&'s self) -> glium::uniforms::Sampler<'s, Texture1d> {
+        self.gamma_table
+            .sampled()
+            .wrap_function(glium::uniforms::SamplerWrapFunction::Clamp)
+            .minify_filter(glium::uniforms::MinifySamplerFilter::Linear)
+            .magnify_filter(glium::uniforms::MagnifySamplerFilter::Linear)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[ignore]
+    #[cfg(not(feature = "glfw"))]
+    fn read_display_round_trip() {
+        // this needs an OpenGL context, so run it with `cargo test -- --ignored` on a
+        // machine that has one
+        use Screen;
+        use super::OutputEncoding;
+
+        let mut screen = Screen::new_headless(8, 2, false).unwrap();
+        let color = [0.25, 0.5, 0.75];
+        let encodings = [
+            (OutputEncoding::Plain, 0.5 / 255.0),
+            (OutputEncoding::MonoPlusPlus, 0.5 / 65535.0),
+            (OutputEncoding::ColorPlusPlus, 0.5 / 65535.0),
+        ];
+        for &(encoding, tolerance) in &encodings {
+            screen.output_encoding(encoding).unwrap();
+            screen.background(color[0], color[1], color[2], 1.0);
+            screen.reveal().unwrap();
+            let decoded = encoding.decode(&screen.read_display());
+            let pixels = if encoding == OutputEncoding::ColorPlusPlus { 8 } else { 16 };
+            assert_eq!(decoded.len(), pixels);
+            for p in decoded {
+                for c in 0..3 {
+                    // Mono++ shows the red channel as gray
+                    let expected = if encoding == OutputEncoding::MonoPlusPlus { color[0] } else { color[c] };
+                    assert!((p[c] - expected).abs() <= tolerance + 1e-6, "{:?}: {:?}", encoding, p);
+                }
+            }
+        }
+    }
+}
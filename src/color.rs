@@ -1,22 +1,27 @@
 use glium::Surface;
 
 use Screen;
+use errors::ProcessingErr;
 
 impl<'a> Screen<'a> {
 	/// Change the background color of the window. It takes four f32's. If color mode
 	/// is equal to "RGB", then it takes one for red, one for green, one for blue, and
 	/// one for alpha. If color mode is equal to "HSB", then the arguments are
 	/// reinterpreted as one for hue, one for saturation, one for brightness, and one
-	/// for alpha.
+	/// for alpha ("HSL" works the same way, but with lightness instead of brightness).
+	/// The values are scaled by the ranges set with color_mode_range().
     #[inline]
     pub fn background(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        let c = self.color(r, g, b, a);
+        // anything that hasn't been drawn yet would be covered up anyway
+        self.batch.clear();
         let framebuffer = &mut self.fbo;
-        framebuffer.clear_color_srgb(r, g, b, a);
+        framebuffer.clear_color_srgb(c.r, c.g, c.b, c.a);
     }
 
-	/// Change the color mode to "RGB" or "HSB". This causes the arguments to fill(),
-	/// stroke(), background(), and color() to be reinterpreted in the respective 
-	/// color system.
+	/// Change the color mode to "RGB", "HSB", or "HSL". This causes the arguments to
+	/// fill(), stroke(), background(), and color() to be reinterpreted in the
+	/// respective color system.
     #[inline]
     pub fn color_mode(&mut self, mode: &str) {
         self.c_mode = mode.to_owned();
@@ -26,24 +31,26 @@ impl<'a> Screen<'a> {
 	/// setting fill to 1, 1, 1, 1 in "RGB" mode will cause the interior of a
 	/// rectangle to be white. The arguments to fill are actually slices of f32's. This
 	/// is meant to be a convenience when you know that you want to draw many of the
-	/// same kind of shape, but each with a different stroke color. Calling this
+	/// same kind of shape, but each with a different fill color: the i-th color goes
+	/// to the i-th shape created by a shape constructor, and a single color is shared by
+	/// all of them. All four slices need to have the same length. Calling this
 	/// function will also undo the effect of fill_off().
     #[inline]
-    pub fn fill(&mut self, r: &[f32], g: &[f32], b: &[f32], a: &[f32]) {
+    pub fn fill(&mut self, r: &[f32], g: &[f32], b: &[f32], a: &[f32]) -> Result<(), ProcessingErr> {
+        check_color_lengths(r, g, b, a)?;
         if self.fill_stuff == false {
             self.fill_stuff = true;
         }
-        if self.c_mode == "RGB" {
-            self.fill_col = vec![];
-            for x in 0..r.len() {
-                // self.fill_col.push(RGB(r[x], g[x], b[x]);
-                self.fill_col.push(r[x]);
-                self.fill_col.push(g[x]);
-                self.fill_col.push(b[x]);
-                self.fill_col.push(a[x]);
-            }
-        } else {
+        self.fill_col = vec![];
+        for x in 0..r.len() {
+            let c = self.color(r[x], g[x], b[x], a[x]);
+            self.fill_col.push(c.r);
+            self.fill_col.push(c.g);
+            self.fill_col.push(c.b);
+            self.fill_col.push(c.a);
         }
+
+        Ok(())
     }
 
 	/// This disables filling in of shapes, such that only their outline is drawn. It
@@ -82,73 +89,341 @@ impl<'a> Screen<'a> {
 	/// setting stroke to 1, 1, 1, 1 in "RGB" mode will cause the edge of a
 	/// rectangle to be white. The arguments to stroke are actually slices of f32's.
 	/// This is meant to be a convenience when you know that you want to draw many of
-	/// the same kind of shape, but each with a different edge color. Calling this
-	/// function will also undo the effect of stroke_off().
+	/// the same kind of shape, but each with a different edge color, in the same way as
+	/// for fill(). Calling this function will also undo the effect of stroke_off().
     #[inline]
-    pub fn stroke(&mut self, r: &[f32], g: &[f32], b: &[f32], a: &[f32]) {
+    pub fn stroke(&mut self, r: &[f32], g: &[f32], b: &[f32], a: &[f32]) -> Result<(), ProcessingErr> {
+        check_color_lengths(r, g, b, a)?;
         if self.stroke_stuff == false {
             self.stroke_stuff = true;
         }
-        if self.c_mode == "RGB" {
-            self.stroke_col = vec![];
-            for x in 0..r.len() {
-                // self.stroke_col.push(RGB(r[x], g[x], b[x]);
-                self.stroke_col.push(r[x]);
-                self.stroke_col.push(g[x]);
-                self.stroke_col.push(b[x]);
-                self.stroke_col.push(a[x]);
-            }
-        } else {
+        self.stroke_col = vec![];
+        for x in 0..r.len() {
+            let c = self.color(r[x], g[x], b[x], a[x]);
+            self.stroke_col.push(c.r);
+            self.stroke_col.push(c.g);
+            self.stroke_col.push(c.b);
+            self.stroke_col.push(c.a);
         }
+
+        Ok(())
     }
 
     // Creating & Reading
 
-    // #[inline]
-    // pub fn alpha(c: Color) {
-    //     c.a
-    // }
-    //
-    // #[inline]
-    // pub fn blue(c: Color) {
-    //     c.b
-    // }
-    //
-    // #[inline]
-    // pub fn brightness(c: Color) {
-    //     hsv = convert(HSV, c);
-    //     hsv.v
-    // }
-    //
-    // #[inline]
-    // pub fn color(r: f32, g: f32, b: f32) {
-    //     RGB(r, g, b)
-    // }
-    //
-    // #[inline]
-    // pub fn green(c: Color) {
-    //     c.g;
-    // }
-    //
-    // #[inline]
-    // pub fn hue(c: Color) {
-    //     hsv = convert(HSV, c);
-    //     hsv.h
-    // }
-    //
-    // #[inline]
-    // pub fn lerpColor(c1: Color, c2: Color, amt: f64) {
-    //     weighted_color_mean(amt, c1, c2);
-    // }
-    //
-    // #[inline]
-    // pub fn red(c: Color) {
-    //     c.r
-    // }
-    //
-    // #[inline]
-    // pub fn saturation(c: Color) {
-    //     hsv = convert(HSV, c);
-    //     hsv.s
-    // }
+	/// The same as color_mode(), but also sets the range of each of the color channels,
+	/// as with Processing's colorMode(HSB, 360, 100, 100). For example, after calling
+	/// color_mode_range("HSB", 360.0, 100.0, 100.0, 1.0), fill(&[120.0], &[100.0],
+	/// &[50.0], &[1.0]) gives a half-bright green. The ranges are kept when the mode is
+	/// changed again with color_mode(). By default, every channel runs from 0 to 1.
+    #[inline]
+    pub fn color_mode_range(&mut self, mode: &str, max1: f32, max2: f32, max3: f32, max_a: f32) {
+        self.c_mode = mode.to_owned();
+        self.color_ranges = [max1, max2, max3, max_a];
+    }
+
+	/// Create a Color from the given values, which are interpreted according to the
+	/// current color mode and ranges, just like the arguments to fill().
+    #[inline]
+    pub fn color(&self, c1: f32, c2: f32, c3: f32, a: f32) -> Color {
+        let v1 = c1 / self.color_ranges[0];
+        let v2 = c2 / self.color_ranges[1];
+        let v3 = c3 / self.color_ranges[2];
+        let a = a / self.color_ranges[3];
+        if self.c_mode == "HSB" {
+            Color::hsb(v1, v2, v3, a)
+        } else if self.c_mode == "HSL" {
+            Color::hsl(v1, v2, v3, a)
+        } else {
+            Color::rgb(v1, v2, v3, a)
+        }
+    }
+
+	/// Use the given Color to fill in shapes. This is the same as calling fill() with
+	/// the components of the color, but it doesn't depend on the current color mode.
+    #[inline]
+    pub fn fill_color(&mut self, c: &Color) {
+        self.fill_stuff = true;
+        self.fill_col = vec![c.r, c.g, c.b, c.a];
+    }
+
+	/// Use the given Color to draw the edges of shapes.
+    #[inline]
+    pub fn stroke_color(&mut self, c: &Color) {
+        self.stroke_stuff = true;
+        self.stroke_col = vec![c.r, c.g, c.b, c.a];
+    }
+
+	/// Erase the screen with the given Color.
+    #[inline]
+    pub fn background_color(&mut self, c: &Color) {
+        self.batch.clear();
+        let framebuffer = &mut self.fbo;
+        framebuffer.clear_color_srgb(c.r, c.g, c.b, c.a);
+    }
+
+	/// The red component of a color, in the current red range.
+    #[inline]
+    pub fn red(&self, c: &Color) -> f32 {
+        c.r * self.color_ranges[0]
+    }
+
+	/// The green component of a color, in the current green range.
+    #[inline]
+    pub fn green(&self, c: &Color) -> f32 {
+        c.g * self.color_ranges[1]
+    }
+
+	/// The blue component of a color, in the current blue range.
+    #[inline]
+    pub fn blue(&self, c: &Color) -> f32 {
+        c.b * self.color_ranges[2]
+    }
+
+	/// The alpha component of a color, in the current alpha range.
+    #[inline]
+    pub fn alpha(&self, c: &Color) -> f32 {
+        c.a * self.color_ranges[3]
+    }
+
+	/// The hue of a color, in the current hue range.
+    #[inline]
+    pub fn hue(&self, c: &Color) -> f32 {
+        c.to_hsb()[0] * self.color_ranges[0]
+    }
+
+	/// The saturation of a color (as used by the HSB color mode), in the current
+	/// saturation range.
+    #[inline]
+    pub fn saturation(&self, c: &Color) -> f32 {
+        c.to_hsb()[1] * self.color_ranges[1]
+    }
+
+	/// The brightness of a color, in the current brightness range.
+    #[inline]
+    pub fn brightness(&self, c: &Color) -> f32 {
+        c.to_hsb()[2] * self.color_ranges[2]
+    }
+
+	/// The lightness of a color (as used by the HSL color mode), in the current
+	/// lightness range.
+    #[inline]
+    pub fn lightness(&self, c: &Color) -> f32 {
+        c.to_hsl()[2] * self.color_ranges[2]
+    }
+
+	/// Mix two colors. An amount of 0 gives c1, 1 gives c2, and anything in between
+	/// gives a blend of the two. As in Processing, the mixing happens in the current
+	/// color mode, so colors mixed in "HSB" mode pass through the hues in between.
+    #[inline]
+    pub fn lerp_color(&self, c1: &Color, c2: &Color, amt: f32) -> Color {
+        let amt = amt.max(0.0).min(1.0);
+        let lerp = |a: f32, b: f32| a + (b - a) * amt;
+        let a = lerp(c1.a, c2.a);
+        if self.c_mode == "HSB" {
+            let (h1, h2) = (c1.to_hsb(), c2.to_hsb());
+            Color::hsb(lerp(h1[0], h2[0]), lerp(h1[1], h2[1]), lerp(h1[2], h2[2]), a)
+        } else if self.c_mode == "HSL" {
+            let (h1, h2) = (c1.to_hsl(), c2.to_hsl());
+            Color::hsl(lerp(h1[0], h2[0]), lerp(h1[1], h2[1]), lerp(h1[2], h2[2]), a)
+        } else {
+            Color::rgb(lerp(c1.r, c2.r), lerp(c1.g, c2.g), lerp(c1.b, c2.b), a)
+        }
+    }
+}
+
+/// A color, stored as red, green, blue, and alpha components that each run from 0 to
+/// 1. Use screen.color() to create one according to the current color mode, or the
+/// constructors below to create one in a specific color system.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+	/// Create a color from its red, green, blue, and alpha components.
+    pub fn rgb(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color { r: r, g: g, b: b, a: a }
+    }
+
+	/// Create a color from its hue, saturation, brightness (also known as value), and
+	/// alpha. All of them run from 0 to 1, so a hue of 1 is the same as a hue of 0.
+    pub fn hsb(h: f32, s: f32, v: f32, a: f32) -> Self {
+        let h = (h - h.floor()) * 6.0;
+        let c = v * s;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = hue_sector(h, c, x);
+        let m = v - c;
+        Color::rgb(r + m, g + m, b + m, a)
+    }
+
+	/// Create a color from its hue, saturation, lightness, and alpha, which all run
+	/// from 0 to 1.
+    pub fn hsl(h: f32, s: f32, l: f32, a: f32) -> Self {
+        let h = (h - h.floor()) * 6.0;
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = hue_sector(h, c, x);
+        let m = l - c / 2.0;
+        Color::rgb(r + m, g + m, b + m, a)
+    }
+
+	/// The hue, saturation, and brightness of this color, each from 0 to 1.
+    pub fn to_hsb(&self) -> [f32; 3] {
+        let (h, max, min) = self.hue_max_min();
+        let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+        [h, s, max]
+    }
+
+	/// The hue, saturation, and lightness of this color, each from 0 to 1.
+    pub fn to_hsl(&self) -> [f32; 3] {
+        let (h, max, min) = self.hue_max_min();
+        let l = (max + min) / 2.0;
+        let s = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+        };
+        [h, s, l]
+    }
+
+    fn hue_max_min(&self) -> (f32, f32, f32) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let d = max - min;
+        let h = if d == 0.0 {
+            0.0
+        } else if max == self.r {
+            ((self.g - self.b) / d) % 6.0
+        } else if max == self.g {
+            (self.b - self.r) / d + 2.0
+        } else {
+            (self.r - self.g) / d + 4.0
+        };
+        let h = h / 6.0;
+        (if h < 0.0 { h + 1.0 } else { h }, max, min)
+    }
+}
+
+// every channel needs one value per color and there has to be at least one color
+fn check_color_lengths(r: &[f32], g: &[f32], b: &[f32], a: &[f32]) -> Result<(), ProcessingErr> {
+    for l in &[g.len(), b.len(), a.len()] {
+        if *l != r.len() {
+            return Err(ProcessingErr::LengthMismatch(r.len(), *l));
+        }
+    }
+    if r.len() == 0 {
+        return Err(ProcessingErr::NoColors);
+    }
+
+    Ok(())
+}
+
+fn hue_sector(h: f32, c: f32, x: f32) -> (f32, f32, f32) {
+    if h < 1.0 {
+        (c, x, 0.0)
+    } else if h < 2.0 {
+        (x, c, 0.0)
+    } else if h < 3.0 {
+        (0.0, c, x)
+    } else if h < 4.0 {
+        (0.0, x, c)
+    } else if h < 5.0 {
+        (x, 0.0, c)
+    } else {
+        (c, 0.0, x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Color;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_close(c: Color, r: f32, g: f32, b: f32) {
+        assert!(
+            (c.r - r).abs() < EPSILON && (c.g - g).abs() < EPSILON && (c.b - b).abs() < EPSILON,
+            "{:?} is not ({}, {}, {})",
+            c,
+            r,
+            g,
+            b
+        );
+    }
+
+    // a grid over the whole RGB cube, including grays, black, and white
+    fn rgb_cube() -> Vec<Color> {
+        let steps = [0.0, 0.1, 0.25, 0.5, 0.75, 0.9, 1.0];
+        let mut colors = vec![];
+        for &r in &steps {
+            for &g in &steps {
+                for &b in &steps {
+                    colors.push(Color::rgb(r, g, b, 1.0));
+                }
+            }
+        }
+        colors
+    }
+
+    #[test]
+    fn primaries() {
+        assert_close(Color::hsb(0.0, 1.0, 1.0, 1.0), 1.0, 0.0, 0.0);
+        assert_close(Color::hsb(1.0 / 3.0, 1.0, 1.0, 1.0), 0.0, 1.0, 0.0);
+        assert_close(Color::hsb(2.0 / 3.0, 1.0, 1.0, 1.0), 0.0, 0.0, 1.0);
+        assert_close(Color::hsb(1.0 / 6.0, 1.0, 1.0, 1.0), 1.0, 1.0, 0.0);
+        assert_close(Color::hsb(0.5, 0.0, 0.5, 1.0), 0.5, 0.5, 0.5);
+        assert_close(Color::hsl(0.0, 1.0, 0.5, 1.0), 1.0, 0.0, 0.0);
+        assert_close(Color::hsl(0.5, 1.0, 0.5, 1.0), 0.0, 1.0, 1.0);
+        assert_close(Color::hsl(0.3, 1.0, 1.0, 1.0), 1.0, 1.0, 1.0);
+        assert_close(Color::hsl(0.3, 1.0, 0.0, 1.0), 0.0, 0.0, 0.0);
+    }
+
+    #[test]
+    fn hue_wraps_around() {
+        for &h in &[0.0, 0.2, 0.45, 0.8] {
+            let c = Color::hsb(h, 0.7, 0.9, 1.0);
+            let wrapped = Color::hsb(h + 1.0, 0.7, 0.9, 1.0);
+            assert_close(wrapped, c.r, c.g, c.b);
+            let wrapped = Color::hsb(h - 1.0, 0.7, 0.9, 1.0);
+            assert_close(wrapped, c.r, c.g, c.b);
+        }
+    }
+
+    #[test]
+    fn hsb_round_trip() {
+        for c in rgb_cube() {
+            let hsb = c.to_hsb();
+            for &x in &hsb {
+                assert!(x >= 0.0 && x <= 1.0, "{:?} of {:?} is out of range", hsb, c);
+            }
+            assert_close(Color::hsb(hsb[0], hsb[1], hsb[2], 1.0), c.r, c.g, c.b);
+        }
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        for c in rgb_cube() {
+            let hsl = c.to_hsl();
+            for &x in &hsl {
+                assert!(x >= 0.0 && x <= 1.0, "{:?} of {:?} is out of range", hsl, c);
+            }
+            assert_close(Color::hsl(hsl[0], hsl[1], hsl[2], 1.0), c.r, c.g, c.b);
+        }
+    }
+
+    #[test]
+    fn hsb_and_hsl_agree() {
+        // full saturation and brightness in HSB is full saturation and half lightness
+        // in HSL, and both systems share the same hue
+        for i in 0..12 {
+            let h = i as f32 / 12.0;
+            let c = Color::hsb(h, 1.0, 1.0, 1.0);
+            assert_close(Color::hsl(h, 1.0, 0.5, 1.0), c.r, c.g, c.b);
+            assert!((c.to_hsb()[0] - c.to_hsl()[0]).abs() < EPSILON);
+        }
+    }
 }
@@ -81,13 +81,21 @@ impl<'a> Screen<'a> {
         self.frame_count
     }
 
-	/// What is the current framerate of the screen.
+	/// What framerate the screen is trying to run at, as set by screen.set_frame_rate().
+	/// See screen.measured_frame_rate() for the one it actually runs at.
     #[inline]
     pub fn get_frame_rate(&self) -> isize {
         self.frame_rate
     }
 
-	/// Change the framerate of the screen.
+	/// Change the framerate of the screen. From now on, screen.reveal() waits as long
+	/// as it takes to show at most that many frames per second. If the screen is
+	/// synchronized to the monitor and the framerate divides its refresh rate, every
+	/// frame is shown for the same number of refreshes, e.g., for two at 30 fps on a
+	/// 60 Hz monitor. A framerate of 0 or less shows frames as fast as possible, which
+	/// is the default: a screen that is synchronized to the monitor then shows one
+	/// frame per refresh, while headless screens and windows without vsync are not
+	/// held back at all.
     #[inline]
     pub fn set_frame_rate(&mut self, f_rate: isize) {
         self.frame_rate = f_rate;
@@ -7,9 +7,12 @@ use glium::IncompatibleOpenGl;
 use glium::glutin::ContextError;
 use glium::glutin::CreationError;
 use glium::DrawError;
+use glium::buffer::ReadError;
 use glium::SwapBuffersError;
 use glium::program::ProgramCreationError;
 use image_ext::ImageError;
+use rusttype;
+use rusttype::gpu_cache::{CacheReadErr, CacheWriteErr};
 
 use std::io;
 use std::fmt;
@@ -41,7 +44,35 @@ pub enum ProcessingErr {
 	HeadlessNoCreate(IncompatibleOpenGl),
 	GLFWWindowNoCreate,
 	GLFWAlreadyInited,
-	GLFWInternal
+	GLFWInternal,
+	FontNotFound(io::Error),
+	FontNotParsed(rusttype::Error),
+	NoFontAvailable,
+	GlyphNotCached(CacheWriteErr),
+	GlyphNotRead(CacheReadErr),
+	ShapeNotBegun,
+	UnknownShapeKind(isize),
+	ColorCountMismatch(usize, usize),
+	NoColors,
+	LengthMismatch(usize, usize),
+	InstancingNotSupported,
+	TooManyLights(usize),
+	CameraAlreadyBegun,
+	CameraNotBegun,
+	CameraNotInvertible,
+	ModelNotFound(io::Error),
+	ErrorReadingModel(usize, io::Error),
+	MaterialNotFound(io::Error),
+	ErrorReadingMaterial(usize, io::Error),
+	PixelsNotLoaded,
+	FrameNotRead(ReadError),
+	VideoNotStarted(io::Error),
+	GammaTableSize(usize),
+	InvalidGamma(f32),
+	EncodingNeeds8BitDisplay(u32, u32, u32),
+	CalibrationNotFound(io::Error),
+	ErrorReadingCalibration(usize, io::Error),
+	FlipLogNotSaved(io::Error)
 }
 
 #[derive(Debug)]
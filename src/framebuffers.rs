@@ -4,6 +4,8 @@ use glium::uniforms::Uniforms;
 
 use shapes::{Shape, IndexType};
 use shapes::mould::Mould;
+use shaders::{TintedUniforms, STROKE_SHADER, LIT_SHADER, LIT_TEXTURED_SHADER};
+use lights::LightUniforms;
 use {Screen, ScreenType};
 use errors::ProcessingErr;
 
@@ -60,9 +62,19 @@ impl<'a> Screen<'a> {
             smooth: None,
             ..Default::default()
         };
+        let lit = !self.lights.is_empty() && shape.has_normals();
+        let lights = if lit { &self.lights[..] } else { &[] };
+        let (cap, join) = self.stroke_style();
+        let (w, h) = framebuffer.get_dimensions();
+        let stroke_prog = &self.shader_bank[STROKE_SHADER];
+        let stroke_u = create_uniforms!{self, viewport: [w as f32, h as f32],
+            strokeWeight: self.stroke_weight, strokeCap: cap, strokeJoin: join};
         if let Some(tex) = shape.get_texture() {
-            let prog = &self.shader_bank[1];
-            let u = create_uniforms!{self, tex: *tex};
+            let prog = &self.shader_bank[if lit { LIT_TEXTURED_SHADER } else { 1 }];
+            let tint = if self.tint_stuff { self.tint_col } else { [1.0; 4] };
+            let sampling = self.texture_sampling_of(shape);
+            let u = create_uniforms!{self, tex: sampling.sampler(*tex), tint: tint};
+            let u = LightUniforms::new(&u, lights, &self.material);
             if self.fill_stuff {
                 match *shape.fill_indices() {
                     &IndexType::Buffer { ind: ref ib } => {
@@ -84,12 +96,17 @@ impl<'a> Screen<'a> {
                             .draw(*shape.stroke_buffer(), ib, prog, &u, &t)
                             .map_err(|e| ProcessingErr::FBDrawFailed(e))?
                     }
-                    _ => {}
+                    &IndexType::Buffer { ind: ref ib } => {
+                        framebuffer
+                            .draw(*shape.stroke_buffer(), ib, stroke_prog, &stroke_u, &t)
+                            .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                    }
                 }
             };
         } else {
-            let prog = &self.shader_bank[0];
+            let prog = &self.shader_bank[if lit { LIT_SHADER } else { 0 }];
             let u = create_uniforms!{self};
+            let u = LightUniforms::new(&u, lights, &self.material);
             if self.fill_stuff {
                 match *shape.fill_indices() {
                     &IndexType::Buffer { ind: ref ib } => {
@@ -111,7 +128,11 @@ impl<'a> Screen<'a> {
                             .draw(*shape.stroke_buffer(), ib, prog, &u, &t)
                             .map_err(|e| ProcessingErr::FBDrawFailed(e))?
                     }
-                    _ => {}
+                    &IndexType::Buffer { ind: ref ib } => {
+                        framebuffer
+                            .draw(*shape.stroke_buffer(), ib, stroke_prog, &stroke_u, &t)
+                            .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                    }
                 }
             };
         }
@@ -134,7 +155,8 @@ impl<'a> Screen<'a> {
         let shader = mould.get_shader();
         let shape = mould.get_shape();
         let prog = &self.shader_bank[shader.get_idx()];
-        let uniforms = shader.get_uniforms();
+        let tint = if self.tint_stuff { self.tint_col } else { [1.0; 4] };
+        let uniforms = &TintedUniforms::new(shader.get_uniforms(), tint);
         // let t = glium::draw_parameters::DrawParameters {
         //     depth: glium::Depth {
         //         write: false,
@@ -184,7 +206,11 @@ impl<'a> Screen<'a> {
                         )
                         .map_err(|e| ProcessingErr::FBDrawFailed(e))?
                 }
-                _ => {}
+                &IndexType::Buffer { ind: ref ib } => {
+                    framebuffer
+                        .draw(*shape.stroke_buffer(), ib, &prog, uniforms, &Default::default())
+                        .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                }
             }
         };
         
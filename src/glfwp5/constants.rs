@@ -7,13 +7,15 @@ use glfw;
 ///! with some slight adjustments
 
 // shapes
-// pub const POINTS = GL_POINTS;
-// pub const LINES = GL_LINES;
-// pub const TRIANGLES = GL_TRIANGLES;
-// pub const TRIANGLE_FAN = GL_TRIANGLE_FAN;
-// pub const TRIANGLE_STRIP = GL_TRIANGLE_STRIP;
-// pub const QUADS = GL_QUADS;
-// pub const QUAD_STRIP = GL_QUAD_STRIP;
+pub const POLYGON: isize = 0;
+pub const POINTS: isize = 1;
+pub const LINES: isize = 2;
+pub const TRIANGLES: isize = 3;
+pub const TRIANGLE_FAN: isize = 4;
+pub const TRIANGLE_STRIP: isize = 5;
+pub const QUADS: isize = 6;
+pub const QUAD_STRIP: isize = 7;
+pub const OPEN: isize = 0;
 pub const CLOSE: isize = 1;
 
 /// Perlin noise table. Use it as a precomputed source of noise that emulates the
@@ -21,6 +21,13 @@ use Matrix4;
 use {Screen, GLmatStruct, FBtexs, DFBFDVertex};
 use ScreenType;
 use errors::ProcessingErr;
+use text::default_font_face;
+use shapes::immediate::Batch;
+use lights::Material;
+use shaders::{init_stroke_shader, init_instanced_shaders, init_lit_shaders, init_batch_shader};
+use textures::{ScreenFormat, TextureFormat, empty_texture_with_format, granted_display_format};
+use calibration::identity_gamma_table;
+use timing::{FlipInfo, FlipTimer};
 
 #[cfg(target_os = "macos")]
 use mac_priority;
@@ -63,6 +70,21 @@ impl<'a> Screen<'a> {
         fullscreen: bool,
         preserve_aspect_ratio: bool,
         headless: bool
+    ) -> Result<Screen<'a>, ProcessingErr> {
+        Screen::new_with_format(width, height, glfw, fullscreen, preserve_aspect_ratio, headless, ScreenFormat::default())
+    }
+
+	/// The same as Screen::new(), except that you also choose the formats of the
+	/// screen's framebuffers (see ScreenFormat). glfw treats the display format as a
+	/// hint, so check screen.display_format() for what you actually got.
+    pub fn new_with_format(
+        width: u32,
+        height: u32,
+        mut glfw: glfw::Glfw,
+        fullscreen: bool,
+        preserve_aspect_ratio: bool,
+        headless: bool,
+        format: ScreenFormat,
     ) -> Result<Screen<'a>, ProcessingErr> {
         #[cfg(target_os = "macos")] mac_priority();
 
@@ -74,11 +96,13 @@ impl<'a> Screen<'a> {
             glfw::OpenGlProfileHint::Core,
         ));
 
-        // try to activate 10 bpc support
-        glfw.window_hint(glfw::WindowHint::RedBits(Some(10)));
-        glfw.window_hint(glfw::WindowHint::GreenBits(Some(10)));
-        glfw.window_hint(glfw::WindowHint::BlueBits(Some(10)));
-        glfw.window_hint(glfw::WindowHint::AlphaBits(Some(2)));
+        // try to activate 10 bpc support, unless asked for something else
+        let (color_bits, alpha_bits) = format.display.unwrap_or(TextureFormat::RGB10A2).pixel_format();
+        glfw.window_hint(glfw::WindowHint::RedBits(Some(color_bits as u32 / 3)));
+        glfw.window_hint(glfw::WindowHint::GreenBits(Some(color_bits as u32 / 3)));
+        glfw.window_hint(glfw::WindowHint::BlueBits(Some(color_bits as u32 / 3)));
+        glfw.window_hint(glfw::WindowHint::AlphaBits(Some(alpha_bits as u32)));
+        glfw.window_hint(glfw::WindowHint::SRgbCapable(format.display == Some(TextureFormat::SRGB8A8)));
 
         // anti-aliasing by default
         glfw.window_hint(glfw::WindowHint::Samples(Some(0)));
@@ -116,6 +140,12 @@ impl<'a> Screen<'a> {
             events_loop = e;
         }
 
+        // the refresh rate of the monitor, which the window goes full screen on or is
+        // most likely shown on
+        let refresh_rate = glfw.with_primary_monitor(|_: &mut _, m: Option<&glfw::Monitor>| {
+            m.and_then(|m| m.get_video_mode()).map(|mode| mode.refresh_rate as f64)
+        });
+
         // let frame_rate = 0;
         // if frame_rate == 0 {
         glfw.window_hint(glfw::WindowHint::RefreshRate(Some(60)));
@@ -135,6 +165,7 @@ impl<'a> Screen<'a> {
         gl::load_with(|symbol| {
             (*display.gl_window_mut()).get_proc_address(symbol) as *const _
         });
+        let display_format = granted_display_format();
 
         let mut glsl_version;
         {
@@ -166,13 +197,14 @@ impl<'a> Screen<'a> {
 
         let aspect_ratio = w as f32 / h as f32;
 
-        let fb_texture = glium::texture::Texture2d::empty_with_format(
+        let gamma_table = identity_gamma_table(&display)?;
+        let fb_texture = empty_texture_with_format(
             &display,
-            glium::texture::UncompressedFloatFormat::F32F32F32F32,
+            format.framebuffer,
             glium::texture::MipmapsOption::NoMipmap,
             w,
             h,
-        ).map_err(|e| ProcessingErr::TextureNoCreate(e))?;
+        )?;
         let fbid = fb_texture.get_id();
         let depthtexture = glium::texture::DepthTexture2d::empty_with_format(
             &display,
@@ -190,18 +222,19 @@ impl<'a> Screen<'a> {
                 Box::new(
                     glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(
                         &display,
-                        &(*v).fbtex,
+                        &*(*v).fbtex,
                         &(*v).depthtexture,
                     ).expect("Could not create a SimpleFrameBuffer with attached DepthBuffer. Please check your graphics card, drivers, and OS."),
                 )
             },
         );
+        // a view of the texture of the framebuffer, which FBtexs owns
         let fbtexture = unsafe {
             glium::texture::Texture2d::from_id(
                 &display,
-                glium::texture::UncompressedFloatFormat::F32F32F32F32,
+                format.framebuffer.glium_format(),
                 fbid,
-                true,
+                false,
                 glium::texture::MipmapsOption::NoMipmap,
                 glium::texture::Dimensions::Texture2d {
                     width: w,
@@ -246,14 +279,7 @@ impl<'a> Screen<'a> {
         // glCheckError("Screen initilization.");
 
         // by default, use system fonts that are known to basically always be available
-        let mut font_face = "".to_owned();
-        if cfg!(target_os = "windows") {
-            font_face = "C:/Windows/Fonts/arial.ttf".to_owned();
-        } else if cfg!(target_os = "linux") {
-            font_face = "/usr/share/fonts/ttf-dejavu-ib/DejaVuSansMono.ttf".to_owned();
-        } else if cfg!(target_os = "macos") {
-            font_face = "/Users/rje/Library/Fonts/Go-Regular.ttf".to_owned();
-        }
+        let font_face = default_font_face();
 
         let shader_bank = init_shaders(&display, &glsl_version)?;
 
@@ -333,6 +359,9 @@ impl<'a> Screen<'a> {
                 );
                     1
                 ],
+                view_matrix: Matrix4::identity(),
+                projection_matrix: Matrix4::identity(),
+                camera_saved: None,
             },
             fbo: oh,
             fbtexture: fbtexture,
@@ -348,15 +377,22 @@ impl<'a> Screen<'a> {
             stroke_stuff: true,
             stroke_col: vec![0.0f32, 0.0, 0.0, 1.0],
             tint_stuff: false,
-            tint_col: vec![1.0f32, 1.0, 1.0, 1.0],
+            tint_col: [1.0f32, 1.0, 1.0, 1.0],
+            lights: Vec::new(),
+            light_falloff: [1.0, 0.0, 0.0],
+            light_specular: [0.0; 3],
+            material: Material::default(),
             shader_bank: shader_bank,
             draw_texture: false,
             aspect_ratio: aspect_ratio,
             preserve_aspect_ratio: preserve_aspect_ratio,
             fb_size: fb_size,
             stroke_weight: 1.0,
+            stroke_cap: "ROUND".to_owned(),
+            stroke_join: "MITER".to_owned(),
+            dynamic_shapes: false,
             font_face: font_face,
-            text_size: 0.4,
+            text_size: 12.0,
             height: height,
             width: width,
             left: -1f32,
@@ -364,17 +400,36 @@ impl<'a> Screen<'a> {
             top: 1f32,
             bottom: -1f32,
             c_mode: "RGB".to_owned(),
+            color_ranges: [1.0, 1.0, 1.0, 1.0],
             title: "processing-rs".to_owned(),
             ellipse_mode: "CENTER".to_owned(),
             rect_mode: "CORNER".to_owned(),
             shape_mode: "CORNER".to_owned(),
             image_mode: "CORNER".to_owned(),
-            frame_rate: 60,
+            frame_rate: 0,
             frame_count: 0,
             fonts_initialized: false,
+            font_state: None,
+            batch: Batch::new(),
+            shape_builder: None,
+            curve_tightness: 0.0,
+            bezier_detail: 20,
+            curve_detail: 20,
+            sphere_detail: (30, 30),
             curr_shader: 0,
             curr_cursor: glfw::StandardCursor::Arrow,
-            wrap: glium::uniforms::SamplerWrapFunction::Repeat,
+            texture_sampling: Default::default(),
+            texture_format: TextureFormat::default(),
+            fb_format: format.framebuffer,
+            display_format: display_format,
+            pixels: Vec::new(),
+            frame_writer: None,
+            recording: None,
+            video_encoder: None,
+            gamma_table: gamma_table,
+            gamma_correction: false,
+            output_encoding: Default::default(),
+            flip_timer: FlipTimer::new(true, refresh_rate),
             curr_texture: None,
             alternate_shader: 1 << 20,
             using_alternate_shader: false,
@@ -394,15 +449,21 @@ impl<'a> Screen<'a> {
 	/// because `processing-rs` uses double-buffering, whereby all of the drawing 
 	/// happens on a separate, hidden buffer and once that is done, it is transferred
 	/// to a viewable, monitor buffer. This is standard practice in graphics programming,
-	/// since it makes drawing faster and reduces screen tearing.
+	/// since it makes drawing faster and reduces screen tearing. It returns a FlipInfo
+	/// that tells you when the frame was shown and whether any frames were missed.
     #[inline]
-    pub fn reveal(&mut self) -> Result<(), ProcessingErr> {
+    pub fn reveal(&mut self) -> Result<FlipInfo, ProcessingErr> {
+        self.flush()?;
+        self.record_frame()?;
         let mut target = match self.display {
             ScreenType::Window(ref d) => d.draw(),
             ScreenType::Headless(ref d) => d.draw(),
         };
         {
-            let uniforms = uniform! { texFramebuffer: &self.fbtexture };
+            let srgb = self.fb_format == TextureFormat::SRGB8A8;
+            let uniforms = uniform! { texFramebuffer: &self.fbtexture, srgbFramebuffer: srgb,
+                gammaCorrection: self.gamma_correction, gammaTable: self.gamma_table_sampler(),
+                outputEncoding: self.output_encoding as i32 };
             let p = &self.shader_bank[3];
             target
                 .draw(
@@ -413,8 +474,10 @@ impl<'a> Screen<'a> {
                     &Default::default(),
                 )
                 .map_err(|e| ProcessingErr::DrawFailed(e))?;
-            target.finish().map_err(|e| ProcessingErr::SwapFailed(e))?;
         }
+        self.pace_frame();
+        target.finish().map_err(|e| ProcessingErr::SwapFailed(e))?;
+        let flip = self.record_flip();
 
         let mut kp = None;
         let mut mp = None;
@@ -538,7 +601,7 @@ impl<'a> Screen<'a> {
 
         self.frame_count += 1;
         
-        Ok(())
+        Ok(flip)
     }
 
 	/// This will safely close a window and drop the Screen struct associated with it.
@@ -654,10 +717,11 @@ pub fn init_shaders(display: &Display, glsl_version: &str) -> Result<Vec<glium::
 
     uniform \
                  sampler2D tex;
+    uniform vec4 tint;
 
     void main() {
         outColor = texture(tex, Texcoord) * \
-                 vColor;
+                 vColor * tint;
     }
     ";
 
@@ -688,11 +752,11 @@ pub fn init_shaders(display: &Display, glsl_version: &str) -> Result<Vec<glium::
     out vec2 TexCoord;
 
     \
-                 uniform mat4 proj;
+                 uniform mat4 MVP;
 
     void main()
     {
-        gl_Position = proj * \
+        gl_Position = MVP * \
                  vec4(position, 0.0, 1.0);
         TexCoord = texcoord;
     }
@@ -709,13 +773,13 @@ pub fn init_shaders(display: &Display, glsl_version: &str) -> Result<Vec<glium::
 
     uniform sampler2D text;
     \
-                 uniform vec3 textColor;
+                 uniform vec4 textColor;
 
     void main()
     {
         vec4 sampled = vec4(1.0, \
                  1.0, 1.0, texture(text, TexCoord).r);
-        color = vec4(textColor, 1.0) * \
+        color = textColor * \
                  sampled;
     }
     ";
@@ -735,26 +799,6 @@ pub fn init_shaders(display: &Display, glsl_version: &str) -> Result<Vec<glium::
     ).map_err(|e| ProcessingErr::ShaderCompileFail(e))?;
     shader_bank.push(fd_program);
 
-    // text is rendered with an orthographic projection
-    // let projection = vec![
-    //     2.0f32 / width as f32,
-    //     0.,
-    //     0.,
-    //     -1.,
-    //     0.,
-    //     2.0 / height as f32,
-    //     0.,
-    //     -1.,
-    //     0.,
-    //     0.,
-    //     -1.,
-    //     0.,
-    //     0.,
-    //     0.,
-    //     0.,
-    //     1.,
-    // ];
-
     // unsafe {
     //     gl::UniformMatrix4fv(
     //         gl::GetUniformLocation(
@@ -797,10 +841,60 @@ pub fn init_shaders(display: &Display, glsl_version: &str) -> Result<Vec<glium::
 
     uniform sampler2D \
                   texFramebuffer;
+    uniform bool srgbFramebuffer;
+    uniform bool gammaCorrection;
+    uniform sampler1D gammaTable;
+    uniform int outputEncoding;
+
+    vec3 framebufferColor(vec2 coord) {
+        vec3 c = texture(texFramebuffer, coord).rgb;
+        // an sRGB framebuffer hands back linear values, but it was written unencoded
+        if (srgbFramebuffer) {
+            c = mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+        }
+        return c;
+    }
+
+    vec3 correctGamma(vec3 c) {
+        // look every channel up in the gamma table, between the centers of its entries
+        if (gammaCorrection) {
+            float n = float(textureSize(gammaTable, 0));
+            vec3 coord = (clamp(c, 0.0, 1.0) * (n - 1.0) + 0.5) / n;
+            c = vec3(texture(gammaTable, coord.r).r, texture(gammaTable, coord.g).g,
+                     texture(gammaTable, coord.b).b);
+        }
+        return c;
+    }
+
+    // split values into the high and low bytes of 16 bits, as they should be written
+    // to an 8-bit framebuffer
+    vec3 highBytes(vec3 c) {
+        return floor(floor(clamp(c, 0.0, 1.0) * 65535.0 + 0.5) / 256.0);
+    }
+
+    vec3 lowBytes(vec3 c) {
+        vec3 n = floor(clamp(c, 0.0, 1.0) * 65535.0 + 0.5);
+        return n - 256.0 * floor(n / 256.0);
+    }
 
     void main() {
-        outColor = texture(texFramebuffer, \
-                  Texcoord);
+        vec4 drawn = texture(texFramebuffer, Texcoord);
+        if (outputEncoding == 1) {
+            // Mono++: the luminance (taken from red) in red and green, no overlay in blue
+            float l = correctGamma(framebufferColor(Texcoord)).r;
+            outColor = vec4(highBytes(vec3(l)).r / 255.0, lowBytes(vec3(l)).r / 255.0, 0.0, 1.0);
+        } else if (outputEncoding == 2) {
+            // Color++: every pair of pixels shows the average of the two, with the high
+            // bytes in the left one and the low bytes in the right one
+            float dx = dFdx(Texcoord.x);
+            bool left = mod(floor(gl_FragCoord.x), 2.0) < 0.5;
+            vec2 first = left ? Texcoord : Texcoord - vec2(dx, 0.0);
+            vec3 c = (framebufferColor(first) + framebufferColor(first + vec2(dx, 0.0))) / 2.0;
+            c = correctGamma(c);
+            outColor = vec4((left ? highBytes(c) : lowBytes(c)) / 255.0, 1.0);
+        } else {
+            outColor = vec4(correctGamma(framebufferColor(Texcoord)), drawn.a);
+        }
     }
     ";
 
@@ -819,5 +913,17 @@ pub fn init_shaders(display: &Display, glsl_version: &str) -> Result<Vec<glium::
     ).map_err(|e| ProcessingErr::ShaderCompileFail(e))?;
     shader_bank.push(dfb_program);
 
+    // thick strokes
+    shader_bank.push(init_stroke_shader(display, glsl_version)?);
+
+    // instanced shapes
+    shader_bank.extend(init_instanced_shaders(display, glsl_version)?);
+
+    // lit shapes
+    shader_bank.extend(init_lit_shaders(display, glsl_version)?);
+
+    // immediate-mode shapes
+    shader_bank.push(init_batch_shader(display, glsl_version)?);
+
     Ok(shader_bank)
 }
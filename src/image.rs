@@ -1,10 +1,16 @@
 // Loading & Displaying
-use std::mem;
+use std::io;
+use std::io::{Write, BufWriter};
+use std::fs::File;
 use std::path::Path;
+use std::borrow::Cow;
 
 use image_ext;
-use gl;
+use image_ext::hdr::HDREncoder;
+use glium::Rect;
+use glium::texture::{RawImage2d, ClientFormat};
 use errors::ProcessingErr;
+use color::Color;
 
 /// A convienence function that will open an image (using the `image` crate) and
 /// transform it to the format that glium expects for textures. Use the output of
@@ -14,6 +20,218 @@ pub fn load_image(filename: &str) -> Result<image_ext::RgbaImage, ProcessingErr>
     image_ext::open(filename).and_then(|img| Ok(img.to_rgba())).map_err(|e| ProcessingErr::ImageNotFound(e))
 }
 
+/// How screen.save_with() and save_pixels() write an image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SaveOptions {
+    /// Bits per channel: 8 for any format, 16 for PNG and TIFF, and 32 (floats) for
+    /// TIFF. Radiance HDR (.hdr), Portable FloatMap (.pfm), and raw (.raw) files are
+    /// always written as floats, whatever this is.
+    pub bit_depth: u8,
+    /// Whether to keep the alpha channel, if the format can hold it. Radiance HDR and
+    /// Portable FloatMap files never have one.
+    pub alpha: bool,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        SaveOptions {
+            bit_depth: 8,
+            alpha: false,
+        }
+    }
+}
+
+/// Write pixels, given as RGBA floats row by row from the top left (as in
+/// screen.pixels()), to an image file whose format is determined by its extension.
+/// Besides everything that the `image` crate can write with 8 bits per channel, this
+/// handles 16-bit PNG, 8-bit, 16-bit, and float TIFF, Radiance HDR, Portable FloatMap,
+/// and raw little-endian floats without any header. Values are clamped to [0, 1],
+/// except in the float formats, which keep them exactly as they are.
+pub fn save_pixels(
+    filename: &str,
+    pixels: &[[f32; 4]],
+    width: u32,
+    height: u32,
+    options: SaveOptions,
+) -> Result<(), ProcessingErr> {
+    write_pixels(Path::new(filename), pixels, width, height, options).map_err(|e| ProcessingErr::ImageNotSaved(e))
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map_or("".to_owned(), |s| s.to_lowercase())
+}
+
+/// Whether write_pixels() needs more than 8 bits per channel to write an image to the
+/// given path with the given options.
+pub fn keeps_floats(path: &Path, options: SaveOptions) -> bool {
+    match &*extension(path) {
+        "hdr" | "pfm" | "raw" => true,
+        _ => options.bit_depth != 8,
+    }
+}
+
+/// The same as save_pixels(), but with errors that can be sent between threads.
+pub fn write_pixels(path: &Path, pixels: &[[f32; 4]], width: u32, height: u32, options: SaveOptions) -> io::Result<()> {
+    let ext = extension(path);
+    let channels = if options.alpha { 4 } else { 3 };
+    let create = || File::create(path).map(BufWriter::new);
+
+    match (&*ext, options.bit_depth) {
+        ("hdr", _) => {
+            let data: Vec<image_ext::Rgb<f32>> = pixels.iter().map(|p| image_ext::Rgb([p[0], p[1], p[2]])).collect();
+            HDREncoder::new(create()?).encode(&data, width as usize, height as usize)
+        }
+        ("pfm", _) => write_pfm(create()?, pixels, width, height),
+        ("raw", _) => write_raw(create()?, pixels, channels),
+        ("tif", 8) | ("tiff", 8) | ("tif", 16) | ("tiff", 16) | ("tif", 32) | ("tiff", 32) => {
+            write_tiff(create()?, pixels, width, height, options.bit_depth, channels)
+        }
+        ("png", 16) => {
+            let mut data = Vec::with_capacity(pixels.len() * channels * 2);
+            for p in pixels {
+                for c in &p[..channels] {
+                    // PNG stores 16-bit samples big-endian
+                    let v = (c.max(0.0).min(1.0) * 65535.0).round() as u16;
+                    data.push((v >> 8) as u8);
+                    data.push(v as u8);
+                }
+            }
+            let color = if options.alpha { image_ext::RGBA(16) } else { image_ext::RGB(16) };
+            image_ext::save_buffer(path, &data, width, height, color)
+        }
+        (_, 8) => {
+            let data = to_u8(pixels, channels);
+            let color = if options.alpha { image_ext::RGBA(8) } else { image_ext::RGB(8) };
+            image_ext::save_buffer(path, &data, width, height, color)
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Cannot save a .{} image with {} bits per channel", ext, options.bit_depth),
+        )),
+    }
+}
+
+fn to_u8(pixels: &[[f32; 4]], channels: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(pixels.len() * channels);
+    for p in pixels {
+        for c in &p[..channels] {
+            data.push((c.max(0.0).min(1.0) * 255.0).round() as u8);
+        }
+    }
+
+    data
+}
+
+fn write_f32s<W: Write>(w: &mut W, values: &[f32]) -> io::Result<()> {
+    for v in values {
+        w.write_all(&v.to_bits().to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn write_raw<W: Write>(mut w: W, pixels: &[[f32; 4]], channels: usize) -> io::Result<()> {
+    for p in pixels {
+        write_f32s(&mut w, &p[..channels])?;
+    }
+
+    w.flush()
+}
+
+// Portable FloatMap: RGB floats, starting at the bottom row, with a negative scale
+// meaning little-endian
+fn write_pfm<W: Write>(mut w: W, pixels: &[[f32; 4]], width: u32, height: u32) -> io::Result<()> {
+    write!(w, "PF\n{} {}\n-1.0\n", width, height)?;
+    for row in pixels.chunks(width as usize).rev() {
+        for p in row {
+            write_f32s(&mut w, &p[..3])?;
+        }
+    }
+
+    w.flush()
+}
+
+// A baseline, uncompressed, little-endian TIFF with a single strip. 32 bits per
+// channel means floats.
+fn write_tiff<W: Write>(
+    mut w: W,
+    pixels: &[[f32; 4]],
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    channels: usize,
+) -> io::Result<()> {
+    let mut data = Vec::with_capacity(pixels.len() * channels * bit_depth as usize / 8);
+    match bit_depth {
+        8 => data = to_u8(pixels, channels),
+        16 => {
+            for p in pixels {
+                for c in &p[..channels] {
+                    let v = (c.max(0.0).min(1.0) * 65535.0).round() as u16;
+                    data.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+        }
+        _ => {
+            for p in pixels {
+                write_f32s(&mut data, &p[..channels])?;
+            }
+        }
+    }
+
+    // the header and the bits per sample come first, then the pixels, then the tags
+    let bits_offset = 8u32;
+    let data_offset = bits_offset + 2 * channels as u32;
+    let ifd_offset = data_offset + data.len() as u32 + (data.len() as u32 & 1);
+    let sample_format = if bit_depth == 32 { 3 } else { 1 };
+    // (tag, type (3 = SHORT, 4 = LONG), count, value or offset)
+    let mut tags: Vec<(u16, u16, u32, u32)> = vec![
+        (256, 4, 1, width),
+        (257, 4, 1, height),
+        (258, 3, channels as u32, bits_offset),
+        (259, 3, 1, 1),
+        (262, 3, 1, 2),
+        (273, 4, 1, data_offset),
+        (277, 3, 1, channels as u32),
+        (278, 4, 1, height),
+        (279, 4, 1, data.len() as u32),
+        (284, 3, 1, 1),
+    ];
+    if channels == 4 {
+        // unassociated alpha
+        tags.push((338, 3, 1, 2));
+    }
+    tags.push((339, 3, 1, sample_format));
+
+    w.write_all(b"II")?;
+    w.write_all(&42u16.to_le_bytes())?;
+    w.write_all(&ifd_offset.to_le_bytes())?;
+    for _ in 0..channels {
+        w.write_all(&(bit_depth as u16).to_le_bytes())?;
+    }
+    w.write_all(&data)?;
+    if data.len() & 1 == 1 {
+        w.write_all(&[0])?;
+    }
+    w.write_all(&(tags.len() as u16).to_le_bytes())?;
+    for &(tag, ty, count, value) in &tags {
+        w.write_all(&tag.to_le_bytes())?;
+        w.write_all(&ty.to_le_bytes())?;
+        w.write_all(&count.to_le_bytes())?;
+        if ty == 3 && count == 1 {
+            w.write_all(&(value as u16).to_le_bytes())?;
+            w.write_all(&[0, 0])?;
+        } else {
+            w.write_all(&value.to_le_bytes())?;
+        }
+    }
+    w.write_all(&0u32.to_le_bytes())?;
+
+    w.flush()
+}
+
 use Screen;
 
 impl<'a> Screen<'a> {
@@ -24,42 +242,297 @@ impl<'a> Screen<'a> {
         self.image_mode = mode.to_owned();
     }
 
+	/// Tint every textured shape drawn from now on with the given color, which is
+	/// interpreted according to the current color mode. The color of each texel is
+	/// multiplied by the tint, so, for example, tint(1, 1, 1, 0.5) draws images at half
+	/// opacity. Since the tint is applied when a shape is drawn, rather than when it is
+	/// created, you can fade the same shape in and out by changing the tint every frame.
+	/// Moulds are tinted as well if their shader declares `uniform vec4 tint`.
+    pub fn tint(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        let c = self.color(r, g, b, a);
+        self.tint_stuff = true;
+        self.tint_col = [c.r, c.g, c.b, c.a];
+    }
+
 	/// Stop applying tint to a drawn image.
     pub fn no_tint(&mut self) {
         self.tint_stuff = false
     }
 
 	/// Save the current state of the screen to an image. The format will be determined
-	/// by the file extension.
-    pub fn save(&self, filename: &str) -> Result<(), ProcessingErr> {
-        let data = vec![0f32; self.fb_size[0] as usize * self.fb_size[1] as usize * 4 * 4];
-        unsafe {
-            gl::ReadPixels(
-                0,
-                0,
-                self.fb_size[0] as gl::types::GLsizei,
-                self.fb_size[1] as gl::types::GLsizei,
-                gl::RGBA,
-                gl::FLOAT,
-                mem::transmute(&data[0]),
-            );
-        }
-
-        let mut img = image_ext::ImageBuffer::new(self.fb_size[0], self.fb_size[1]);
-        let mut i = 0;
-        for pixel in img.pixels_mut() {
-            *pixel = image_ext::Rgba(
-                [
-                    (data[i] * 255.0) as u8,
-                    (data[i + 1] * 255.0) as u8,
-                    (data[i + 2] * 255.0) as u8,
-                    255u8,
-                ],
-            );
-            i += 4;
-        }
-        let fimg = image_ext::imageops::flip_vertical(&img);
-
-        fimg.save(&Path::new(filename)).map_err(|e| ProcessingErr::ImageNotSaved(e))
+	/// by the file extension. Ordinary image formats get 8 bits per channel and no alpha;
+	/// see screen.save_with() for more.
+    pub fn save(&mut self, filename: &str) -> Result<(), ProcessingErr> {
+        self.save_with(filename, SaveOptions::default())
+    }
+
+	/// Save the current state of the screen to an image, with the given bit depth and
+	/// with or without alpha (see SaveOptions and save_pixels() for which file formats
+	/// support what). The pixels are read straight from the internal framebuffer, so
+	/// this works the same for windows and headless screens.
+    pub fn save_with(&mut self, filename: &str, options: SaveOptions) -> Result<(), ProcessingErr> {
+        self.flush()?;
+        let pixels = self.framebuffer_pixels();
+        let (w, h) = (self.fbtexture.get_width(), self.fbtexture.get_height().unwrap_or(1));
+        save_pixels(filename, &pixels, w, h, options)
+    }
+
+	/// Read everything in the internal framebuffer as RGBA floats, row by row from the
+	/// top left, without touching the pixel array of screen.load_pixels(). Shapes that
+	/// are still waiting to be drawn in a batch are not included.
+    pub fn framebuffer_pixels(&self) -> Vec<[f32; 4]> {
+        let (w, h) = (self.fbtexture.get_width(), self.fbtexture.get_height().unwrap_or(1));
+        let rect = Rect {
+            left: 0,
+            bottom: 0,
+            width: w,
+            height: h,
+        };
+        let img = self.fbtexture
+            .main_level()
+            .first_layer()
+            .into_image(None)
+            .expect("The framebuffer texture should always have an image.")
+            .raw_read::<RawImage2d<f32>, (f32, f32, f32, f32)>(&rect);
+
+        // OpenGL starts at the bottom row
+        let mut pixels = Vec::with_capacity(w as usize * h as usize);
+        for row in img.data.chunks(w as usize * 4).rev() {
+            pixels.extend(row.chunks(4).map(|p| [p[0], p[1], p[2], p[3]]));
+        }
+
+        pixels
+    }
+
+	/// Read what has been drawn so far into the pixel array of the screen, which can then
+	/// be inspected and changed with screen.get(), screen.set(), screen.pixels(), and
+	/// screen.pixels_mut(). The pixels come straight from the internal framebuffer as
+	/// floats, so nothing is lost if it has more than 8 bits per channel (see
+	/// textures::ScreenFormat). They are stored row by row, starting at the top left,
+	/// so the pixel at (x, y) is at index y * width + x.
+    pub fn load_pixels(&mut self) -> Result<(), ProcessingErr> {
+        self.flush()?;
+        self.pixels = self.framebuffer_pixels();
+
+        Ok(())
+    }
+
+	/// Write the pixel array of the screen, including any changes made to it, back into
+	/// the internal framebuffer, so that it will be shown at the next screen.reveal().
+	/// screen.load_pixels() has to be called first.
+    pub fn update_pixels(&mut self) -> Result<(), ProcessingErr> {
+        let (w, h) = (self.fbtexture.get_width(), self.fbtexture.get_height().unwrap_or(1));
+        if self.pixels.len() != w as usize * h as usize {
+            return Err(ProcessingErr::PixelsNotLoaded);
+        }
+        let mut data = Vec::with_capacity(self.pixels.len() * 4);
+        for row in self.pixels.chunks(w as usize).rev() {
+            for p in row {
+                data.extend_from_slice(p);
+            }
+        }
+        let img = RawImage2d {
+            data: Cow::Owned(data),
+            width: w,
+            height: h,
+            format: ClientFormat::F32F32F32F32,
+        };
+        self.fbtexture.write(
+            Rect {
+                left: 0,
+                bottom: 0,
+                width: w,
+                height: h,
+            },
+            img,
+        );
+
+        Ok(())
+    }
+
+	/// The pixel array of the screen, as read by screen.load_pixels(). It is empty until
+	/// then.
+    pub fn pixels(&self) -> &[[f32; 4]] {
+        &self.pixels
+    }
+
+	/// The pixel array of the screen, for changing it directly. Call
+	/// screen.update_pixels() afterwards to show the changes.
+    pub fn pixels_mut(&mut self) -> &mut [[f32; 4]] {
+        &mut self.pixels
+    }
+
+	/// Get the color of the pixel at (x, y) in the pixel array, where (0, 0) is the top
+	/// left corner of the screen. Pixels outside of the screen are transparent black, as
+	/// in Processing. screen.load_pixels() has to be called first.
+    pub fn get(&self, x: i32, y: i32) -> Result<Color, ProcessingErr> {
+        let p = self.pixel_index(x, y)?.map_or([0.0; 4], |i| self.pixels[i]);
+        Ok(Color::rgb(p[0], p[1], p[2], p[3]))
+    }
+
+	/// Get the colors of the w by h pixels whose top left corner is at (x, y), row by row.
+	/// Pixels outside of the screen are transparent black.
+    pub fn get_region(&self, x: i32, y: i32, w: u32, h: u32) -> Result<Vec<[f32; 4]>, ProcessingErr> {
+        let mut region = Vec::with_capacity(w as usize * h as usize);
+        for j in 0..h as i32 {
+            for i in 0..w as i32 {
+                let p = self.pixel_index(x + i, y + j)?.map_or([0.0; 4], |k| self.pixels[k]);
+                region.push(p);
+            }
+        }
+
+        Ok(region)
+    }
+
+	/// Set the pixel at (x, y) in the pixel array to the given color. Pixels outside of
+	/// the screen are ignored. As with all changes to the pixel array, this only shows up
+	/// after screen.update_pixels().
+    pub fn set(&mut self, x: i32, y: i32, c: &Color) -> Result<(), ProcessingErr> {
+        if let Some(i) = self.pixel_index(x, y)? {
+            self.pixels[i] = [c.r, c.g, c.b, c.a];
+        }
+
+        Ok(())
+    }
+
+    // where (x, y) is in the pixel array, if it is on the screen at all
+    fn pixel_index(&self, x: i32, y: i32) -> Result<Option<usize>, ProcessingErr> {
+        let (w, h) = (self.fbtexture.get_width(), self.fbtexture.get_height().unwrap_or(1));
+        if self.pixels.len() != w as usize * h as usize {
+            return Err(ProcessingErr::PixelsNotLoaded);
+        }
+        if x < 0 || y < 0 || x >= w as i32 || y >= h as i32 {
+            return Ok(None);
+        }
+
+        Ok(Some(y as usize * w as usize + x as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image_ext::{ImageDecoder, DecodingResult, ColorType};
+    use image_ext::tiff::TIFFDecoder;
+
+    use super::{write_tiff, write_pfm};
+
+    // two rows of three pixels, with a different value in every channel
+    fn pixels() -> Vec<[f32; 4]> {
+        vec![
+            [1.0, 0.0, 0.0, 1.0],
+            [0.0, 1.0, 0.0, 0.5],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.2, 0.4, 0.6, 0.8],
+            [0.25, 0.5, 0.75, 1.0],
+            [1.5, -0.5, 0.1, 0.3],
+        ]
+    }
+
+    fn tiff(bit_depth: u8, channels: usize) -> Vec<u8> {
+        let mut out = vec![];
+        write_tiff(&mut out, &pixels(), 3, 2, bit_depth, channels).unwrap();
+        out
+    }
+
+    fn expected(channels: usize, max: f32) -> Vec<u32> {
+        let mut values = vec![];
+        for p in pixels() {
+            for c in &p[..channels] {
+                values.push((c.max(0.0).min(1.0) * max).round() as u32);
+            }
+        }
+        values
+    }
+
+    fn decode(bytes: Vec<u8>) -> (ColorType, DecodingResult) {
+        let mut decoder = TIFFDecoder::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(decoder.dimensions().unwrap(), (3, 2));
+        let color = decoder.colortype().unwrap();
+        (color, decoder.read_image().unwrap())
+    }
+
+    // the value of a tag with a single LONG or SHORT, read straight from the IFD
+    fn tag(bytes: &[u8], tag: u16) -> u32 {
+        let le16 = |i: usize| bytes[i] as u32 | (bytes[i + 1] as u32) << 8;
+        let le32 = |i: usize| le16(i) | le16(i + 2) << 16;
+        let ifd = le32(4) as usize;
+        for e in 0..le16(ifd) as usize {
+            let entry = ifd + 2 + 12 * e;
+            if le16(entry) == tag as u32 {
+                return if le16(entry + 2) == 3 { le16(entry + 8) } else { le32(entry + 8) };
+            }
+        }
+        panic!("no tag {}", tag);
+    }
+
+    #[test]
+    fn tiff_8_bits() {
+        for &channels in &[3, 4] {
+            let (color, data) = decode(tiff(8, channels));
+            assert_eq!(color, if channels == 4 { ColorType::RGBA(8) } else { ColorType::RGB(8) });
+            match data {
+                DecodingResult::U8(v) => {
+                    assert_eq!(v.into_iter().map(|x| x as u32).collect::<Vec<_>>(), expected(channels, 255.0))
+                }
+                _ => panic!("expected 8-bit samples"),
+            }
+        }
+    }
+
+    #[test]
+    fn tiff_16_bits() {
+        for &channels in &[3, 4] {
+            let (color, data) = decode(tiff(16, channels));
+            assert_eq!(color, if channels == 4 { ColorType::RGBA(16) } else { ColorType::RGB(16) });
+            match data {
+                DecodingResult::U16(v) => {
+                    assert_eq!(v.into_iter().map(|x| x as u32).collect::<Vec<_>>(), expected(channels, 65535.0))
+                }
+                _ => panic!("expected 16-bit samples"),
+            }
+        }
+    }
+
+    #[test]
+    fn tiff_floats() {
+        // the decoder of the image crate reads the header of a float TIFF but not its
+        // samples, so those are checked directly
+        for &channels in &[3, 4] {
+            let bytes = tiff(32, channels);
+            let mut decoder = TIFFDecoder::new(Cursor::new(bytes.clone())).unwrap();
+            assert_eq!(decoder.dimensions().unwrap(), (3, 2));
+            assert_eq!(tag(&bytes, 277), channels as u32);
+            assert_eq!(tag(&bytes, 339), 3);
+
+            let offset = tag(&bytes, 273) as usize;
+            let len = tag(&bytes, 279) as usize;
+            assert_eq!(len, 3 * 2 * channels * 4);
+            let samples = bytes[offset..offset + len]
+                .chunks(4)
+                .map(|b| f32::from_bits(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24))
+                .collect::<Vec<_>>();
+            let unclamped = pixels().iter().flat_map(|p| p[..channels].to_vec()).collect::<Vec<_>>();
+            assert_eq!(samples, unclamped);
+        }
+    }
+
+    #[test]
+    fn pfm_header_and_rows() {
+        let mut out = vec![];
+        write_pfm(&mut out, &pixels(), 3, 2).unwrap();
+        let header = b"PF\n3 2\n-1.0\n";
+        assert_eq!(&out[..header.len()], &header[..]);
+
+        let samples = out[header.len()..]
+            .chunks(4)
+            .map(|b| f32::from_bits(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24))
+            .collect::<Vec<_>>();
+        // the bottom row comes first, without alpha
+        let p = pixels();
+        let rows = p[3..].iter().chain(p[..3].iter());
+        let expected = rows.flat_map(|p| p[..3].to_vec()).collect::<Vec<_>>();
+        assert_eq!(samples, expected);
     }
 }
@@ -25,7 +25,9 @@
 //!        giving some marginal speed boosts and allowing the possibility to store
 //!        shapes in collections, such as Vectors. All shapes (Rect, Ellipse, etc.) in
 //!        this crate implement the Shape trait, which defines a common interface for
-//!        entities that can be drawn to a screen in a meaningful way.
+//!        entities that can be drawn to a screen in a meaningful way. If you would
+//!        rather draw a shape right away, as in Processing, you can also use the
+//!        immediate-mode functions, like screen.rect() and screen.ellipse().
 //!
 //!     3. Draw the shapes to the screen with screen.draw(). This is also where you will
 //!        want to use commands like screen.fill() and screen.stroke() to change the
@@ -73,6 +75,8 @@ extern crate nalgebra;
 //extern crate rand;
 extern crate image as image_ext;
 extern crate owning_ref;
+extern crate rusttype;
+extern crate serde_json;
 
 #[cfg(target_os = "macos")]
 #[macro_use]
@@ -84,7 +88,7 @@ use cocoa::foundation::{NSProcessInfo, NSString};
 #[cfg(target_os = "macos")]
 use cocoa::base::nil;
 
-use nalgebra::{Matrix4, Vector3, Unit};
+use nalgebra::{Matrix4, Vector3, Point3, Unit};
 
 #[cfg(not(feature = "glfw"))]
 pub use glium::*;
@@ -108,6 +112,11 @@ pub mod transform;
 pub mod rendering;
 pub mod image;
 pub mod errors;
+pub mod text;
+pub mod lights;
+pub mod recording;
+pub mod calibration;
+pub mod timing;
 
 #[cfg(not(feature = "glfw"))]
 pub mod environment;
@@ -127,15 +136,20 @@ pub use glfwp5::constants;
 pub use constants::{Key, MouseButton};
 
 pub use image::load_image;
+pub use shapes::obj::load_obj;
+pub use color::Color;
 
 #[derive(Debug)]
 pub struct GLmatStruct {
     pub curr_matrix: Matrix4<f32>,
+    pub view_matrix: Matrix4<f32>,
+    pub projection_matrix: Matrix4<f32>,
     matrix_stack: Vec<Matrix4<f32>>,
+    camera_saved: Option<Matrix4<f32>>,
 }
 
 pub struct FBtexs {
-    fbtex: glium::texture::Texture2d,
+    fbtex: textures::ScreenTexture,
     depthtexture: glium::texture::DepthTexture2d,
 }
 
@@ -175,13 +189,20 @@ pub struct Screen<'a> {
     stroke_stuff: bool,
     stroke_col: Vec<f32>,
     tint_stuff: bool,
-    tint_col: Vec<f32>,
+    tint_col: [f32; 4],
+    lights: Vec<lights::Light>,
+    light_falloff: [f32; 3],
+    light_specular: [f32; 3],
+    material: lights::Material,
     shader_bank: Vec<glium::program::Program>,
     draw_texture: bool,
     aspect_ratio: f32,
     preserve_aspect_ratio: bool,
     fb_size: Vec<u32>,
     stroke_weight: f32,
+    stroke_cap: String,
+    stroke_join: String,
+    dynamic_shapes: bool,
     font_face: String,
     text_size: f32,
     height: u32,
@@ -191,6 +212,7 @@ pub struct Screen<'a> {
     top: f32,
     bottom: f32,
     c_mode: String,
+    color_ranges: [f32; 4],
     title: String,
     ellipse_mode: String,
     rect_mode: String,
@@ -199,9 +221,27 @@ pub struct Screen<'a> {
     frame_rate: isize,
     frame_count: isize,
     fonts_initialized: bool,
+    font_state: Option<text::FontState>,
+    batch: shapes::immediate::Batch,
+    shape_builder: Option<shapes::custom::ShapeBuilder>,
+    curve_tightness: f32,
+    bezier_detail: usize,
+    curve_detail: usize,
+    sphere_detail: (usize, usize),
     curr_shader: usize,
     curr_cursor: glium::glutin::MouseCursor,
-    wrap: glium::uniforms::SamplerWrapFunction,
+    texture_sampling: textures::TextureSampling,
+    texture_format: textures::TextureFormat,
+    fb_format: textures::TextureFormat,
+    display_format: textures::GrantedFormat,
+    pixels: Vec<[f32; 4]>,
+    frame_writer: Option<recording::FrameWriter>,
+    recording: Option<recording::Recording>,
+    video_encoder: Option<Vec<String>>,
+    gamma_table: glium::texture::Texture1d,
+    gamma_correction: bool,
+    output_encoding: calibration::OutputEncoding,
+    flip_timer: timing::FlipTimer,
     alternate_shader: usize,
     curr_texture: Option<glium::texture::Texture2d>,
     using_alternate_shader: bool,
@@ -247,13 +287,20 @@ pub struct Screen<'a> {
     stroke_stuff: bool,
     stroke_col: Vec<f32>,
     tint_stuff: bool,
-    tint_col: Vec<f32>,
+    tint_col: [f32; 4],
+    lights: Vec<lights::Light>,
+    light_falloff: [f32; 3],
+    light_specular: [f32; 3],
+    material: lights::Material,
     shader_bank: Vec<glium::program::Program>,
     draw_texture: bool,
     aspect_ratio: f32,
     preserve_aspect_ratio: bool,
     fb_size: Vec<u32>,
     stroke_weight: f32,
+    stroke_cap: String,
+    stroke_join: String,
+    dynamic_shapes: bool,
     font_face: String,
     text_size: f32,
     height: u32,
@@ -263,6 +310,7 @@ pub struct Screen<'a> {
     top: f32,
     bottom: f32,
     c_mode: String,
+    color_ranges: [f32; 4],
     title: String,
     ellipse_mode: String,
     rect_mode: String,
@@ -271,9 +319,27 @@ pub struct Screen<'a> {
     frame_rate: isize,
     frame_count: isize,
     fonts_initialized: bool,
+    font_state: Option<text::FontState>,
+    batch: shapes::immediate::Batch,
+    shape_builder: Option<shapes::custom::ShapeBuilder>,
+    curve_tightness: f32,
+    bezier_detail: usize,
+    curve_detail: usize,
+    sphere_detail: (usize, usize),
     curr_shader: usize,
     curr_cursor: glfw::StandardCursor,
-    wrap: glium::uniforms::SamplerWrapFunction,
+    texture_sampling: textures::TextureSampling,
+    texture_format: textures::TextureFormat,
+    fb_format: textures::TextureFormat,
+    display_format: textures::GrantedFormat,
+    pixels: Vec<[f32; 4]>,
+    frame_writer: Option<recording::FrameWriter>,
+    recording: Option<recording::Recording>,
+    video_encoder: Option<Vec<String>>,
+    gamma_table: glium::texture::Texture1d,
+    gamma_correction: bool,
+    output_encoding: calibration::OutputEncoding,
+    flip_timer: timing::FlipTimer,
     alternate_shader: usize,
     curr_texture: Option<glium::texture::Texture2d>,
     using_alternate_shader: bool,
@@ -0,0 +1,288 @@
+// Lights and materials
+use glium::uniforms::{Uniforms, UniformValue};
+use nalgebra::Vector4;
+
+use Screen;
+use errors::ProcessingErr;
+
+// how many lights the lit shaders can handle at once (see MAX_LIGHTS in shaders.rs)
+const MAX_LIGHTS: usize = 8;
+
+/// A single light, in the form that the lit shaders expect it. Its position and
+/// direction have already been transformed by the matrix that was current when the
+/// light was created, just like in Processing.
+#[derive(Copy, Clone, Debug)]
+pub struct Light {
+    position: [f32; 4],
+    normal: [f32; 3],
+    ambient: [f32; 3],
+    diffuse: [f32; 3],
+    specular: [f32; 3],
+    falloff: [f32; 3],
+    spot: [f32; 2],
+}
+
+/// How the surfaces of lit shapes respond to light. The diffuse color of a surface is
+/// always its fill color, while its ambient color is the fill color unless it was set
+/// with screen.ambient().
+#[derive(Copy, Clone, Debug)]
+pub struct Material {
+    ambient: Option<[f32; 3]>,
+    specular: [f32; 3],
+    emissive: [f32; 3],
+    shininess: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            ambient: None,
+            specular: [0.0; 3],
+            emissive: [0.0; 3],
+            shininess: 1.0,
+        }
+    }
+}
+
+impl Material {
+	/// A material with the given ambient color (None to use the fill color), specular
+	/// color, emissive color, and shininess, as set by screen.ambient() and friends.
+    pub fn new(ambient: Option<[f32; 3]>, specular: [f32; 3], emissive: [f32; 3], shininess: f32) -> Self {
+        Material {
+            ambient: ambient,
+            specular: specular,
+            emissive: emissive,
+            shininess: shininess,
+        }
+    }
+}
+
+/// Adds the lights and the material to the uniforms of a lit program. Nothing is added
+/// if there are no lights, which is the case for shapes that are drawn unlit.
+pub struct LightUniforms<'u, U: Uniforms + 'u> {
+    uniforms: &'u U,
+    lights: &'u [Light],
+    material: &'u Material,
+}
+
+impl<'u, U: Uniforms> LightUniforms<'u, U> {
+    pub fn new(uniforms: &'u U, lights: &'u [Light], material: &'u Material) -> Self {
+        LightUniforms {
+            uniforms: uniforms,
+            lights: lights,
+            material: material,
+        }
+    }
+}
+
+impl<'u, U: Uniforms> Uniforms for LightUniforms<'u, U> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut f: F) {
+        self.uniforms.visit_values(&mut f);
+        if self.lights.is_empty() {
+            return;
+        }
+
+        f("lightCount", UniformValue::SignedInt(self.lights.len() as i32));
+        for (i, l) in self.lights.iter().enumerate() {
+            f(&format!("lightPosition[{}]", i), UniformValue::Vec4(l.position));
+            f(&format!("lightNormal[{}]", i), UniformValue::Vec3(l.normal));
+            f(&format!("lightAmbient[{}]", i), UniformValue::Vec3(l.ambient));
+            f(&format!("lightDiffuse[{}]", i), UniformValue::Vec3(l.diffuse));
+            f(&format!("lightSpecular[{}]", i), UniformValue::Vec3(l.specular));
+            f(&format!("lightFalloff[{}]", i), UniformValue::Vec3(l.falloff));
+            f(&format!("lightSpot[{}]", i), UniformValue::Vec2(l.spot));
+        }
+
+        let ambient = match self.material.ambient {
+            Some(c) => [c[0], c[1], c[2], 1.0],
+            None => [0.0; 4],
+        };
+        f("ambientMaterial", UniformValue::Vec4(ambient));
+        f("specularMaterial", UniformValue::Vec3(self.material.specular));
+        f("emissiveMaterial", UniformValue::Vec3(self.material.emissive));
+        f("shininess", UniformValue::Float(self.material.shininess));
+    }
+}
+
+impl<'a> Screen<'a> {
+	/// Turn on the default lights: a gray ambient light and a gray directional light
+	/// that shines straight into the screen. Any lights that were there before are
+	/// removed, and the falloff and specular color of new lights are reset to their
+	/// defaults. As in Processing, lights only affect shapes that have normals, such as
+	/// Sphere or BoxShape; everything else is drawn as before. Unlike in Processing,
+	/// lights are not reset at the start of every frame, but stay on until
+	/// screen.no_lights() is called.
+    pub fn lights(&mut self) -> Result<(), ProcessingErr> {
+        self.lights.clear();
+        self.light_falloff = [1.0, 0.0, 0.0];
+        self.light_specular = [0.0; 3];
+        let gray = [128.0 / 255.0; 3];
+        self.push_light([0.0; 4], [0.0, 0.0, -1.0], gray, [0.0; 3], [0.0; 3], [-1.0, 0.0])?;
+        self.push_light([0.0; 4], [0.0, 0.0, -1.0], [0.0; 3], gray, [0.0; 3], [-1.0, 0.0])
+    }
+
+	/// Turn off all lights, so that shapes are drawn in their flat colors again.
+    pub fn no_lights(&mut self) {
+        self.lights.clear();
+    }
+
+	/// Add a light that shines evenly on everything, from all directions. The color is
+	/// interpreted according to the current color mode, ignoring alpha. There can be at
+	/// most eight lights at once.
+    pub fn ambient_light(&mut self, r: f32, g: f32, b: f32) -> Result<(), ProcessingErr> {
+        let c = self.light_color(r, g, b);
+        self.push_light([0.0; 4], [0.0, 0.0, -1.0], c, [0.0; 3], [0.0; 3], [-1.0, 0.0])
+    }
+
+	/// Add a light that shines from far away in the direction (nx, ny, nz), like the
+	/// sun. For example, (0, 0, -1) shines straight into the screen and (0, -1, 0)
+	/// shines down from above.
+    pub fn directional_light(
+        &mut self,
+        r: f32,
+        g: f32,
+        b: f32,
+        nx: f32,
+        ny: f32,
+        nz: f32,
+    ) -> Result<(), ProcessingErr> {
+        let c = self.light_color(r, g, b);
+        let normal = self.light_direction(nx, ny, nz);
+        let specular = self.light_specular;
+        self.push_light([0.0; 4], normal, [0.0; 3], c, specular, [-1.0, 0.0])
+    }
+
+	/// Add a light that shines in all directions from the position (x, y, z), like a
+	/// bare light bulb. How quickly it gets dimmer with distance is set by
+	/// screen.light_falloff().
+    pub fn point_light(&mut self, r: f32, g: f32, b: f32, x: f32, y: f32, z: f32) -> Result<(), ProcessingErr> {
+        let c = self.light_color(r, g, b);
+        let position = self.light_position(x, y, z);
+        let specular = self.light_specular;
+        self.push_light(position, [0.0, 0.0, -1.0], [0.0; 3], c, specular, [-1.0, 0.0])
+    }
+
+	/// Add a light that shines from the position (x, y, z) in the direction
+	/// (nx, ny, nz), but only within a cone whose half-angle is given in radians. The
+	/// concentration makes the light fall off towards the edge of the cone, where
+	/// 1 is a gentle falloff and larger values focus the light more and more on the
+	/// center.
+    pub fn spot_light(
+        &mut self,
+        r: f32,
+        g: f32,
+        b: f32,
+        x: f32,
+        y: f32,
+        z: f32,
+        nx: f32,
+        ny: f32,
+        nz: f32,
+        angle: f32,
+        concentration: f32,
+    ) -> Result<(), ProcessingErr> {
+        let c = self.light_color(r, g, b);
+        let position = self.light_position(x, y, z);
+        let normal = self.light_direction(nx, ny, nz);
+        let specular = self.light_specular;
+        self.push_light(position, normal, [0.0; 3], c, specular, [angle.cos(), concentration])
+    }
+
+	/// Set how point and spot lights that are created from now on get dimmer with the
+	/// distance d from them: their light is divided by constant + linear * d +
+	/// quadratic * d * d. The default of (1, 0, 0) means that they do not get dimmer
+	/// at all.
+    pub fn light_falloff(&mut self, constant: f32, linear: f32, quadratic: f32) {
+        self.light_falloff = [constant, linear, quadratic];
+    }
+
+	/// Set the color of the highlights of the lights that are created from now on. The
+	/// default is black, i.e., no highlights. Highlights also need a specular material
+	/// (see screen.specular()).
+    pub fn light_specular(&mut self, r: f32, g: f32, b: f32) {
+        self.light_specular = self.light_color(r, g, b);
+    }
+
+	/// Set the color in which lit shapes reflect ambient light. By default, they do so
+	/// in their fill color.
+    pub fn ambient(&mut self, r: f32, g: f32, b: f32) {
+        self.material.ambient = Some(self.light_color(r, g, b));
+    }
+
+	/// Set the color of the highlights of lit shapes. The default is black, i.e., no
+	/// highlights.
+    pub fn specular(&mut self, r: f32, g: f32, b: f32) {
+        self.material.specular = self.light_color(r, g, b);
+    }
+
+	/// Set how glossy lit shapes are. Larger values make their highlights smaller and
+	/// sharper. The default is 1.
+    pub fn shininess(&mut self, shine: f32) {
+        self.material.shininess = shine;
+    }
+
+	/// Set the color that lit shapes give off on their own, regardless of any lights.
+	/// The default is black.
+    pub fn emissive(&mut self, r: f32, g: f32, b: f32) {
+        self.material.emissive = self.light_color(r, g, b);
+    }
+
+    fn push_light(
+        &mut self,
+        position: [f32; 4],
+        normal: [f32; 3],
+        ambient: [f32; 3],
+        diffuse: [f32; 3],
+        specular: [f32; 3],
+        spot: [f32; 2],
+    ) -> Result<(), ProcessingErr> {
+        if self.lights.len() >= MAX_LIGHTS {
+            return Err(ProcessingErr::TooManyLights(MAX_LIGHTS));
+        }
+        self.lights.push(Light {
+            position: position,
+            normal: normal,
+            ambient: ambient,
+            diffuse: diffuse,
+            specular: specular,
+            falloff: self.light_falloff,
+            spot: spot,
+        });
+
+        Ok(())
+    }
+
+    fn light_color(&self, r: f32, g: f32, b: f32) -> [f32; 3] {
+        let c = self.color(r, g, b, 1.0);
+        [c.r, c.g, c.b]
+    }
+
+    // Lights are placed in the same coordinates as the shapes, so they get squeezed to
+    // keep the aspect ratio just like the shapes do and are then transformed by the
+    // current model and view matrices into the coordinates of the camera.
+    fn light_transform(&self, v: [f32; 4]) -> Vector4<f32> {
+        let mut v = Vector4::new(v[0], v[1], v[2], v[3]);
+        if self.preserve_aspect_ratio && self.aspect_ratio > 1f32 {
+            v[0] = v[0] / self.aspect_ratio;
+        } else if self.preserve_aspect_ratio && self.aspect_ratio < 1f32 {
+            v[1] = v[1] * self.aspect_ratio;
+        }
+
+        self.matrices.modelview() * v
+    }
+
+    fn light_position(&self, x: f32, y: f32, z: f32) -> [f32; 4] {
+        let p = self.light_transform([x, y, z, 1.0]);
+        [p[0] / p[3], p[1] / p[3], p[2] / p[3], 1.0]
+    }
+
+    fn light_direction(&self, nx: f32, ny: f32, nz: f32) -> [f32; 3] {
+        let d = self.light_transform([nx, ny, nz, 0.0]);
+        let len = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+        if len > 0.0 {
+            [d[0] / len, d[1] / len, d[2] / len]
+        } else {
+            [0.0, 0.0, -1.0]
+        }
+    }
+}
@@ -0,0 +1,557 @@
+// Output: saving frames and videos
+use std::io;
+use std::io::{Write, BufWriter};
+use std::fs::File;
+use std::path::Path;
+use std::process::{Command, Child, Stdio};
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::thread;
+
+use glium::Rect;
+use glium::texture::pixel_buffer::PixelBuffer;
+
+use {Screen, ScreenType};
+use errors::ProcessingErr;
+use image::{SaveOptions, write_pixels, keeps_floats};
+
+/// An encoder command for screen.video_encoder() that turns the frames into an H.264
+/// video with `ffmpeg`, which has to be installed and on the path.
+pub const FFMPEG: &'static [&'static str] = &[
+    "ffmpeg", "-y", "-loglevel", "error",
+    "-f", "rawvideo", "-pix_fmt", "rgba", "-s", "{width}x{height}", "-r", "{fps}", "-i", "-",
+    "-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2", "-pix_fmt", "yuv420p", "{output}",
+];
+
+// how many frames can be on their way back from the GPU while recording
+const RECORDING_BUFFERS: usize = 3;
+
+// how many frames can wait for the writing thread before saving another one blocks
+// until it catches up, which keeps the memory of a long recording bounded
+const QUEUED_FRAMES: usize = 4;
+
+type PixelTuple = (f32, f32, f32, f32);
+
+// the pixels of a frame, either as screen.save_frame() reads them or straight from a
+// pixel buffer, which starts at the bottom row, or already cut down to 8 bits per
+// channel when that is all that will be written, so that queued frames take up a
+// quarter of the memory
+enum FramePixels {
+    TopDown(Vec<[f32; 4]>),
+    BottomUp(Vec<PixelTuple>),
+    Bytes(Vec<[u8; 4]>),
+}
+
+// a frame that is waiting to be encoded and written
+struct Frame {
+    pixels: FramePixels,
+    width: u32,
+    height: u32,
+}
+
+impl Frame {
+    fn into_top_down(self) -> Vec<[f32; 4]> {
+        match self.pixels {
+            FramePixels::TopDown(p) => p,
+            FramePixels::BottomUp(p) => p.chunks(self.width as usize)
+                .rev()
+                .flat_map(|row| row.iter().map(|&(r, g, b, a)| [r, g, b, a]))
+                .collect(),
+            FramePixels::Bytes(p) => p.iter()
+                .map(|c| [c[0] as f32 / 255.0, c[1] as f32 / 255.0, c[2] as f32 / 255.0, c[3] as f32 / 255.0])
+                .collect(),
+        }
+    }
+
+    // the pixels with 8 bits per channel, top row first
+    fn into_byte_pixels(self) -> Vec<[u8; 4]> {
+        match self.pixels {
+            FramePixels::Bytes(p) => p,
+            FramePixels::TopDown(p) => p.iter().map(|c| to_bytes(c[0], c[1], c[2], c[3])).collect(),
+            FramePixels::BottomUp(p) => p.chunks(self.width as usize)
+                .rev()
+                .flat_map(|row| row.iter().map(|&(r, g, b, a)| to_bytes(r, g, b, a)))
+                .collect(),
+        }
+    }
+
+    // cut the pixels down to 8 bits per channel, top row first
+    fn into_bytes(self) -> Frame {
+        let (width, height) = (self.width, self.height);
+        Frame {
+            pixels: FramePixels::Bytes(self.into_byte_pixels()),
+            width: width,
+            height: height,
+        }
+    }
+}
+
+fn to_bytes(r: f32, g: f32, b: f32, a: f32) -> [u8; 4] {
+    [to_channel(r), to_channel(g), to_channel(b), to_channel(a)]
+}
+
+// Where a video goes: into the standard input of an encoder process, as raw RGBA with
+// 8 bits per channel, or into an uncompressed Y4M file.
+enum VideoSink {
+    Encoder(Child),
+    Y4M(BufWriter<File>),
+}
+
+fn to_channel(c: f32) -> u8 {
+    (c.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+impl VideoSink {
+    fn y4m(filename: &str, width: u32, height: u32, fps: u32) -> io::Result<VideoSink> {
+        let mut file = File::create(filename).map(BufWriter::new)?;
+        write!(file, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444 XCOLORRANGE=FULL\n", width, height, fps)?;
+        Ok(VideoSink::Y4M(file))
+    }
+
+    fn write_frame(&mut self, pixels: &[[u8; 4]]) -> io::Result<()> {
+        match *self {
+            VideoSink::Encoder(ref mut child) => {
+                let data: Vec<u8> = pixels.iter().flat_map(|p| p.iter().cloned()).collect();
+                match child.stdin {
+                    Some(ref mut stdin) => stdin.write_all(&data),
+                    None => Ok(()),
+                }
+            }
+            VideoSink::Y4M(ref mut w) => {
+                // full range BT.601, in three planes of the whole size (4:4:4)
+                let mut y = Vec::with_capacity(pixels.len());
+                let mut cb = Vec::with_capacity(pixels.len());
+                let mut cr = Vec::with_capacity(pixels.len());
+                for p in pixels {
+                    let (r, g, b) = (p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0);
+                    y.push(to_channel(0.299 * r + 0.587 * g + 0.114 * b));
+                    cb.push(to_channel(0.5 - 0.168736 * r - 0.331264 * g + 0.5 * b));
+                    cr.push(to_channel(0.5 + 0.5 * r - 0.418688 * g - 0.081312 * b));
+                }
+                w.write_all(b"FRAME\n")?;
+                w.write_all(&y)?;
+                w.write_all(&cb)?;
+                w.write_all(&cr)
+            }
+        }
+    }
+
+    fn close(self) -> io::Result<()> {
+        match self {
+            VideoSink::Encoder(mut child) => {
+                // closing its input tells the encoder that the video is over
+                child.stdin = None;
+                let status = child.wait()?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(io::Error::new(io::ErrorKind::Other, format!("The video encoder failed with {}.", status)))
+                }
+            }
+            VideoSink::Y4M(mut w) => w.flush(),
+        }
+    }
+}
+
+// everything that the writing thread can be asked to do, in order
+enum Job {
+    Image(Frame, String, SaveOptions),
+    VideoFrame(Frame),
+    StartVideo(VideoSink),
+    EndVideo,
+}
+
+/// Encodes and writes frames on a separate thread, so that saving them does not hold
+/// up drawing. The thread is started by screen.save_frame(), screen.start_recording(),
+/// or screen.record_video() and keeps running until screen.wait_for_frames() or
+/// screen.stop_recording() is called, or until the Screen is dropped, which waits for
+/// every frame to be written. Only a few frames can wait for the thread at a time;
+/// beyond that, saving another frame waits until the thread has caught up.
+pub struct FrameWriter {
+    sender: Option<mpsc::SyncSender<Job>>,
+    worker: Option<thread::JoinHandle<io::Result<()>>>,
+}
+
+impl FrameWriter {
+    fn new() -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<Job>(QUEUED_FRAMES);
+        let worker = thread::spawn(move || {
+            // keep going after an error, so that as many frames as possible are saved,
+            // but report the first one
+            let mut res = Ok(());
+            let mut video: Option<VideoSink> = None;
+            for job in receiver {
+                let written = match job {
+                    Job::Image(frame, filename, options) => {
+                        let (w, h) = (frame.width, frame.height);
+                        write_pixels(Path::new(&filename), &frame.into_top_down(), w, h, options)
+                    }
+                    Job::VideoFrame(frame) => match video {
+                        Some(ref mut v) => v.write_frame(&frame.into_byte_pixels()),
+                        None => Ok(()),
+                    },
+                    Job::StartVideo(v) => {
+                        let closed = video.take().map_or(Ok(()), |v| v.close());
+                        video = Some(v);
+                        closed
+                    }
+                    Job::EndVideo => video.take().map_or(Ok(()), |v| v.close()),
+                };
+                if res.is_ok() {
+                    res = written;
+                }
+            }
+            let closed = video.take().map_or(Ok(()), |v| v.close());
+            res.and(closed)
+        });
+
+        FrameWriter {
+            sender: Some(sender),
+            worker: Some(worker),
+        }
+    }
+
+    fn send(&self, job: Job) {
+        // the thread only stops once the sender is gone, so this cannot fail
+        if let Some(ref s) = self.sender {
+            let _ = s.send(job);
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.sender = None;
+        match self.worker.take() {
+            Some(w) => w.join().unwrap_or_else(|_| {
+                Err(io::Error::new(io::ErrorKind::Other, "The thread that saves frames panicked."))
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for FrameWriter {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+// what is being recorded
+enum Target {
+    Images(String, SaveOptions),
+    Video,
+}
+
+/// The state of screen.start_recording() and screen.record_video(): the frames that
+/// are still being read back from the GPU through pixel buffer objects, so that
+/// recording does not stall drawing while the GPU catches up.
+pub struct Recording {
+    target: Target,
+    pending: VecDeque<(PixelBuffer<PixelTuple>, isize)>,
+    free: Vec<PixelBuffer<PixelTuple>>,
+}
+
+// Replace the first run of '#'s in the pattern with the frame number, padded with
+// zeros to the length of the run, like Processing does.
+fn frame_filename(pattern: &str, frame: isize) -> String {
+    match pattern.find('#') {
+        Some(start) => {
+            let digits = pattern[start..].chars().take_while(|&c| c == '#').count();
+            format!(
+                "{}{:0width$}{}",
+                &pattern[..start],
+                frame,
+                &pattern[start + digits..],
+                width = digits
+            )
+        }
+        None => pattern.to_owned(),
+    }
+}
+
+impl<'a> Screen<'a> {
+	/// Save the current frame to an image file, as in Processing's saveFrame(). The first
+	/// run of '#'s in the pattern is replaced by the frame count, padded with zeros, so
+	/// "frame-####.png" becomes "frame-0042.png" in frame 42. The pixels are read right
+	/// away, but they are encoded and written on a separate thread, so this returns
+	/// quickly. Use screen.wait_for_frames() to make sure that everything was written.
+    pub fn save_frame(&mut self, pattern: &str) -> Result<(), ProcessingErr> {
+        self.save_frame_with(pattern, SaveOptions::default())
+    }
+
+	/// The same as screen.save_frame(), but with the given bit depth and alpha (see
+	/// SaveOptions).
+    pub fn save_frame_with(&mut self, pattern: &str, options: SaveOptions) -> Result<(), ProcessingErr> {
+        self.flush()?;
+        let (w, h) = (self.fbtexture.get_width(), self.fbtexture.get_height().unwrap_or(1));
+        let mut frame = Frame {
+            pixels: FramePixels::TopDown(self.framebuffer_pixels()),
+            width: w,
+            height: h,
+        };
+        let filename = frame_filename(pattern, self.frame_count);
+        if !keeps_floats(Path::new(&filename), options) {
+            frame = frame.into_bytes();
+        }
+        self.frame_writer.get_or_insert_with(FrameWriter::new).send(Job::Image(frame, filename, options));
+
+        Ok(())
+    }
+
+	/// Start saving every frame that is revealed from now on, using the pattern as in
+	/// screen.save_frame(). The frames are copied into pixel buffers on the GPU and only
+	/// read back a couple of frames later, when they are ready, and are then written on
+	/// a separate thread, so recording disturbs the timing of a sketch as little as
+	/// possible. Call screen.stop_recording() when you are done. Anything that was
+	/// being recorded before is finished first.
+    pub fn start_recording(&mut self, pattern: &str) -> Result<(), ProcessingErr> {
+        self.start_recording_with(pattern, SaveOptions::default())
+    }
+
+	/// The same as screen.start_recording(), but with the given bit depth and alpha (see
+	/// SaveOptions).
+    pub fn start_recording_with(&mut self, pattern: &str, options: SaveOptions) -> Result<(), ProcessingErr> {
+        let free = self.end_recording()?;
+        self.recording = Some(Recording {
+            target: Target::Images(pattern.to_owned(), options),
+            pending: VecDeque::new(),
+            free: free,
+        });
+
+        Ok(())
+    }
+
+	/// Set the command that screen.record_video() runs to encode a video. The first
+	/// element is the program and the rest are its arguments, in which "{width}",
+	/// "{height}", "{fps}", and "{output}" are replaced by the size of the screen, the
+	/// frame rate, and the file name of the video. The frames are written to its standard
+	/// input as raw RGBA, with 8 bits per channel, starting at the top row. For example,
+	/// `screen.video_encoder(recording::FFMPEG)` uses `ffmpeg`.
+    pub fn video_encoder(&mut self, command: &[&str]) {
+        self.video_encoder = Some(command.iter().map(|s| s.to_string()).collect());
+    }
+
+	/// Stop using an encoder command for screen.record_video(), which is the default, so
+	/// that videos are written as uncompressed Y4M files again.
+    pub fn no_video_encoder(&mut self) {
+        self.video_encoder = None;
+    }
+
+	/// Start recording every frame that is revealed from now on into a video with the
+	/// given frame rate. If an encoder was set with screen.video_encoder(), it is started
+	/// right away and fed the frames as they come. Otherwise, the video is written as an
+	/// uncompressed Y4M file (4:4:4, full range), which most video tools can read, so a
+	/// file name ending in ".y4m" is best. As with screen.start_recording(), the frames
+	/// are read back through pixel buffers and written on a separate thread. Call
+	/// screen.stop_recording() to finish the video.
+    pub fn record_video(&mut self, filename: &str, fps: u32) -> Result<(), ProcessingErr> {
+        let free = self.end_recording()?;
+        let (w, h) = (self.fbtexture.get_width(), self.fbtexture.get_height().unwrap_or(1));
+        let sink = match self.video_encoder {
+            Some(ref command) if !command.is_empty() => {
+                let args: Vec<String> = command[1..]
+                    .iter()
+                    .map(|a| {
+                        a.replace("{width}", &w.to_string())
+                            .replace("{height}", &h.to_string())
+                            .replace("{fps}", &fps.to_string())
+                            .replace("{output}", filename)
+                    })
+                    .collect();
+                let child = Command::new(&command[0])
+                    .args(&args)
+                    .stdin(Stdio::piped())
+                    .spawn()
+                    .map_err(|e| ProcessingErr::VideoNotStarted(e))?;
+                VideoSink::Encoder(child)
+            }
+            _ => VideoSink::y4m(filename, w, h, fps).map_err(|e| ProcessingErr::VideoNotStarted(e))?,
+        };
+
+        self.frame_writer.get_or_insert_with(FrameWriter::new).send(Job::StartVideo(sink));
+        self.recording = Some(Recording {
+            target: Target::Video,
+            pending: VecDeque::new(),
+            free: free,
+        });
+
+        Ok(())
+    }
+
+	/// Stop recording images or a video, save the frames that were still on their way,
+	/// and wait until every frame has been written. The first error that occurred while
+	/// writing any of them is returned.
+    pub fn stop_recording(&mut self) -> Result<(), ProcessingErr> {
+        self.end_recording()?;
+        self.wait_for_frames()
+    }
+
+	/// Wait until every frame from screen.save_frame(), screen.start_recording(), or
+	/// screen.record_video() has been written, and return the first error that occurred
+	/// while writing them.
+    pub fn wait_for_frames(&mut self) -> Result<(), ProcessingErr> {
+        match self.frame_writer.take() {
+            Some(mut w) => w.finish().map_err(|e| ProcessingErr::ImageNotSaved(e)),
+            None => Ok(()),
+        }
+    }
+
+	/// Whether every revealed frame is currently being saved.
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+	/// While recording, start copying the frame into a pixel buffer and send off the
+	/// oldest one, if it has to make room. This is called by screen.reveal(), so there
+	/// is no need to call it yourself.
+    pub fn record_frame(&mut self) -> Result<(), ProcessingErr> {
+        let mut r = match self.recording.take() {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+        let (w, h) = (self.fbtexture.get_width(), self.fbtexture.get_height().unwrap_or(1));
+
+        if r.pending.len() >= RECORDING_BUFFERS {
+            if let Some((buffer, frame)) = r.pending.pop_front() {
+                self.send_recorded_frame(&r.target, &buffer, frame)?;
+                r.free.push(buffer);
+            }
+        }
+
+        let buffer = match r.free.pop() {
+            Some(b) => b,
+            None => match self.display {
+                ScreenType::Window(ref d) => PixelBuffer::new_empty(d, w as usize * h as usize),
+                ScreenType::Headless(ref d) => PixelBuffer::new_empty(d, w as usize * h as usize),
+            },
+        };
+        let rect = Rect {
+            left: 0,
+            bottom: 0,
+            width: w,
+            height: h,
+        };
+        self.fbtexture
+            .main_level()
+            .first_layer()
+            .into_image(None)
+            .expect("The framebuffer texture should always have an image.")
+            .raw_read_to_pixel_buffer(&rect, &buffer);
+        r.pending.push_back((buffer, self.frame_count));
+        self.recording = Some(r);
+
+        Ok(())
+    }
+
+    // Send off everything that is still being recorded and finish a video, if that is
+    // what was recorded. The pixel buffers are handed back for the next recording.
+    fn end_recording(&mut self) -> Result<Vec<PixelBuffer<PixelTuple>>, ProcessingErr> {
+        let mut r = match self.recording.take() {
+            Some(r) => r,
+            None => return Ok(Vec::new()),
+        };
+        while let Some((buffer, frame)) = r.pending.pop_front() {
+            self.send_recorded_frame(&r.target, &buffer, frame)?;
+            r.free.push(buffer);
+        }
+        if let Target::Video = r.target {
+            if let Some(ref w) = self.frame_writer {
+                w.send(Job::EndVideo);
+            }
+        }
+
+        Ok(r.free)
+    }
+
+    fn send_recorded_frame(
+        &mut self,
+        target: &Target,
+        buffer: &PixelBuffer<PixelTuple>,
+        frame_count: isize,
+    ) -> Result<(), ProcessingErr> {
+        let data = buffer.read().map_err(|e| ProcessingErr::FrameNotRead(e))?;
+        let (w, h) = (self.fbtexture.get_width(), self.fbtexture.get_height().unwrap_or(1));
+        let frame = Frame {
+            pixels: FramePixels::BottomUp(data),
+            width: w,
+            height: h,
+        };
+        let job = match *target {
+            Target::Images(ref pattern, options) => {
+                let filename = frame_filename(pattern, frame_count);
+                if keeps_floats(Path::new(&filename), options) {
+                    Job::Image(frame, filename, options)
+                } else {
+                    Job::Image(frame.into_bytes(), filename, options)
+                }
+            }
+            // videos are always written with 8 bits per channel
+            Target::Video => Job::VideoFrame(frame.into_bytes()),
+        };
+        self.frame_writer.get_or_insert_with(FrameWriter::new).send(job);
+
+        Ok(())
+    }
+}
+
+impl<'a> Drop for Screen<'a> {
+    // The frames that are still in pixel buffers have to be read back while the GL
+    // context is alive, so that a recording is complete even without
+    // screen.stop_recording(). The writing thread then finishes them when the
+    // FrameWriter is dropped.
+    fn drop(&mut self) {
+        let _ = self.end_recording();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::VideoSink;
+
+    #[test]
+    fn y4m() {
+        let path = env::temp_dir().join(format!("processing-rs-test-{}.y4m", ::std::process::id()));
+        let filename = path.to_str().unwrap();
+        let (w, h) = (3, 2);
+        let red = [255, 0, 0, 255];
+        let green = [0, 255, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let frames = vec![
+            vec![red, green, blue, red, green, blue],
+            vec![blue, blue, blue, green, green, green],
+        ];
+
+        let mut sink = VideoSink::y4m(filename, w, h, 30).unwrap();
+        for f in &frames {
+            sink.write_frame(f).unwrap();
+        }
+        sink.close().unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let header = b"YUV4MPEG2 W3 H2 F30:1 Ip A1:1 C444 XCOLORRANGE=FULL\n";
+        assert_eq!(&bytes[..header.len()], &header[..]);
+        let n = (w * h) as usize;
+        let frame_len = 6 + 3 * n;
+        assert_eq!(bytes.len(), header.len() + frames.len() * frame_len);
+
+        // full range BT.601 Y, Cb, and Cr of pure red, green, and blue
+        let ycbcr = |p: [u8; 4]| if p == red {
+            [76, 84, 255]
+        } else if p == green {
+            [150, 43, 21]
+        } else {
+            [29, 255, 107]
+        };
+        for (i, f) in frames.iter().enumerate() {
+            let frame = &bytes[header.len() + i * frame_len..header.len() + (i + 1) * frame_len];
+            assert_eq!(&frame[..6], b"FRAME\n");
+            for plane in 0..3 {
+                let expected = f.iter().map(|&p| ycbcr(p)[plane]).collect::<Vec<u8>>();
+                assert_eq!(&frame[6 + plane * n..6 + (plane + 1) * n], &expected[..]);
+            }
+        }
+    }
+}
@@ -3,7 +3,7 @@ use std::io::prelude::*;
 use std::io::BufReader;
 
 use glium;
-use glium::uniforms::Uniforms;
+use glium::uniforms::{Uniforms, UniformValue};
 //use rand;
 //use rand::Rng;
 
@@ -23,30 +23,32 @@ pub struct ShaderInfo<U: Uniforms> {
 
 /// This macro rolls your custom uniforms for your custom shader into the uniform
 /// format expected by glium. It makes things more convienent, because it will add the
-/// current MVP transformation matrix that is shared across all shaders for consistent
+/// current transformation matrices that are shared across all shaders for consistent
 /// rendering to the screen. If your shader will be rendering 2-dimensional or
-/// 3-dimensional shapes to the screen, you will probably want to make use of this
-/// matrix, which will be available as `uniform mat4 MVP` in your custom shader.
+/// 3-dimensional shapes to the screen, you will probably want to make use of the
+/// combined model-view-projection matrix, which will be available as `uniform mat4 MVP`
+/// in your custom shader. The separate matrices are also available, as
+/// `uniform mat4 modelMatrix`, `uniform mat4 viewMatrix` (see screen.camera()), and
+/// `uniform mat4 projectionMatrix` (see screen.perspective()).
 #[macro_export]
 macro_rules! create_uniforms {
     ($screen:ident) => {
         {
-            let m: [[f32; 4]; 4] = $screen.matrices.curr_matrix.into();
-            let m = [[m[0][0], m[0][1], m[0][2], m[0][3]],
-                    [m[1][0], m[1][1], m[1][2], m[1][3]],
-                    [m[2][0], m[2][1], m[2][2], m[2][3]],
-                    [m[3][0], m[3][1], m[3][2], m[3][3]]];
-            uniform!{MVP: m}
+            let mvp: [[f32; 4]; 4] = $screen.matrices.mvp().into();
+            let model: [[f32; 4]; 4] = $screen.matrices.curr_matrix.into();
+            let view: [[f32; 4]; 4] = $screen.matrices.view_matrix.into();
+            let projection: [[f32; 4]; 4] = $screen.matrices.projection_matrix.into();
+            uniform!{MVP: mvp, modelMatrix: model, viewMatrix: view, projectionMatrix: projection}
         }
     };
     ($screen:ident, $($uniformName:ident: $value:expr),+) => {
         {
-            let m: [[f32; 4]; 4] = $screen.matrices.curr_matrix.into();
-            let m = [[m[0][0], m[0][1], m[0][2], m[0][3]],
-                    [m[1][0], m[1][1], m[1][2], m[1][3]],
-                    [m[2][0], m[2][1], m[2][2], m[2][3]],
-                    [m[3][0], m[3][1], m[3][2], m[3][3]]];
-            uniform!{$($uniformName: $value,)+ MVP: m}
+            let mvp: [[f32; 4]; 4] = $screen.matrices.mvp().into();
+            let model: [[f32; 4]; 4] = $screen.matrices.curr_matrix.into();
+            let view: [[f32; 4]; 4] = $screen.matrices.view_matrix.into();
+            let projection: [[f32; 4]; 4] = $screen.matrices.projection_matrix.into();
+            uniform!{$($uniformName: $value,)+ MVP: mvp, modelMatrix: model, viewMatrix: view,
+                     projectionMatrix: projection}
         }
     }
 }
@@ -87,14 +89,624 @@ impl<U: Uniforms> ShaderInfo<U> {
     }
 }
 
-/// Processing has three ways to feed data through a shader: as points, as lines,
-/// and as triangles. The distinction is not as necessary here and may go away in time.
-/// Regardless, this hasn't been actually implemented yet, but if you want more info,
-/// check the official Processing reference for now.
-enum DrawType {
-    POINT,
-    LINE,
-    TRIANGLE,
+/// Wraps the uniforms of a custom shader and adds the current tint color to them,
+/// which is available as `uniform vec4 tint` in the shader. It is used when drawing
+/// Moulds, so that they can follow screen.tint() if they want to. If your own uniforms
+/// already contain one called `tint`, yours is used instead.
+pub struct TintedUniforms<'u, U: Uniforms + 'u> {
+    uniforms: &'u U,
+    tint: [f32; 4],
+}
+
+impl<'u, U: Uniforms> TintedUniforms<'u, U> {
+    pub fn new(uniforms: &'u U, tint: [f32; 4]) -> Self {
+        TintedUniforms {
+            uniforms: uniforms,
+            tint: tint,
+        }
+    }
+}
+
+impl<'u, U: Uniforms> Uniforms for TintedUniforms<'u, U> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut f: F) {
+        let mut has_tint = false;
+        self.uniforms.visit_values(|name, _| if name == "tint" {
+            has_tint = true;
+        });
+        if !has_tint {
+            f("tint", UniformValue::Vec4(self.tint));
+        }
+        self.uniforms.visit_values(f);
+    }
+}
+
+/// Where the stroke program lives in the shader bank. It comes right after the four
+/// standard programs that are set up when a Screen is created.
+pub const STROKE_SHADER: usize = 4;
+/// Where the instanced versions of the basic, textured, and stroke programs live in
+/// the shader bank. They are used by screen.draw_instanced().
+pub const INSTANCED_SHADER: usize = 5;
+pub const INSTANCED_TEXTURED_SHADER: usize = 6;
+pub const INSTANCED_STROKE_SHADER: usize = 7;
+/// Where the lit versions of the basic and textured programs live in the shader bank.
+/// screen.draw() switches to them when there are lights and the shape has normals.
+pub const LIT_SHADER: usize = 8;
+pub const LIT_TEXTURED_SHADER: usize = 9;
+/// Where the program that draws the immediate-mode shapes lives in the shader bank.
+pub const BATCH_SHADER: usize = 10;
+
+/// Compile the program that turns outlines into thick strokes. It takes lines with
+/// adjacency information (see the stroke index buffers of the shapes) and uses a
+/// geometry shader to expand every line into a strip of triangles that is exactly
+/// strokeWeight pixels wide on the framebuffer, no matter how the shape has been
+/// transformed. The adjacent vertices are used to join connected lines together
+/// according to strokeJoin (0 = MITER, 1 = BEVEL, 2 = ROUND), while loose ends get
+/// a cap according to strokeCap (0 = SQUARE, 1 = PROJECT, 2 = ROUND).
+pub fn init_stroke_shader(
+    display: &glium::backend::Facade,
+    glsl_version: &str,
+) -> Result<glium::program::Program, ProcessingErr> {
+    let vsh = "
+    #version "
+        .to_owned() + glsl_version +
+        "
+
+    in vec3 position;
+    in vec4 color;
+
+    out vec4 vColor;
+
+    uniform mat4 MVP;
+
+    void main() {
+        vColor = color;
+        gl_Position = MVP * vec4(position, 1.0);
+    }
+    ";
+
+    stroke_program(display, &vsh, glsl_version)
+}
+
+// Link a vertex shader, which has to output gl_Position and vColor, with the geometry
+// and fragment shaders that turn lines into strokes.
+fn stroke_program(
+    display: &glium::backend::Facade,
+    vsh: &str,
+    glsl_version: &str,
+) -> Result<glium::program::Program, ProcessingErr> {
+    let gsh = "
+    #version "
+        .to_owned() + glsl_version +
+        "
+
+    layout(lines_adjacency) in;
+    layout(triangle_strip, max_vertices = 64) out;
+
+    in vec4 vColor[];
+
+    out vec4 gColor;
+
+    uniform vec2 viewport;
+    uniform float strokeWeight;
+    uniform int strokeCap;
+    uniform int strokeJoin;
+
+    const int ROUND_STEPS = 8;
+    const float MITER_LIMIT = 4.0;
+    const float PI = 3.14159265;
+    // how far in front of the eye a point has to be to be projected
+    const float NEAR_W = 0.0001;
+
+    // from clip space to pixels, measured from the center of the framebuffer
+    vec2 toScreen(vec4 p) {
+        return p.xy / p.w * viewport * 0.5;
+    }
+
+    // the point where the line from a, in front of the eye, to b, behind it, crosses
+    // the plane just in front of the eye
+    float nearCut(vec4 a, vec4 b) {
+        return (a.w - NEAR_W) / (a.w - b.w);
+    }
+
+    // Emit the point p, in pixels, at the depth of the end of the line at clip space
+    // position base. The point keeps the w of base, so that it is clipped and its
+    // color is interpolated in perspective.
+    void emit(vec2 p, vec4 base, vec4 color) {
+        gl_Position = vec4(p / (viewport * 0.5) * base.w, base.z, base.w);
+        gColor = color;
+        EmitVertex();
+    }
+
+    // triangles around c, covering the sector swept by v when it is rotated by angle
+    void fan(vec2 c, vec2 v, float angle, vec4 base, vec4 color) {
+        for (int i = 0; i < ROUND_STEPS; i++) {
+            float a0 = angle * float(i) / float(ROUND_STEPS);
+            float a1 = angle * float(i + 1) / float(ROUND_STEPS);
+            emit(c, base, color);
+            emit(c + mat2(cos(a0), sin(a0), -sin(a0), cos(a0)) * v, base, color);
+            emit(c + mat2(cos(a1), sin(a1), -sin(a1), cos(a1)) * v, base, color);
+            EndPrimitive();
+        }
+    }
+
+    // Half of the miter at the corner where a line going in direction a turns into
+    // one going in direction b, or zero if the miter would be too long.
+    vec2 miter(vec2 a, vec2 b, float r) {
+        vec2 na = vec2(-a.y, a.x);
+        vec2 nb = vec2(-b.y, b.x);
+        if (length(na + nb) < 0.0001) {
+            return vec2(0.0);
+        }
+        vec2 m = normalize(na + nb);
+        float d = dot(m, nb);
+        if (d < 1.0 / MITER_LIMIT) {
+            return vec2(0.0);
+        }
+        return m * r / d;
+    }
+
+    void main() {
+        vec4 q0 = gl_in[0].gl_Position;
+        vec4 q1 = gl_in[1].gl_Position;
+        vec4 q2 = gl_in[2].gl_Position;
+        vec4 q3 = gl_in[3].gl_Position;
+        vec4 c1 = vColor[1];
+        vec4 c2 = vColor[2];
+        float r = strokeWeight / 2.0;
+
+        // Cut the line where it goes behind the eye, since points there would be
+        // projected through the origin. The cut end gets neither a cap nor a join.
+        bool cut1 = q1.w <= NEAR_W;
+        bool cut2 = q2.w <= NEAR_W;
+        if (cut1 && cut2) {
+            return;
+        } else if (cut1) {
+            float t = nearCut(q2, q1);
+            q1 = mix(q2, q1, t);
+            c1 = mix(c2, c1, t);
+        } else if (cut2) {
+            float t = nearCut(q1, q2);
+            q2 = mix(q1, q2, t);
+            c2 = mix(c1, c2, t);
+        }
+        // neighbours behind the eye are cut in the same way, so that the joins
+        // still point along the visible part of the neighbouring lines
+        if (q0.w <= NEAR_W) {
+            q0 = mix(q1, q0, nearCut(q1, q0));
+        }
+        if (q3.w <= NEAR_W) {
+            q3 = mix(q2, q3, nearCut(q2, q3));
+        }
+
+        vec2 p0 = toScreen(q0);
+        vec2 p1 = toScreen(q1);
+        vec2 p2 = toScreen(q2);
+        vec2 p3 = toScreen(q3);
+
+        if (length(p2 - p1) < 0.0001) {
+            return;
+        }
+        vec2 dir = normalize(p2 - p1);
+        vec2 n = vec2(-dir.y, dir.x) * r;
+        bool hasPrev = !cut1 && length(p1 - p0) > 0.0001;
+        bool hasNext = !cut2 && length(p3 - p2) > 0.0001;
+
+        vec2 s0 = p1 + n;
+        vec2 s1 = p1 - n;
+        vec2 e0 = p2 + n;
+        vec2 e1 = p2 - n;
+
+        if (hasPrev) {
+            vec2 prevDir = normalize(p1 - p0);
+            vec2 m = strokeJoin == 0 ? miter(prevDir, dir, r) : vec2(0.0);
+            if (m != vec2(0.0)) {
+                s0 = p1 + m;
+                s1 = p1 - m;
+            } else {
+                // fill the gap on the outside of the corner; the line that starts
+                // at a corner is the one that does this
+                vec2 prevN = vec2(-prevDir.y, prevDir.x) * r;
+                float turn = prevDir.x * dir.y - prevDir.y * dir.x;
+                vec2 from = turn > 0.0 ? -prevN : prevN;
+                vec2 to = turn > 0.0 ? -n : n;
+                if (strokeJoin == 2) {
+                    float angle = atan(from.x * to.y - from.y * to.x, dot(from, to));
+                    fan(p1, from, angle, q1, c1);
+                } else {
+                    emit(p1, q1, c1);
+                    emit(p1 + from, q1, c1);
+                    emit(p1 + to, q1, c1);
+                    EndPrimitive();
+                }
+            }
+        } else if (!cut1 && strokeCap == 1) {
+            s0 -= dir * r;
+            s1 -= dir * r;
+        } else if (!cut1 && strokeCap == 2) {
+            fan(p1, n, PI, q1, c1);
+        }
+
+        if (hasNext) {
+            vec2 m = strokeJoin == 0 ? miter(dir, normalize(p3 - p2), r) : vec2(0.0);
+            if (m != vec2(0.0)) {
+                e0 = p2 + m;
+                e1 = p2 - m;
+            }
+        } else if (!cut2 && strokeCap == 1) {
+            e0 += dir * r;
+            e1 += dir * r;
+        } else if (!cut2 && strokeCap == 2) {
+            fan(p2, -n, PI, q2, c2);
+        }
+
+        emit(s0, q1, c1);
+        emit(s1, q1, c1);
+        emit(e0, q2, c2);
+        emit(e1, q2, c2);
+        EndPrimitive();
+    }
+    ";
+
+    let fsh = "
+    #version "
+        .to_owned() + glsl_version +
+        "
+
+    in vec4 gColor;
+
+    out vec4 outColor;
+
+    void main() {
+        outColor = gColor;
+    }
+    ";
+
+    glium::Program::new(
+        display,
+        glium::program::ProgramCreationInput::SourceCode {
+            vertex_shader: vsh,
+            tessellation_control_shader: None,
+            tessellation_evaluation_shader: None,
+            geometry_shader: Some(&gsh),
+            fragment_shader: &fsh,
+            transform_feedback_varyings: None,
+            outputs_srgb: true,
+            uses_point_size: false,
+        },
+    ).map_err(|e| ProcessingErr::ShaderCompileFail(e))
+}
+
+
+// Per-instance attributes, shared by all of the instanced vertex shaders. The shape is
+// scaled and rotated around its own origin before it is moved to the instance's offset.
+// This happens before the aspect ratio correction, which the shapes have already
+// applied to their vertices, so it is undone first and then done again.
+const INSTANCE_TRANSFORM: &str = "
+    in vec3 offset;
+    in vec3 scale;
+    in float rotation;
+    in vec4 instance_color;
+
+    uniform mat4 MVP;
+    uniform vec2 aspect;
+
+    vec4 instancePosition(vec3 p) {
+        p.xy /= aspect;
+        p *= scale;
+        float c = cos(rotation);
+        float s = sin(rotation);
+        p = vec3(c * p.x - s * p.y, s * p.x + c * p.y, p.z) + offset;
+        p.xy *= aspect;
+        return MVP * vec4(p, 1.0);
+    }
+";
+
+/// Compile the programs that draw many copies of a shape in one go (see
+/// shapes::instanced::Instanced). They work like the basic, textured, and stroke
+/// programs, except that every copy is moved, scaled, rotated, and colored according
+/// to its own per-instance attributes.
+pub fn init_instanced_shaders(
+    display: &glium::backend::Facade,
+    glsl_version: &str,
+) -> Result<Vec<glium::program::Program>, ProcessingErr> {
+    let vsh = "
+    #version "
+        .to_owned() + glsl_version + INSTANCE_TRANSFORM +
+        "
+    in vec3 position;
+    in vec4 color;
+
+    out vec4 vColor;
+
+    void main() {
+        vColor = color * instance_color;
+        gl_Position = instancePosition(position);
+    }
+    ";
+
+    let fsh = "
+    #version "
+        .to_owned() + glsl_version +
+        "
+
+    in vec4 vColor;
+
+    out vec4 outColor;
+
+    void main() {
+        outColor = vColor;
+    }
+    ";
+
+    let vsh_tex = "
+    #version "
+        .to_owned() + glsl_version + INSTANCE_TRANSFORM +
+        "
+    in vec3 position;
+    in vec4 color;
+    in vec2 texcoord;
+
+    out vec4 vColor;
+    out vec2 Texcoord;
+
+    void main() {
+        vColor = color * instance_color;
+        Texcoord = texcoord;
+        gl_Position = instancePosition(position);
+    }
+    ";
+
+    let fsh_tex = "
+    #version "
+        .to_owned() + glsl_version +
+        "
+
+    in vec4 vColor;
+    in vec2 Texcoord;
+
+    out vec4 outColor;
+
+    uniform sampler2D tex;
+    uniform vec4 tint;
+
+    void main() {
+        outColor = texture(tex, Texcoord) * vColor * tint;
+    }
+    ";
+
+    let mut programs = Vec::new();
+    for &(v, f) in &[(&vsh, &fsh), (&vsh_tex, &fsh_tex)] {
+        let program = glium::Program::new(
+            display,
+            glium::program::ProgramCreationInput::SourceCode {
+                vertex_shader: v,
+                tessellation_control_shader: None,
+                tessellation_evaluation_shader: None,
+                geometry_shader: None,
+                fragment_shader: f,
+                transform_feedback_varyings: None,
+                outputs_srgb: true,
+                uses_point_size: true,
+            },
+        ).map_err(|e| ProcessingErr::ShaderCompileFail(e))?;
+        programs.push(program);
+    }
+    programs.push(stroke_program(display, &vsh, glsl_version)?);
+
+    Ok(programs)
+}
+
+// The lighting model, shared by the lit fragment shaders. It follows Processing's: every
+// light adds its ambient color, its diffuse color weighted by the angle between the
+// surface and the light (Lambert), and its specular color weighted by the Blinn-Phong
+// highlight, all scaled by the light's falloff with distance and, for spot lights, by
+// the cone of the spot. Directional and ambient lights have a w of 0 in lightPosition.
+// Lighting happens in the coordinates of the camera, i.e., after the model and view
+// matrices have been applied. With a perspective projection, the viewer is at the
+// origin, otherwise they look down the negative z-axis from infinitely far away.
+const LIGHTING: &str = "
+    const int MAX_LIGHTS = 8;
+
+    uniform int lightCount;
+    uniform vec4 lightPosition[MAX_LIGHTS];
+    uniform vec3 lightNormal[MAX_LIGHTS];
+    uniform vec3 lightAmbient[MAX_LIGHTS];
+    uniform vec3 lightDiffuse[MAX_LIGHTS];
+    uniform vec3 lightSpecular[MAX_LIGHTS];
+    uniform vec3 lightFalloff[MAX_LIGHTS];
+    uniform vec2 lightSpot[MAX_LIGHTS];
+
+    uniform vec4 ambientMaterial;
+    uniform vec3 specularMaterial;
+    uniform vec3 emissiveMaterial;
+    uniform float shininess;
+
+    uniform mat4 projectionMatrix;
+
+    vec4 shade(vec4 base, vec3 ecPosition, vec3 ecNormal) {
+        vec3 n = normalize(ecNormal);
+        if (!gl_FrontFacing) {
+            n = -n;
+        }
+        vec3 eye = projectionMatrix[3][3] == 0.0 ? -normalize(ecPosition) : vec3(0.0, 0.0, 1.0);
+
+        vec3 totalAmbient = vec3(0.0);
+        vec3 totalDiffuse = vec3(0.0);
+        vec3 totalSpecular = vec3(0.0);
+        for (int i = 0; i < lightCount; i++) {
+            vec3 dir = -lightNormal[i];
+            float falloff = 1.0;
+            float spot = 1.0;
+            if (lightPosition[i].w != 0.0) {
+                vec3 d = lightPosition[i].xyz - ecPosition;
+                float dist = length(d);
+                dir = d / max(dist, 1e-6);
+                falloff = 1.0 / dot(lightFalloff[i], vec3(1.0, dist, dist * dist));
+                if (lightSpot[i].x > -1.0) {
+                    float c = dot(-dir, lightNormal[i]);
+                    spot = c >= lightSpot[i].x ? pow(max(c, 0.0), lightSpot[i].y) : 0.0;
+                }
+            }
+
+            float lambert = max(dot(n, dir), 0.0);
+            totalAmbient += lightAmbient[i] * falloff;
+            totalDiffuse += lightDiffuse[i] * falloff * spot * lambert;
+            if (lambert > 0.0) {
+                float highlight = pow(max(dot(n, normalize(dir + eye)), 0.0), shininess);
+                totalSpecular += lightSpecular[i] * falloff * spot * highlight;
+            }
+        }
+
+        // without a material ambient color, surfaces reflect ambient light in their fill color
+        vec3 ambient = ambientMaterial.a > 0.0 ? ambientMaterial.rgb : base.rgb;
+        return vec4(totalAmbient * ambient + totalDiffuse * base.rgb +
+                    totalSpecular * specularMaterial + emissiveMaterial, base.a);
+    }
+";
+
+/// Compile the programs that draw shapes with lighting (see screen.lights()). They
+/// work like the basic and textured programs, except that the color of every fragment
+/// is computed from the lights, the material (see screen.specular() and friends), and
+/// the normal of the shape, which is why they are only used for shapes with normals.
+pub fn init_lit_shaders(
+    display: &glium::backend::Facade,
+    glsl_version: &str,
+) -> Result<Vec<glium::program::Program>, ProcessingErr> {
+    let vsh = "
+    #version "
+        .to_owned() + glsl_version +
+        "
+
+    in vec3 position;
+    in vec4 color;
+    in vec2 texcoord;
+    in vec3 normal;
+
+    out vec4 vColor;
+    out vec2 Texcoord;
+    out vec3 ecPosition;
+    out vec3 ecNormal;
+
+    uniform mat4 MVP;
+    uniform mat4 modelMatrix;
+    uniform mat4 viewMatrix;
+
+    void main() {
+        vColor = color;
+        Texcoord = texcoord;
+        vec4 p = viewMatrix * modelMatrix * vec4(position, 1.0);
+        ecPosition = p.xyz / p.w;
+        ecNormal = transpose(inverse(mat3(viewMatrix * modelMatrix))) * normal;
+        gl_Position = MVP * vec4(position, 1.0);
+    }
+    ";
+
+    let fsh = "
+    #version "
+        .to_owned() + glsl_version + LIGHTING +
+        "
+    in vec4 vColor;
+    in vec2 Texcoord;
+    in vec3 ecPosition;
+    in vec3 ecNormal;
+
+    out vec4 outColor;
+
+    void main() {
+        outColor = shade(vColor, ecPosition, ecNormal);
+    }
+    ";
+
+    let fsh_tex = "
+    #version "
+        .to_owned() + glsl_version + LIGHTING +
+        "
+    in vec4 vColor;
+    in vec2 Texcoord;
+    in vec3 ecPosition;
+    in vec3 ecNormal;
+
+    out vec4 outColor;
+
+    uniform sampler2D tex;
+    uniform vec4 tint;
+
+    void main() {
+        outColor = shade(texture(tex, Texcoord) * vColor, ecPosition, ecNormal) * tint;
+    }
+    ";
+
+    let mut programs = Vec::new();
+    for f in &[&fsh, &fsh_tex] {
+        let program = glium::Program::new(
+            display,
+            glium::program::ProgramCreationInput::SourceCode {
+                vertex_shader: &vsh,
+                tessellation_control_shader: None,
+                tessellation_evaluation_shader: None,
+                geometry_shader: None,
+                fragment_shader: f,
+                transform_feedback_varyings: None,
+                outputs_srgb: true,
+                uses_point_size: true,
+            },
+        ).map_err(|e| ProcessingErr::ShaderCompileFail(e))?;
+        programs.push(program);
+    }
+
+    Ok(programs)
+}
+
+/// Compile the program that draws the shapes of the immediate-mode functions, such as
+/// screen.rect() (see screen.flush()). Their vertices are already in clip space, so
+/// they are passed on unchanged.
+pub fn init_batch_shader(
+    display: &glium::backend::Facade,
+    glsl_version: &str,
+) -> Result<glium::program::Program, ProcessingErr> {
+    let vsh = "
+    #version "
+        .to_owned() + glsl_version +
+        "
+
+    in vec4 position;
+    in vec4 color;
+
+    out vec4 vColor;
+
+    void main() {
+        vColor = color;
+        gl_Position = position;
+    }
+    ";
+
+    let fsh = "
+    #version "
+        .to_owned() + glsl_version +
+        "
+
+    in vec4 vColor;
+
+    out vec4 outColor;
+
+    void main() {
+        outColor = vColor;
+    }
+    ";
+
+    glium::Program::new(
+        display,
+        glium::program::ProgramCreationInput::SourceCode {
+            vertex_shader: &vsh,
+            tessellation_control_shader: None,
+            tessellation_evaluation_shader: None,
+            geometry_shader: None,
+            fragment_shader: &fsh,
+            transform_feedback_varyings: None,
+            outputs_srgb: true,
+            uses_point_size: false,
+        },
+    ).map_err(|e| ProcessingErr::ShaderCompileFail(e))
 }
 
 impl<'a> Screen<'a> {
@@ -2,10 +2,14 @@ use std::f32;
 
 use glium;
 
-use {Screen, ScreenType};
+use Screen;
 use errors::ProcessingErr;
 
-use shapes::{Shape, ShapeVertex, IndexType, load_colors};
+use shapes::{Shape, ShapeVertex, ShapeData, IndexType, check_lengths, outline_edges,
+             stroke_indices, fan_triangles, triangle_indices};
+
+// how many vertices each arc is made of, including the one at its center
+const NUM_SLICES: f64 = 200.0 + 2.0;
 
 /// An arc is essentially just part of an ellipse, so it traces out part of the contour
 /// by starting at a certain position (specified in radians) and ending at a certain
@@ -15,6 +19,7 @@ pub struct Arc {
     stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
     fill_index_buffer: IndexType,
     stroke_index_buffer: IndexType,
+    data: ShapeData,
 }
 
 
@@ -56,6 +61,42 @@ impl Arc {
         starti: &[f64],
         stopi: &[f64],
     ) -> Result<Self, ProcessingErr> {
+        let (shape, n) = Arc::vertices(screen, xci, yci, zci, wi, hi, starti, stopi)?;
+        let data = ShapeData::new(screen, shape, n);
+        let (fill_shape_buffer, stroke_shape_buffer) = data.buffers(screen)?;
+
+        Ok(Arc {
+            fill_buffer: fill_shape_buffer,
+            stroke_buffer: stroke_shape_buffer,
+            fill_index_buffer: triangle_indices(screen, &fan_triangles(n, NUM_SLICES as usize))?,
+            stroke_index_buffer: stroke_indices(
+                screen,
+                &outline_edges(n, NUM_SLICES as usize, 1, NUM_SLICES as usize - 1, false),
+            )?,
+            data: data,
+        })
+    }
+
+    // the vertices of all of the sub-shapes, and how many of them there are
+    fn vertices(
+        screen: &Screen,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        wi: &[f64],
+        hi: &[f64],
+        starti: &[f64],
+        stopi: &[f64],
+    ) -> Result<(Vec<ShapeVertex>, usize), ProcessingErr> {
+        let n = check_lengths(&[
+            xci.len(),
+            yci.len(),
+            zci.len(),
+            wi.len(),
+            hi.len(),
+            starti.len(),
+            stopi.len(),
+        ])?;
         let mut xc = xci.iter().map(|&v| v).collect::<Vec<f64>>();
         let mut yc = yci.iter().map(|&v| v).collect::<Vec<f64>>();
         let zc = zci.iter().map(|&v| v).collect::<Vec<f64>>();
@@ -94,7 +135,7 @@ impl Arc {
             //  h = h ./ 2
         }
 
-        let num_slices = 200.0 + 2.0;
+        let num_slices = NUM_SLICES;
         let mut cs: Vec<Vec<f64>> = Vec::with_capacity(xc.len());
         let mut ss: Vec<Vec<f64>> = Vec::with_capacity(xc.len());
         for j in 0..xc.len() {
@@ -130,6 +171,7 @@ impl Arc {
                         ],
                         color: [0.0, 0.0, 0.0, 0.0],
                         texcoord: [0f32, 0.],
+                        normal: [0.0, 0.0, 0.0],
                     }
                 } else {
                     ShapeVertex {
@@ -144,41 +186,43 @@ impl Arc {
                         ],
                         color: [0.0, 0.0, 0.0, 0.0],
                         texcoord: [0f32, 0.],
+                        normal: [0.0, 0.0, 0.0],
                     }
                 };
                 shape.push(vertex);
             }
         }
 
-        load_colors(&mut shape, &screen.fill_col);
-        let fill_shape_buffer = match screen.display {
-            ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-        };
-
-        load_colors(&mut shape, &screen.stroke_col);
-        let stroke_shape_buffer = match screen.display {
-            ScreenType::Window(ref d) => {
-                glium::VertexBuffer::new(d, &shape[1..shape.len() - 1])
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?
-            }
-            ScreenType::Headless(ref d) => {
-                glium::VertexBuffer::new(d, &shape[1..shape.len() - 1])
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?
-            }
-        };
+        Ok((shape, n))
+    }
 
-        Ok(Arc {
-            fill_buffer: fill_shape_buffer,
-            stroke_buffer: stroke_shape_buffer,
-            fill_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::TriangleFan),
-            },
-            stroke_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::LineStrip),
-            },
-        })
+	/// Move, resize, or reshape the arcs of this Arc without creating a new one. The
+	/// arguments are the same as for Arc::new(), but they have to describe the same
+	/// number of arcs as before. The colors stay as they are.
+    pub fn update(
+        &mut self,
+        screen: &Screen,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        wi: &[f64],
+        hi: &[f64],
+        starti: &[f64],
+        stopi: &[f64],
+    ) -> Result<(), ProcessingErr> {
+        let (shape, n) = Arc::vertices(screen, xci, yci, zci, wi, hi, starti, stopi)?;
+        self.data.set_vertices(shape, n, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+	/// Change the fill color of this shape in place. As in screen.fill(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_fill(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_fill(color_mat, &self.fill_buffer)
+    }
+
+	/// Change the stroke color of this shape in place. As in screen.stroke(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_stroke(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_stroke(color_mat, &self.fill_buffer, &self.stroke_buffer)
     }
 }
@@ -0,0 +1,172 @@
+use glium;
+
+use Screen;
+use errors::ProcessingErr;
+use textures::TextureSampling;
+
+use shapes::{Shape, ShapeVertex, ShapeData, IndexType, Mesh, check_lengths};
+
+/// A box is a rectangular volume with a separate width, height, and depth, which run
+/// along the x-, y-, and z-axes, respectively. (It is called BoxShape so that it
+/// does not get confused with Rust's Box.) Each of its six faces is flat, with its
+/// own normal, and shows the whole of an attached texture.
+pub struct BoxShape<'a> {
+    fill_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    fill_index_buffer: IndexType,
+    stroke_index_buffer: IndexType,
+    texture: Option<&'a glium::texture::Texture2d>,
+    texture_sampling: Option<TextureSampling>,
+    data: ShapeData,
+}
+
+impl<'a> Shape for BoxShape<'a> {
+    fn fill_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.fill_buffer)
+    }
+
+    fn stroke_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.stroke_buffer)
+    }
+
+    fn fill_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.fill_index_buffer)
+    }
+
+    fn stroke_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.stroke_index_buffer)
+    }
+
+    fn get_texture(&self) -> Option<Box<&glium::texture::Texture2d>> {
+        match self.texture {
+            Some(t) => Some(Box::new(t)),
+            _ => None,
+        }
+    }
+
+    fn get_texture_sampling(&self) -> Option<TextureSampling> {
+        self.texture_sampling
+    }
+
+    fn has_normals(&self) -> bool {
+        true
+    }
+}
+
+impl<'a> BoxShape<'a> {
+	/// Create a new BoxShape to be drawn later. It has a center position
+	/// (xci, yci, zci), a width (wi), a height (hi), and a depth (di).
+    pub fn new(
+        screen: &Screen,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        wi: &[f64],
+        hi: &[f64],
+        di: &[f64],
+    ) -> Result<Self, ProcessingErr> {
+        let mesh = BoxShape::mesh();
+        let (shape, n) = BoxShape::vertices(screen, &mesh, xci, yci, zci, wi, hi, di)?;
+        let (fill_index_buffer, stroke_index_buffer) = mesh.indices(screen, n)?;
+        let data = ShapeData::new(screen, shape, n);
+        let (fill_shape_buffer, stroke_shape_buffer) = data.buffers(screen)?;
+
+        Ok(BoxShape {
+            fill_buffer: fill_shape_buffer,
+            stroke_buffer: stroke_shape_buffer,
+            fill_index_buffer: fill_index_buffer,
+            stroke_index_buffer: stroke_index_buffer,
+            texture: None,
+            texture_sampling: None,
+            data: data,
+        })
+    }
+
+    // A box with sides of length 1 around the origin. Every face is given by its normal
+    // and by the directions in which its texture runs to the right and up.
+    fn mesh() -> Mesh {
+        let faces = [
+            ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+            ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+            ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+            ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ];
+
+        let mut mesh = Mesh::new();
+        for &(normal, right, up) in &faces {
+            let mut face = Mesh::grid(1, 1, |u, v| {
+                let mut p = [0.0; 3];
+                for a in 0..3 {
+                    p[a] = 0.5 * normal[a] + (u - 0.5) * right[a] + (0.5 - v) * up[a];
+                }
+                (p, normal)
+            });
+            // just the outline of the face, going around it
+            face.edges = vec![[0, 1], [1, 3], [3, 2], [2, 0]];
+            mesh.append(face);
+        }
+
+        mesh
+    }
+
+    // the vertices of all of the sub-shapes, and how many of them there are
+    fn vertices(
+        screen: &Screen,
+        mesh: &Mesh,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        wi: &[f64],
+        hi: &[f64],
+        di: &[f64],
+    ) -> Result<(Vec<ShapeVertex>, usize), ProcessingErr> {
+        let n = check_lengths(&[xci.len(), yci.len(), zci.len(), wi.len(), hi.len(), di.len()])?;
+        let mut shape = Vec::with_capacity(n * mesh.positions.len());
+        for i in 0..n {
+            mesh.place(screen, [xci[i], yci[i], zci[i]], [wi[i], hi[i], di[i]], &mut shape);
+        }
+
+        Ok((shape, n))
+    }
+
+	/// Move and resize the boxes of this BoxShape without creating a new one. There
+	/// have to be as many boxes as before. The colors stay as they are.
+    pub fn update(
+        &mut self,
+        screen: &Screen,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        wi: &[f64],
+        hi: &[f64],
+        di: &[f64],
+    ) -> Result<(), ProcessingErr> {
+        let mesh = BoxShape::mesh();
+        let (shape, n) = BoxShape::vertices(screen, &mesh, xci, yci, zci, wi, hi, di)?;
+        self.data.set_vertices(shape, n, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+	/// Change the fill color of this shape in place. As in screen.fill(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_fill(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_fill(color_mat, &self.fill_buffer)
+    }
+
+	/// Change the stroke color of this shape in place. As in screen.stroke(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_stroke(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_stroke(color_mat, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+    pub fn attach_texture(&mut self, tex: &'a glium::texture::Texture2d) {
+        self.texture = Some(tex);
+    }
+
+	/// Give this shape its own texture sampling, which takes the place of the global
+	/// one (see screen.texture_wrap() and friends) whenever it is drawn.
+    pub fn texture_sampling(&mut self, sampling: TextureSampling) {
+        self.texture_sampling = Some(sampling);
+    }
+}
@@ -0,0 +1,164 @@
+use std::f64;
+
+use glium;
+
+use Screen;
+use errors::ProcessingErr;
+use textures::TextureSampling;
+
+use shapes::{Shape, ShapeVertex, ShapeData, IndexType, Mesh, check_lengths};
+
+/// A cone narrows from a circular base to a single point, its apex. It stands upright,
+/// with the apex at the top and the base, which is closed off, at the bottom. Its side
+/// has smooth normals and an attached texture is wrapped around it, with the left and
+/// right edges of the texture meeting at the back.
+pub struct Cone<'a> {
+    fill_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    fill_index_buffer: IndexType,
+    stroke_index_buffer: IndexType,
+    texture: Option<&'a glium::texture::Texture2d>,
+    texture_sampling: Option<TextureSampling>,
+    detail: usize,
+    data: ShapeData,
+}
+
+impl<'a> Shape for Cone<'a> {
+    fn fill_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.fill_buffer)
+    }
+
+    fn stroke_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.stroke_buffer)
+    }
+
+    fn fill_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.fill_index_buffer)
+    }
+
+    fn stroke_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.stroke_index_buffer)
+    }
+
+    fn get_texture(&self) -> Option<Box<&glium::texture::Texture2d>> {
+        match self.texture {
+            Some(t) => Some(Box::new(t)),
+            _ => None,
+        }
+    }
+
+    fn get_texture_sampling(&self) -> Option<TextureSampling> {
+        self.texture_sampling
+    }
+
+    fn has_normals(&self) -> bool {
+        true
+    }
+}
+
+impl<'a> Cone<'a> {
+	/// Create a new Cone to be drawn later. It has a center position
+	/// (xci, yci, zci), halfway between its base and its apex, the radius of its base
+	/// (ri), and a height (hi). The number of pieces it is made of around its axis is
+	/// taken from screen.sphere_detail().
+    pub fn new(
+        screen: &Screen,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        ri: &[f64],
+        hi: &[f64],
+    ) -> Result<Self, ProcessingErr> {
+        let detail = screen.sphere_detail.0;
+        let mesh = Cone::mesh(detail);
+        let (shape, n) = Cone::vertices(screen, &mesh, xci, yci, zci, ri, hi)?;
+        let (fill_index_buffer, stroke_index_buffer) = mesh.indices(screen, n)?;
+        let data = ShapeData::new(screen, shape, n);
+        let (fill_shape_buffer, stroke_shape_buffer) = data.buffers(screen)?;
+
+        Ok(Cone {
+            fill_buffer: fill_shape_buffer,
+            stroke_buffer: stroke_shape_buffer,
+            fill_index_buffer: fill_index_buffer,
+            stroke_index_buffer: stroke_index_buffer,
+            texture: None,
+            texture_sampling: None,
+            detail: detail,
+            data: data,
+        })
+    }
+
+    // A cone with a base of radius 1 and a height of 1 around the origin. Its side
+    // rises at 45 degrees, so the normals there point halfway between outwards and up.
+    fn mesh(detail: usize) -> Mesh {
+        let s = 0.5f64.sqrt();
+        let mut mesh = Mesh::grid(detail, 1, |u, v| {
+            let phi = 2.0 * f64::consts::PI * u - f64::consts::PI;
+            (
+                [v * phi.sin(), 0.5 - v, v * phi.cos()],
+                [s * phi.sin(), s, s * phi.cos()],
+            )
+        });
+        mesh.append(Mesh::disc(detail, -0.5, false));
+
+        mesh
+    }
+
+    // the vertices of all of the sub-shapes, and how many of them there are
+    fn vertices(
+        screen: &Screen,
+        mesh: &Mesh,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        ri: &[f64],
+        hi: &[f64],
+    ) -> Result<(Vec<ShapeVertex>, usize), ProcessingErr> {
+        let n = check_lengths(&[xci.len(), yci.len(), zci.len(), ri.len(), hi.len()])?;
+        let mut shape = Vec::with_capacity(n * mesh.positions.len());
+        for i in 0..n {
+            mesh.place(screen, [xci[i], yci[i], zci[i]], [ri[i], hi[i], ri[i]], &mut shape);
+        }
+
+        Ok((shape, n))
+    }
+
+	/// Move and resize the cones of this Cone without creating a new one. There
+	/// have to be as many cones as before. They keep the detail that they were
+	/// created with, as well as their colors.
+    pub fn update(
+        &mut self,
+        screen: &Screen,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        ri: &[f64],
+        hi: &[f64],
+    ) -> Result<(), ProcessingErr> {
+        let mesh = Cone::mesh(self.detail);
+        let (shape, n) = Cone::vertices(screen, &mesh, xci, yci, zci, ri, hi)?;
+        self.data.set_vertices(shape, n, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+	/// Change the fill color of this shape in place. As in screen.fill(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_fill(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_fill(color_mat, &self.fill_buffer)
+    }
+
+	/// Change the stroke color of this shape in place. As in screen.stroke(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_stroke(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_stroke(color_mat, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+    pub fn attach_texture(&mut self, tex: &'a glium::texture::Texture2d) {
+        self.texture = Some(tex);
+    }
+
+	/// Give this shape its own texture sampling, which takes the place of the global
+	/// one (see screen.texture_wrap() and friends) whenever it is drawn.
+    pub fn texture_sampling(&mut self, sampling: TextureSampling) {
+        self.texture_sampling = Some(sampling);
+    }
+}
@@ -1,9 +1,9 @@
 use glium;
 
-use {Screen, ScreenType};
+use Screen;
 use errors::ProcessingErr;
 
-use shapes::{Shape, ShapeVertex, IndexType, load_colors};
+use shapes::{Shape, ShapeVertex, ShapeData, IndexType};
 
 /// A cube is a three-dimensional volume with equal width, height, and depth, so a box.
 pub struct Cube {
@@ -11,6 +11,7 @@ pub struct Cube {
     stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
     fill_index_buffer: IndexType,
     stroke_index_buffer: IndexType,
+    data: ShapeData,
 }
 
 impl Shape for Cube {
@@ -40,6 +41,62 @@ impl Cube {
 	/// A scale of 1 is the standard "unit" cube and any other values
 	/// give back a version of this cube scaled up or down.
     pub fn new(screen: &Screen, s: &[f64]) -> Result<Self, ProcessingErr> {
+        let (shape, n) = Cube::vertices(s)?;
+        // if RenderState.drawTexture
+        //	// texcoords
+        //	texData = zeros(GLfloat, num_slices*4*len(xc))
+        //	texData[8:vertexStride:end] = 0
+        //	texData[9:vertexStride:end] = 0
+
+        //	texData[17:vertexStride:end] = 1
+        //	texData[18:vertexStride:end] = 0
+
+        //	texData[26:vertexStride:end] = 1
+        //	texData[27:vertexStride:end] = 1
+
+        //	texData[35:vertexStride:end] = 0
+        //	texData[36:vertexStride:end] = 1
+        // end
+
+        // elements = zeros(GLuint, 6*len(x1))
+
+        // elements[1] = 0
+        // elements[2] = 1
+        // elements[3] = 2
+        // elements[4] = 2
+        // elements[5] = 3
+        // elements[6] = 0
+
+        // index = 7
+        // for x = 2:len(x1)
+        //	elements[index] = elements[index-6] + 4
+        //	elements[index+1] = elements[(index-6)+1] + 4
+        //	elements[index+2] = elements[(index-6)+2] + 4
+        //	elements[index+3] = elements[(index-6)+3] + 4
+        //	elements[index+4] = elements[(index-6)+4] + 4
+        //	elements[index+5] = elements[(index-6)+5] + 4
+        //	index += 6
+        // end
+
+        let data = ShapeData::new(screen, shape, n);
+        let (fill_shape_buffer, stroke_shape_buffer) = data.buffers(screen)?;
+
+        Ok(Cube {
+            fill_buffer: fill_shape_buffer,
+            stroke_buffer: stroke_shape_buffer,
+            fill_index_buffer: IndexType::NoBuffer {
+                ind: glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+            },
+            stroke_index_buffer: IndexType::NoBuffer {
+                ind: glium::index::NoIndices(glium::index::PrimitiveType::LineLoop),
+            },
+            data: data,
+        })
+    }
+
+    // the vertices of all of the sub-shapes, and how many of them there are
+    fn vertices(s: &[f64]) -> Result<(Vec<ShapeVertex>, usize), ProcessingErr> {
+        let n = s.len();
         let cube_vertices = [
             -1.0f32,
             -1.0,
@@ -163,74 +220,32 @@ impl Cube {
                     ],
                     color: [0.0, 0.0, 0.0, 0.0],
                     texcoord: [0f32, 0.],
+                    normal: [0.0, 0.0, 0.0],
                 };
                 shape.push(vertex);
                 x += 3;
             }
         }
 
-        // if RenderState.drawTexture
-        //	// texcoords
-        //	texData = zeros(GLfloat, num_slices*4*len(xc))
-        //	texData[8:vertexStride:end] = 0
-        //	texData[9:vertexStride:end] = 0
-
-        //	texData[17:vertexStride:end] = 1
-        //	texData[18:vertexStride:end] = 0
-
-        //	texData[26:vertexStride:end] = 1
-        //	texData[27:vertexStride:end] = 1
-
-        //	texData[35:vertexStride:end] = 0
-        //	texData[36:vertexStride:end] = 1
-        // end
-
-        // elements = zeros(GLuint, 6*len(x1))
+        Ok((shape, n))
+    }
 
-        // elements[1] = 0
-        // elements[2] = 1
-        // elements[3] = 2
-        // elements[4] = 2
-        // elements[5] = 3
-        // elements[6] = 0
+	/// Resize the cubes of this Cube without creating a new one. There have to be
+	/// as many sizes as before. The colors stay as they are.
+    pub fn update(&mut self, s: &[f64]) -> Result<(), ProcessingErr> {
+        let (shape, n) = Cube::vertices(s)?;
+        self.data.set_vertices(shape, n, &self.fill_buffer, &self.stroke_buffer)
+    }
 
-        // index = 7
-        // for x = 2:len(x1)
-        //	elements[index] = elements[index-6] + 4
-        //	elements[index+1] = elements[(index-6)+1] + 4
-        //	elements[index+2] = elements[(index-6)+2] + 4
-        //	elements[index+3] = elements[(index-6)+3] + 4
-        //	elements[index+4] = elements[(index-6)+4] + 4
-        //	elements[index+5] = elements[(index-6)+5] + 4
-        //	index += 6
-        // end
+	/// Change the fill color of this shape in place. As in screen.fill(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_fill(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_fill(color_mat, &self.fill_buffer)
+    }
 
-        load_colors(&mut shape, &screen.fill_col);
-        let fill_shape_buffer = match screen.display {
-            ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-        };
-
-        load_colors(&mut shape, &screen.stroke_col);
-        let stroke_shape_buffer = match screen.display {
-            ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-        };
-
-        // screen.draw(fill_shape_buffer, stroke_shape_buffer, Some(index_buffer));
-        Ok(Cube {
-            fill_buffer: fill_shape_buffer,
-            stroke_buffer: stroke_shape_buffer,
-            fill_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
-            },
-            stroke_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::LineLoop),
-            },
-        })
+	/// Change the stroke color of this shape in place. As in screen.stroke(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_stroke(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_stroke(color_mat, &self.fill_buffer, &self.stroke_buffer)
     }
 }
@@ -0,0 +1,434 @@
+use glium;
+
+use {Screen, ScreenType};
+use errors::ProcessingErr;
+
+use shapes::{Shape, ShapeVertex, IndexType, nth_color, check_lengths, subdivide_curve,
+             stroke_indices};
+use shapes::custom::tessellate;
+
+/// A Bezier curve runs from one anchor point to another and is pulled towards two
+/// control points along the way, without usually passing through them.
+pub struct Bezier {
+    fill_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    fill_index_buffer: IndexType,
+    stroke_index_buffer: IndexType,
+}
+
+impl Shape for Bezier {
+    fn fill_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.fill_buffer)
+    }
+
+    fn stroke_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.stroke_buffer)
+    }
+
+    fn fill_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.fill_index_buffer)
+    }
+
+    fn stroke_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.stroke_index_buffer)
+    }
+
+    fn get_texture(&self) -> Option<Box<&glium::texture::Texture2d>> {
+        None
+    }
+}
+
+impl Bezier {
+	/// Create a new Bezier curve to be drawn later. It starts at the anchor point
+	/// (x1i, y1i, z1i), is pulled towards the control points (cx1i, cy1i, cz1i) and
+	/// (cx2i, cy2i, cz2i), and ends at the anchor point (x2i, y2i, z2i). As in
+	/// Processing, the region between the curve and the straight line joining its ends
+	/// is filled. The curve is cut into at least as many pieces as set by
+	/// screen.bezier_detail(), and more if needed for it to look smooth under the
+	/// transformation matrix that is current when it is created.
+    #[inline]
+    pub fn new(
+        screen: &Screen,
+        x1i: &[f64],
+        y1i: &[f64],
+        z1i: &[f64],
+        cx1i: &[f64],
+        cy1i: &[f64],
+        cz1i: &[f64],
+        cx2i: &[f64],
+        cy2i: &[f64],
+        cz2i: &[f64],
+        x2i: &[f64],
+        y2i: &[f64],
+        z2i: &[f64],
+    ) -> Result<Self, ProcessingErr> {
+        check_lengths(&[
+            x1i.len(),
+            y1i.len(),
+            z1i.len(),
+            cx1i.len(),
+            cy1i.len(),
+            cz1i.len(),
+            cx2i.len(),
+            cy2i.len(),
+            cz2i.len(),
+            x2i.len(),
+            y2i.len(),
+            z2i.len(),
+        ])?;
+        let mut outlines = vec![];
+        for i in 0..x1i.len() {
+            let f = |t| {
+                [
+                    screen.bezier_point(x1i[i], cx1i[i], cx2i[i], x2i[i], t),
+                    screen.bezier_point(y1i[i], cy1i[i], cy2i[i], y2i[i], t),
+                    screen.bezier_point(z1i[i], cz1i[i], cz2i[i], z2i[i], t),
+                ]
+            };
+            let mut outline = vec![f(0.0)];
+            outline.extend(subdivide_curve(screen, &f, screen.bezier_detail));
+            outlines.push(outline);
+        }
+
+        let (fill_buffer, stroke_buffer, fill_index_buffer, stroke_index_buffer) =
+            curve_buffers(screen, &outlines)?;
+        Ok(Bezier {
+            fill_buffer: fill_buffer,
+            stroke_buffer: stroke_buffer,
+            fill_index_buffer: fill_index_buffer,
+            stroke_index_buffer: stroke_index_buffer,
+        })
+    }
+}
+
+/// A Curve is a Catmull-Rom spline. It is drawn between its second and third points,
+/// while the first and last points only determine how it bends at either end. How
+/// tightly it follows its points is set with screen.curve_tightness().
+pub struct Curve {
+    fill_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    fill_index_buffer: IndexType,
+    stroke_index_buffer: IndexType,
+}
+
+impl Shape for Curve {
+    fn fill_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.fill_buffer)
+    }
+
+    fn stroke_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.stroke_buffer)
+    }
+
+    fn fill_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.fill_index_buffer)
+    }
+
+    fn stroke_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.stroke_index_buffer)
+    }
+
+    fn get_texture(&self) -> Option<Box<&glium::texture::Texture2d>> {
+        None
+    }
+}
+
+impl Curve {
+	/// Create a new Curve to be drawn later. It passes through (x2i, y2i, z2i) and
+	/// (x3i, y3i, z3i), while (x1i, y1i, z1i) and (x4i, y4i, z4i) act as guides for the
+	/// beginning and end of the curve. The curve is cut into at least as many pieces as
+	/// set by screen.curve_detail(), and more if needed for it to look smooth.
+    #[inline]
+    pub fn new(
+        screen: &Screen,
+        x1i: &[f64],
+        y1i: &[f64],
+        z1i: &[f64],
+        x2i: &[f64],
+        y2i: &[f64],
+        z2i: &[f64],
+        x3i: &[f64],
+        y3i: &[f64],
+        z3i: &[f64],
+        x4i: &[f64],
+        y4i: &[f64],
+        z4i: &[f64],
+    ) -> Result<Self, ProcessingErr> {
+        check_lengths(&[
+            x1i.len(),
+            y1i.len(),
+            z1i.len(),
+            x2i.len(),
+            y2i.len(),
+            z2i.len(),
+            x3i.len(),
+            y3i.len(),
+            z3i.len(),
+            x4i.len(),
+            y4i.len(),
+            z4i.len(),
+        ])?;
+        let mut outlines = vec![];
+        for i in 0..x1i.len() {
+            let f = |t| {
+                [
+                    screen.curve_point(x1i[i], x2i[i], x3i[i], x4i[i], t),
+                    screen.curve_point(y1i[i], y2i[i], y3i[i], y4i[i], t),
+                    screen.curve_point(z1i[i], z2i[i], z3i[i], z4i[i], t),
+                ]
+            };
+            let mut outline = vec![f(0.0)];
+            outline.extend(subdivide_curve(screen, &f, screen.curve_detail));
+            outlines.push(outline);
+        }
+
+        let (fill_buffer, stroke_buffer, fill_index_buffer, stroke_index_buffer) =
+            curve_buffers(screen, &outlines)?;
+        Ok(Curve {
+            fill_buffer: fill_buffer,
+            stroke_buffer: stroke_buffer,
+            fill_index_buffer: fill_index_buffer,
+            stroke_index_buffer: stroke_index_buffer,
+        })
+    }
+}
+
+// upload a list of open curves: the fill closes each of them with a straight line,
+// while the stroke follows only the curves themselves
+fn curve_buffers(
+    screen: &Screen,
+    outlines: &[Vec<[f64; 3]>],
+) -> Result<(glium::VertexBuffer<ShapeVertex>, glium::VertexBuffer<ShapeVertex>, IndexType, IndexType), ProcessingErr> {
+    let mut shape = vec![];
+    let mut elements = vec![];
+    let mut edges = vec![];
+    let mut owners = vec![];
+    for (n, outline) in outlines.iter().enumerate() {
+        let offset = shape.len();
+        let points = outline
+            .iter()
+            .map(|p| {
+                let mut x = p[0];
+                let mut y = p[1];
+                if screen.preserve_aspect_ratio && screen.aspect_ratio != 1f32 {
+                    if screen.aspect_ratio > 1f32 {
+                        x = x / screen.aspect_ratio as f64;
+                    } else {
+                        y = y * screen.aspect_ratio as f64;
+                    }
+                }
+                [x as f32, y as f32, p[2] as f32]
+            })
+            .collect::<Vec<[f32; 3]>>();
+        elements.extend(tessellate(&points).iter().map(|&e| e + offset as u32));
+        for (i, p) in points.iter().enumerate() {
+            shape.push(ShapeVertex {
+                position: *p,
+                color: [0.0, 0.0, 0.0, 0.0],
+                texcoord: [0f32, 0.],
+                normal: [0.0, 0.0, 0.0],
+            });
+            owners.push(n);
+            if i > 0 {
+                edges.push([(offset + i - 1) as u32, (offset + i) as u32]);
+            }
+        }
+    }
+
+    let index_buffer = match screen.display {
+        ScreenType::Window(ref d) => {
+            glium::IndexBuffer::new(d, glium::index::PrimitiveType::TrianglesList, &elements)
+                .map_err(|e| ProcessingErr::IBNoCreate(e))?
+        }
+        ScreenType::Headless(ref d) => {
+            glium::IndexBuffer::new(d, glium::index::PrimitiveType::TrianglesList, &elements)
+                .map_err(|e| ProcessingErr::IBNoCreate(e))?
+        }
+    };
+
+    // the curves can be split into different numbers of pieces, so every vertex has
+    // to look up the color of the curve it belongs to
+    for (v, &n) in shape.iter_mut().zip(owners.iter()) {
+        v.color = nth_color(&screen.fill_col, n, outlines.len())?;
+    }
+    let fill_shape_buffer = match screen.display {
+        ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
+            .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+        ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
+            .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+    };
+
+    for (v, &n) in shape.iter_mut().zip(owners.iter()) {
+        v.color = nth_color(&screen.stroke_col, n, outlines.len())?;
+    }
+    let stroke_shape_buffer = match screen.display {
+        ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
+            .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+        ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
+            .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+    };
+
+    Ok((
+        fill_shape_buffer,
+        stroke_shape_buffer,
+        IndexType::Buffer { ind: index_buffer },
+        stroke_indices(screen, &edges)?,
+    ))
+}
+
+impl<'a> Screen<'a> {
+	/// Evaluate one coordinate of a Bezier curve at t, which runs from 0 at the first
+	/// anchor point (a) to 1 at the second anchor point (d). The control points are b
+	/// and c. Call it once for each of x, y, and z to find a point on the curve.
+    pub fn bezier_point(&self, a: f64, b: f64, c: f64, d: f64, t: f64) -> f64 {
+        bezier_point(a, b, c, d, t)
+    }
+
+	/// The derivative of screen.bezier_point() with respect to t, i.e., one
+	/// coordinate of the direction in which the curve is heading at t.
+    pub fn bezier_tangent(&self, a: f64, b: f64, c: f64, d: f64, t: f64) -> f64 {
+        bezier_tangent(a, b, c, d, t)
+    }
+
+	/// Evaluate one coordinate of a Catmull-Rom curve at t, which runs from 0 at b to
+	/// 1 at c. The guide points are a and d. The result depends on the current
+	/// curve_tightness().
+    pub fn curve_point(&self, a: f64, b: f64, c: f64, d: f64, t: f64) -> f64 {
+        curve_point(self.curve_tightness as f64, a, b, c, d, t)
+    }
+
+	/// The derivative of screen.curve_point() with respect to t.
+    pub fn curve_tangent(&self, a: f64, b: f64, c: f64, d: f64, t: f64) -> f64 {
+        curve_tangent(self.curve_tightness as f64, a, b, c, d, t)
+    }
+
+	/// Change how closely Curves follow their points. At 0, the default, they are
+	/// standard Catmull-Rom splines, while at 1 they become straight lines between
+	/// the points. Values outside of that range are allowed and make the curves
+	/// bulge out or in.
+    pub fn curve_tightness(&mut self, tightness: f32) {
+        self.curve_tightness = tightness;
+    }
+
+	/// Set the number of pieces each Bezier curve is cut into before any additional
+	/// subdivision that is needed to make it look smooth. The default is 20.
+    pub fn bezier_detail(&mut self, detail: usize) {
+        self.bezier_detail = detail;
+    }
+
+	/// Set the number of pieces each Catmull-Rom curve is cut into before any
+	/// additional subdivision that is needed to make it look smooth. The default is
+	/// 20.
+    pub fn curve_detail(&mut self, detail: usize) {
+        self.curve_detail = detail;
+    }
+}
+
+fn bezier_point(a: f64, b: f64, c: f64, d: f64, t: f64) -> f64 {
+    let mt = 1.0 - t;
+    mt * mt * mt * a + 3.0 * mt * mt * t * b + 3.0 * mt * t * t * c + t * t * t * d
+}
+
+fn bezier_tangent(a: f64, b: f64, c: f64, d: f64, t: f64) -> f64 {
+    3.0 * t * t * (-a + 3.0 * b - 3.0 * c + d) + 6.0 * t * (a - 2.0 * b + c) + 3.0 * (-a + b)
+}
+
+fn curve_point(tightness: f64, a: f64, b: f64, c: f64, d: f64, t: f64) -> f64 {
+    let w = curve_basis(tightness, [t * t * t, t * t, t, 1.0]);
+    w[0] * a + w[1] * b + w[2] * c + w[3] * d
+}
+
+fn curve_tangent(tightness: f64, a: f64, b: f64, c: f64, d: f64, t: f64) -> f64 {
+    let w = curve_basis(tightness, [3.0 * t * t, 2.0 * t, 1.0, 0.0]);
+    w[0] * a + w[1] * b + w[2] * c + w[3] * d
+}
+
+// weights of the four points for the given powers of t, using the same basis matrix as
+// Processing
+fn curve_basis(s: f64, tp: [f64; 4]) -> [f64; 4] {
+    let m = [
+        [(s - 1.0) / 2.0, (s + 3.0) / 2.0, (-3.0 - s) / 2.0, (1.0 - s) / 2.0],
+        [1.0 - s, (-5.0 - s) / 2.0, s + 2.0, (s - 1.0) / 2.0],
+        [(s - 1.0) / 2.0, 0.0, (1.0 - s) / 2.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+    ];
+    let mut w = [0.0; 4];
+    for j in 0..4 {
+        for i in 0..4 {
+            w[j] += tp[i] * m[i][j];
+        }
+    }
+
+    w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bezier_point, bezier_tangent, curve_point, curve_tangent};
+
+    const EPSILON: f64 = 1e-9;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < EPSILON
+    }
+
+    fn numeric_derivative<F: Fn(f64) -> f64>(f: F, t: f64) -> f64 {
+        let h = 1e-6;
+        (f(t + h) - f(t - h)) / (2.0 * h)
+    }
+
+    #[test]
+    fn bezier_ends_and_middle() {
+        let (a, b, c, d) = (1.0, 5.0, -3.0, 2.0);
+        assert!(close(bezier_point(a, b, c, d, 0.0), a));
+        assert!(close(bezier_point(a, b, c, d, 1.0), d));
+        assert!(close(bezier_point(a, b, c, d, 0.5), (a + 3.0 * b + 3.0 * c + d) / 8.0));
+    }
+
+    #[test]
+    fn bezier_tangent_is_the_derivative() {
+        let (a, b, c, d) = (1.0, 5.0, -3.0, 2.0);
+        assert!(close(bezier_tangent(a, b, c, d, 0.0), 3.0 * (b - a)));
+        assert!(close(bezier_tangent(a, b, c, d, 1.0), 3.0 * (d - c)));
+        for &t in &[0.1, 0.25, 0.5, 0.9] {
+            let numeric = numeric_derivative(|t| bezier_point(a, b, c, d, t), t);
+            assert!((bezier_tangent(a, b, c, d, t) - numeric).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn catmull_rom() {
+        let (a, b, c, d) = (0.0, 1.0, 4.0, 2.0);
+        for &t in &[0.0, 0.3, 0.5, 0.8, 1.0] {
+            let (t2, t3) = (t * t, t * t * t);
+            let expected = 0.5 *
+                (2.0 * b + (c - a) * t + (2.0 * a - 5.0 * b + 4.0 * c - d) * t2 +
+                     (-a + 3.0 * b - 3.0 * c + d) * t3);
+            assert!(close(curve_point(0.0, a, b, c, d, t), expected));
+        }
+        assert!(close(curve_point(0.0, a, b, c, d, 0.0), b));
+        assert!(close(curve_point(0.0, a, b, c, d, 1.0), c));
+        assert!(close(curve_tangent(0.0, a, b, c, d, 0.0), (c - a) / 2.0));
+        assert!(close(curve_tangent(0.0, a, b, c, d, 1.0), (d - b) / 2.0));
+    }
+
+    #[test]
+    fn curve_tightness() {
+        let (a, b, c, d) = (-7.0, 1.0, 4.0, 9.0);
+        for &s in &[-1.0, 0.0, 0.5, 1.0] {
+            // the curve always runs from b to c
+            assert!(close(curve_point(s, a, b, c, d, 0.0), b));
+            assert!(close(curve_point(s, a, b, c, d, 1.0), c));
+            for &t in &[0.2, 0.5, 0.7] {
+                let numeric = numeric_derivative(|t| curve_point(s, a, b, c, d, t), t);
+                assert!((curve_tangent(s, a, b, c, d, t) - numeric).abs() < 1e-6);
+            }
+        }
+        // at full tightness, the guide points are ignored
+        for &t in &[0.2, 0.5, 0.7] {
+            assert!(close(curve_point(1.0, a, b, c, d, t), curve_point(1.0, 0.0, b, c, 0.0, t)));
+            let p = curve_point(1.0, a, b, c, d, t);
+            assert!(p >= b && p <= c);
+        }
+    }
+}
@@ -0,0 +1,581 @@
+use glium;
+
+use {Screen, ScreenType};
+use errors::ProcessingErr;
+use textures::TextureSampling;
+use constants::{POLYGON, POINTS, LINES, TRIANGLES, TRIANGLE_FAN, TRIANGLE_STRIP, QUADS,
+                QUAD_STRIP, CLOSE};
+
+use shapes::{Shape, ShapeVertex, IndexType, subdivide_curve, stroke_indices};
+
+/// Collects the vertices given to screen.vertex() between calls to
+/// screen.begin_shape() and screen.end_shape().
+pub struct ShapeBuilder {
+    kind: isize,
+    vertices: Vec<ShapeVertex>,
+    stroke_colors: Vec<[f32; 4]>,
+    last_point: Option<[f64; 3]>,
+    curve_points: Vec<[f64; 3]>,
+}
+
+/// A CustomShape is a shape that you build up yourself, one vertex at a time, with
+/// screen.begin_shape(), screen.vertex() and screen.end_shape(). Every vertex keeps
+/// the fill and stroke colors that were active when it was added, so you can make
+/// gradients by changing the fill color in between calls to screen.vertex().
+pub struct CustomShape<'a> {
+    fill_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    fill_index_buffer: IndexType,
+    stroke_index_buffer: IndexType,
+    texture: Option<&'a glium::texture::Texture2d>,
+    texture_sampling: Option<TextureSampling>,
+}
+
+impl<'a> Shape for CustomShape<'a> {
+    fn fill_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.fill_buffer)
+    }
+
+    fn stroke_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.stroke_buffer)
+    }
+
+    fn fill_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.fill_index_buffer)
+    }
+
+    fn stroke_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.stroke_index_buffer)
+    }
+
+    fn get_texture(&self) -> Option<Box<&glium::texture::Texture2d>> {
+        match self.texture {
+            Some(t) => Some(Box::new(t)),
+            _ => None,
+        }
+    }
+
+    fn get_texture_sampling(&self) -> Option<TextureSampling> {
+        self.texture_sampling
+    }
+}
+
+impl<'a> CustomShape<'a> {
+    pub fn attach_texture(&mut self, tex: &'a glium::texture::Texture2d) {
+        self.texture = Some(tex);
+    }
+
+	/// Give this shape its own texture sampling, which takes the place of the global
+	/// one (see screen.texture_wrap() and friends) whenever it is drawn.
+    pub fn texture_sampling(&mut self, sampling: TextureSampling) {
+        self.texture_sampling = Some(sampling);
+    }
+}
+
+impl<'a> Screen<'a> {
+	/// Start building a new shape out of individual vertices. The kind of shape is
+	/// one of the constants POLYGON, POINTS, LINES, TRIANGLES, TRIANGLE_FAN,
+	/// TRIANGLE_STRIP, QUADS, or QUAD_STRIP and determines how the vertices are
+	/// connected, following the same rules as Processing. Any vertices from an
+	/// earlier call that was never finished with screen.end_shape() are thrown away.
+    pub fn begin_shape(&mut self, kind: isize) {
+        self.shape_builder = Some(ShapeBuilder {
+            kind: kind,
+            vertices: vec![],
+            stroke_colors: vec![],
+            last_point: None,
+            curve_points: vec![],
+        });
+    }
+
+	/// Add a vertex at position (x, y, z) to the shape that is currently being built.
+	/// It is colored with the current fill and stroke colors. This does nothing if
+	/// screen.begin_shape() was not called first.
+    pub fn vertex(&mut self, x: f64, y: f64, z: f64) {
+        self.vertex_uv(x, y, z, 0.0, 0.0);
+    }
+
+	/// The same as screen.vertex(), but also gives the vertex the texture coordinates
+	/// (u, v), for when a texture will be attached to the shape.
+    pub fn vertex_uv(&mut self, x: f64, y: f64, z: f64, u: f64, v: f64) {
+        if let Some(ref mut builder) = self.shape_builder {
+            builder.curve_points.clear();
+        }
+        self.push_vertex(x, y, z, u, v);
+    }
+
+	/// Add a Bezier curve to the shape that is currently being built. The curve starts
+	/// at the previous vertex, is pulled towards the control points (cx1, cy1, cz1) and
+	/// (cx2, cy2, cz2), and ends at (x, y, z), which becomes the new previous vertex.
+	/// The curve is split up in the same way as a Bezier shape. If there is no previous
+	/// vertex, only (x, y, z) is added.
+    pub fn bezier_vertex(
+        &mut self,
+        cx1: f64,
+        cy1: f64,
+        cz1: f64,
+        cx2: f64,
+        cy2: f64,
+        cz2: f64,
+        x: f64,
+        y: f64,
+        z: f64,
+    ) {
+        let start = match self.shape_builder {
+            Some(ref mut builder) => {
+                builder.curve_points.clear();
+                builder.last_point
+            }
+            None => return,
+        };
+
+        let points = match start {
+            Some(s) => {
+                let f = |t| {
+                    [
+                        self.bezier_point(s[0], cx1, cx2, x, t),
+                        self.bezier_point(s[1], cy1, cy2, y, t),
+                        self.bezier_point(s[2], cz1, cz2, z, t),
+                    ]
+                };
+                subdivide_curve(self, &f, self.bezier_detail)
+            }
+            None => vec![[x, y, z]],
+        };
+        for p in points {
+            self.push_vertex(p[0], p[1], p[2], 0.0, 0.0);
+        }
+    }
+
+	/// Add a point on a Catmull-Rom curve to the shape that is currently being built.
+	/// As with a Curve shape, the first and last of these points only guide the curve,
+	/// so nothing is added until the fourth call in a row, and after that, every call
+	/// extends the curve up to the previous point.
+    pub fn curve_vertex(&mut self, x: f64, y: f64, z: f64) {
+        let guides = match self.shape_builder {
+            Some(ref mut builder) => {
+                builder.curve_points.push([x, y, z]);
+                builder.curve_points.clone()
+            }
+            None => return,
+        };
+        let n = guides.len();
+        if n < 4 {
+            return;
+        }
+
+        let (a, b, c, d) = (guides[n - 4], guides[n - 3], guides[n - 2], guides[n - 1]);
+        let f = |t| {
+            [
+                self.curve_point(a[0], b[0], c[0], d[0], t),
+                self.curve_point(a[1], b[1], c[1], d[1], t),
+                self.curve_point(a[2], b[2], c[2], d[2], t),
+            ]
+        };
+        let mut points = vec![];
+        if n == 4 {
+            points.push(f(0.0));
+        }
+        points.extend(subdivide_curve(self, &f, self.curve_detail));
+        for p in points {
+            self.push_vertex(p[0], p[1], p[2], 0.0, 0.0);
+        }
+    }
+
+    fn push_vertex(&mut self, x: f64, y: f64, z: f64, u: f64, v: f64) {
+        let point = [x, y, z];
+        let mut x = x;
+        let mut y = y;
+        if self.preserve_aspect_ratio && self.aspect_ratio != 1f32 {
+            if self.aspect_ratio > 1f32 {
+                x = x / self.aspect_ratio as f64;
+            } else {
+                y = y * self.aspect_ratio as f64;
+            }
+        }
+
+        let fill = [self.fill_col[0], self.fill_col[1], self.fill_col[2], self.fill_col[3]];
+        let stroke = [
+            self.stroke_col[0],
+            self.stroke_col[1],
+            self.stroke_col[2],
+            self.stroke_col[3],
+        ];
+        if let Some(ref mut builder) = self.shape_builder {
+            builder.vertices.push(ShapeVertex {
+                position: [x as f32, y as f32, z as f32],
+                color: fill,
+                texcoord: [u as f32, v as f32],
+                normal: [0.0, 0.0, 0.0],
+            });
+            builder.stroke_colors.push(stroke);
+            builder.last_point = Some(point);
+        }
+    }
+
+	/// Finish the shape that was started with screen.begin_shape() and upload it to
+	/// the GPU, so that it can be drawn with screen.draw(). If mode is CLOSE, the
+	/// outline of a POLYGON is closed by joining the last vertex back to the first one;
+	/// use OPEN to leave it open. Closed or not, polygons are filled by splitting them
+	/// into triangles, which also works for concave polygons, as long as their edges
+	/// don't cross each other.
+    pub fn end_shape(&mut self, mode: isize) -> Result<CustomShape<'a>, ProcessingErr> {
+        let builder = self.shape_builder.take().ok_or(ProcessingErr::ShapeNotBegun)?;
+        let n = builder.vertices.len();
+
+        let mut stroke_shape = builder.vertices.clone();
+        for (v, c) in stroke_shape.iter_mut().zip(builder.stroke_colors.iter()) {
+            v.color = *c;
+        }
+
+        // each entry is a list of indices for the fill, if the fill can't be drawn
+        // directly, and pairs of indices for the edges of the outline
+        let (fill_prim, fill_elements, edges) = match builder.kind {
+            POLYGON => {
+                let mut edges = vec![];
+                for i in 1..n {
+                    edges.push((i - 1, i));
+                }
+                if mode == CLOSE && n > 2 {
+                    edges.push((n - 1, 0));
+                }
+                let points = builder.vertices.iter().map(|v| v.position).collect::<Vec<_>>();
+                (
+                    glium::index::PrimitiveType::TrianglesList,
+                    Some(tessellate(&points)),
+                    edges,
+                )
+            }
+            POINTS => (glium::index::PrimitiveType::Points, None, vec![]),
+            LINES => {
+                let edges = (0..n / 2).map(|i| (2 * i, 2 * i + 1)).collect();
+                (glium::index::PrimitiveType::LinesList, None, edges)
+            }
+            TRIANGLES => {
+                let mut edges = vec![];
+                for t in 0..n / 3 {
+                    let i = 3 * t;
+                    edges.push((i, i + 1));
+                    edges.push((i + 1, i + 2));
+                    edges.push((i + 2, i));
+                }
+                (glium::index::PrimitiveType::TrianglesList, None, edges)
+            }
+            TRIANGLE_FAN => {
+                let mut edges = vec![];
+                for i in 1..n {
+                    edges.push((0, i));
+                    if i + 1 < n {
+                        edges.push((i, i + 1));
+                    }
+                }
+                (glium::index::PrimitiveType::TriangleFan, None, edges)
+            }
+            TRIANGLE_STRIP => {
+                let mut edges = vec![];
+                for i in 0..n {
+                    if i + 1 < n {
+                        edges.push((i, i + 1));
+                    }
+                    if i + 2 < n {
+                        edges.push((i, i + 2));
+                    }
+                }
+                (glium::index::PrimitiveType::TriangleStrip, None, edges)
+            }
+            QUADS => {
+                let mut elements = vec![];
+                let mut edges = vec![];
+                for q in 0..n / 4 {
+                    let i = 4 * q;
+                    elements.extend_from_slice(
+                        &[i as u32, i as u32 + 1, i as u32 + 2, i as u32 + 2, i as u32 + 3, i as u32],
+                    );
+                    edges.push((i, i + 1));
+                    edges.push((i + 1, i + 2));
+                    edges.push((i + 2, i + 3));
+                    edges.push((i + 3, i));
+                }
+                (glium::index::PrimitiveType::TrianglesList, Some(elements), edges)
+            }
+            QUAD_STRIP => {
+                // the vertices come in pairs, one from each side of the strip, so
+                // the fill is the same as for a triangle strip
+                let mut edges = vec![];
+                if n >= 2 {
+                    edges.push((0, 1));
+                }
+                for q in 0..(n / 2).saturating_sub(1) {
+                    let i = 2 * q;
+                    edges.push((i, i + 2));
+                    edges.push((i + 1, i + 3));
+                    edges.push((i + 2, i + 3));
+                }
+                (glium::index::PrimitiveType::TriangleStrip, None, edges)
+            }
+            k => return Err(ProcessingErr::UnknownShapeKind(k)),
+        };
+
+        // the outline is drawn by the stroke shader, except for points
+        let stroke_index_buffer = if builder.kind == POINTS {
+            IndexType::NoBuffer { ind: glium::index::NoIndices(glium::index::PrimitiveType::Points) }
+        } else {
+            let edges = edges.iter().map(|&(a, b)| [a as u32, b as u32]).collect::<Vec<_>>();
+            stroke_indices(self, &edges)?
+        };
+        let fill_shape = if builder.kind == POINTS {
+            stroke_shape.clone()
+        } else {
+            builder.vertices
+        };
+
+        let fill_shape_buffer = match self.display {
+            ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &fill_shape)
+                .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+            ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &fill_shape)
+                .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+        };
+        let stroke_shape_buffer = match self.display {
+            ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &stroke_shape)
+                .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+            ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &stroke_shape)
+                .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+        };
+
+        let fill_index_buffer = match fill_elements {
+            Some(elements) => {
+                let ib = match self.display {
+                    ScreenType::Window(ref d) => glium::IndexBuffer::new(d, fill_prim, &elements)
+                        .map_err(|e| ProcessingErr::IBNoCreate(e))?,
+                    ScreenType::Headless(ref d) => glium::IndexBuffer::new(d, fill_prim, &elements)
+                        .map_err(|e| ProcessingErr::IBNoCreate(e))?,
+                };
+                IndexType::Buffer { ind: ib }
+            }
+            None => IndexType::NoBuffer { ind: glium::index::NoIndices(fill_prim) },
+        };
+
+        Ok(CustomShape {
+            fill_buffer: fill_shape_buffer,
+            stroke_buffer: stroke_shape_buffer,
+            fill_index_buffer: fill_index_buffer,
+            stroke_index_buffer: stroke_index_buffer,
+            texture: None,
+            texture_sampling: None,
+        })
+    }
+}
+
+/// Split a simple polygon (i.e., one whose edges don't cross) into triangles by ear
+/// clipping, and return the indices of their corners. The polygon doesn't need to be
+/// convex or lie in the xy-plane, but it should be roughly flat.
+pub fn tessellate(points: &[[f32; 3]]) -> Vec<u32> {
+    let n = points.len();
+    let mut elements = vec![];
+    if n < 3 {
+        return elements;
+    }
+
+    // find the normal of the polygon with Newell's method and then drop its largest
+    // component, so that the polygon can be handled in 2D
+    let mut normal = [0f32; 3];
+    for i in 0..n {
+        let p = points[i];
+        let q = points[(i + 1) % n];
+        normal[0] += (p[1] - q[1]) * (p[2] + q[2]);
+        normal[1] += (p[2] - q[2]) * (p[0] + q[0]);
+        normal[2] += (p[0] - q[0]) * (p[1] + q[1]);
+    }
+    let (a, b, orientation) = if normal[0].abs() > normal[1].abs() &&
+        normal[0].abs() > normal[2].abs()
+    {
+        (1, 2, normal[0].signum())
+    } else if normal[1].abs() > normal[2].abs() {
+        (2, 0, normal[1].signum())
+    } else {
+        (0, 1, normal[2].signum())
+    };
+    let flat = points.iter().map(|p| (p[a], p[b])).collect::<Vec<_>>();
+
+    let cross = |o: (f32, f32), p: (f32, f32), q: (f32, f32)| {
+        (p.0 - o.0) * (q.1 - o.1) - (p.1 - o.1) * (q.0 - o.0)
+    };
+
+    let mut remaining = (0..n).collect::<Vec<usize>>();
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let mut ear = None;
+        for i in 0..m {
+            let (ip, ic, inext) = (remaining[(i + m - 1) % m], remaining[i], remaining[(i + 1) % m]);
+            let (p, c, q) = (flat[ip], flat[ic], flat[inext]);
+            if cross(p, c, q) * orientation <= 0.0 {
+                continue;
+            }
+            let contains_other = remaining.iter().any(|&j| {
+                if j == ip || j == ic || j == inext {
+                    return false;
+                }
+                let r = flat[j];
+                cross(p, c, r) * orientation >= 0.0 && cross(c, q, r) * orientation >= 0.0 &&
+                    cross(q, p, r) * orientation >= 0.0
+            });
+            if !contains_other {
+                ear = Some(i);
+                break;
+            }
+        }
+
+        match ear {
+            Some(i) => {
+                elements.push(remaining[(i + m - 1) % m] as u32);
+                elements.push(remaining[i] as u32);
+                elements.push(remaining[(i + 1) % m] as u32);
+                remaining.remove(i);
+            }
+            None => {
+                // the polygon is degenerate, so just fall back to a fan
+                for i in 1..m - 1 {
+                    elements.push(remaining[0] as u32);
+                    elements.push(remaining[i] as u32);
+                    elements.push(remaining[i + 1] as u32);
+                }
+                return elements;
+            }
+        }
+    }
+    elements.extend(remaining.iter().map(|&i| i as u32));
+
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tessellate;
+
+    fn area(points: &[[f32; 3]], a: usize, b: usize, c: usize) -> f32 {
+        let (p, q, r) = (points[a], points[b], points[c]);
+        ((q[0] - p[0]) * (r[1] - p[1]) - (q[1] - p[1]) * (r[0] - p[0])) / 2.0
+    }
+
+    fn polygon_area(points: &[[f32; 3]]) -> f32 {
+        (0..points.len()).fold(0.0, |sum, i| {
+            let (p, q) = (points[i], points[(i + 1) % points.len()]);
+            sum + (p[0] * q[1] - q[0] * p[1]) / 2.0
+        })
+    }
+
+    // every triangle must wind the same way as the polygon and together they must
+    // cover exactly its area, which they can only do without overlapping or leaving
+    // the polygon
+    fn check(points: &[[f32; 3]]) -> Vec<u32> {
+        let elements = tessellate(points);
+        assert_eq!(elements.len() % 3, 0);
+        let total = polygon_area(points);
+        let mut covered = 0.0;
+        for t in elements.chunks(3) {
+            let a = area(points, t[0] as usize, t[1] as usize, t[2] as usize);
+            assert!(a * total.signum() >= -1e-6, "triangle {:?} is flipped", t);
+            covered += a;
+        }
+        assert!((covered - total).abs() < 1e-4, "covered {} of {}", covered, total);
+        elements
+    }
+
+    #[test]
+    fn too_few_points() {
+        assert!(tessellate(&[]).is_empty());
+        assert!(tessellate(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]]).is_empty());
+    }
+
+    #[test]
+    fn triangle() {
+        assert_eq!(check(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn convex() {
+        let square = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+        assert_eq!(check(&square).len(), 6);
+    }
+
+    #[test]
+    fn concave() {
+        // an arrow head pointing up, whose notch at (0, 1) is the reflex vertex
+        let arrow = [[-2.0, 0.0, 0.0], [0.0, 1.0, 0.0], [2.0, 0.0, 0.0], [0.0, 4.0, 0.0]];
+        assert_eq!(check(&arrow).len(), 6);
+
+        let l = [
+            [0.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [2.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 2.0, 0.0],
+            [0.0, 2.0, 0.0],
+        ];
+        assert_eq!(check(&l).len(), 12);
+
+        let star = (0..10)
+            .map(|i| {
+                let angle = i as f32 * ::std::f32::consts::PI / 5.0;
+                let r = if i % 2 == 0 { 1.0 } else { 0.4 };
+                [r * angle.cos(), r * angle.sin(), 0.0]
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(check(&star).len(), 24);
+    }
+
+    #[test]
+    fn clockwise() {
+        let l = [
+            [0.0, 2.0, 0.0],
+            [1.0, 2.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [2.0, 1.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [0.0, 0.0, 0.0],
+        ];
+        assert!(polygon_area(&l) < 0.0);
+        assert_eq!(check(&l).len(), 12);
+    }
+
+    #[test]
+    fn collinear_vertices() {
+        // a square with extra vertices in the middle of two of its edges
+        let square = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [2.0, 2.0, 0.0],
+            [1.0, 2.0, 0.0],
+            [0.0, 2.0, 0.0],
+        ];
+        check(&square);
+
+        // and a concave one, where the collinear vertex is next to the notch
+        let notched = [
+            [0.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [2.0, 2.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.5, 1.5, 0.0],
+            [0.0, 2.0, 0.0],
+        ];
+        check(&notched);
+    }
+
+    #[test]
+    fn not_in_the_xy_plane() {
+        // the L from above, but lying in the xz-plane
+        let l = [
+            [0.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [2.0, 0.0, 1.0],
+            [1.0, 0.0, 1.0],
+            [1.0, 0.0, 2.0],
+            [0.0, 0.0, 2.0],
+        ];
+        let flat = l.iter().map(|p| [p[0], p[2], 0.0]).collect::<Vec<_>>();
+        let elements = tessellate(&l);
+        assert_eq!(elements, check(&flat));
+    }
+}
@@ -1,8 +1,12 @@
 use glium::Surface;
 use glium::uniforms::Uniforms;
 
-use shapes::{Shape, IndexType};
+use shapes::{Shape, IndexType, draw_with_shader};
 use shapes::mould::Mould;
+use shapes::instanced::Instanced;
+use shaders::{TintedUniforms, STROKE_SHADER, INSTANCED_SHADER, INSTANCED_TEXTURED_SHADER,
+              INSTANCED_STROKE_SHADER, LIT_SHADER, LIT_TEXTURED_SHADER};
+use lights::LightUniforms;
 use errors::ProcessingErr;
 
 use Screen;
@@ -11,12 +15,24 @@ impl<'a> Screen<'a> {
 	/// Take a given shape and draw it onto the screen. Since the shape's properties
 	/// were precomputed and its buffers were already uploaded to the GPU, drawing
 	/// many shapes should be faster than in a standard Processing environment.
+	/// Shapes with normals are lit if there are any lights (see screen.lights()).
     #[inline]
-    pub fn draw<S: Shape>(&mut self, shape: &S) -> Result<(), ProcessingErr> {
+    pub fn draw<S: Shape + ?Sized>(&mut self, shape: &S) -> Result<(), ProcessingErr> {
+        self.flush()?;
+        let lit = !self.lights.is_empty() && shape.has_normals();
+        let lights = if lit { &self.lights[..] } else { &[] };
+        let (cap, join) = self.stroke_style();
+        let viewport = [self.fb_size[0] as f32, self.fb_size[1] as f32];
+        let stroke_prog = &self.shader_bank[STROKE_SHADER];
+        let stroke_u = create_uniforms!{self, viewport: viewport, strokeWeight: self.stroke_weight,
+            strokeCap: cap, strokeJoin: join};
+        let sampling = self.texture_sampling_of(shape);
         let framebuffer = &mut self.fbo;
         if let Some(tex) = shape.get_texture() {
-            let prog = &self.shader_bank[1];
-            let u = create_uniforms!{self, tex: *tex};
+            let prog = &self.shader_bank[if lit { LIT_TEXTURED_SHADER } else { 1 }];
+            let tint = if self.tint_stuff { self.tint_col } else { [1.0; 4] };
+            let u = create_uniforms!{self, tex: sampling.sampler(*tex), tint: tint};
+            let u = LightUniforms::new(&u, lights, &self.material);
             if self.fill_stuff {
                 match *shape.fill_indices() {
                     &IndexType::Buffer { ind: ref ib } => {
@@ -38,12 +54,17 @@ impl<'a> Screen<'a> {
                             .draw(*shape.stroke_buffer(), ib, prog, &u, &self.draw_params)
                             .map_err(|e| ProcessingErr::FBDrawFailed(e))?
                     }
-                    _ => {}
+                    &IndexType::Buffer { ind: ref ib } => {
+                        framebuffer
+                            .draw(*shape.stroke_buffer(), ib, stroke_prog, &stroke_u, &self.draw_params)
+                            .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                    }
                 }
             };
         } else {
-            let prog = &self.shader_bank[0];
+            let prog = &self.shader_bank[if lit { LIT_SHADER } else { 0 }];
             let u = create_uniforms!{self};
+            let u = LightUniforms::new(&u, lights, &self.material);
             if self.fill_stuff {
                 match *shape.fill_indices() {
                     &IndexType::Buffer { ind: ref ib } => {
@@ -65,7 +86,11 @@ impl<'a> Screen<'a> {
                             .draw(*shape.stroke_buffer(), ib, prog, &u, &self.draw_params)
                             .map_err(|e| ProcessingErr::FBDrawFailed(e))?
                     }
-                    _ => {}
+                    &IndexType::Buffer { ind: ref ib } => {
+                        framebuffer
+                            .draw(*shape.stroke_buffer(), ib, stroke_prog, &stroke_u, &self.draw_params)
+                            .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                    }
                 }
             };
         }
@@ -78,50 +103,120 @@ impl<'a> Screen<'a> {
         // }
     }
 
+	/// Draw all of the copies of the shape in an Instanced with a single draw call.
+	/// Everything else works as in screen.draw(): the current fill, stroke, tint,
+	/// and transformation matrix apply to all of the copies.
+    pub fn draw_instanced<S: Shape>(&mut self, instanced: &Instanced<S>) -> Result<(), ProcessingErr> {
+        self.flush()?;
+        let shape = instanced.get_shape();
+        let instances = instanced.get_instance_buffer();
+        let per_instance = || {
+            instances
+                .per_instance()
+                .map_err(|_| ProcessingErr::InstancingNotSupported)
+        };
+        let aspect = self.aspect_correction();
+        let (cap, join) = self.stroke_style();
+        let viewport = [self.fb_size[0] as f32, self.fb_size[1] as f32];
+        let stroke_prog = &self.shader_bank[INSTANCED_STROKE_SHADER];
+        let stroke_u = create_uniforms!{self, aspect: aspect, viewport: viewport,
+            strokeWeight: self.stroke_weight, strokeCap: cap, strokeJoin: join};
+        let sampling = self.texture_sampling_of(shape);
+        let framebuffer = &mut self.fbo;
+        if let Some(tex) = shape.get_texture() {
+            let prog = &self.shader_bank[INSTANCED_TEXTURED_SHADER];
+            let tint = if self.tint_stuff { self.tint_col } else { [1.0; 4] };
+            let u = create_uniforms!{self, aspect: aspect, tex: sampling.sampler(*tex), tint: tint};
+            if self.fill_stuff {
+                match *shape.fill_indices() {
+                    &IndexType::Buffer { ind: ref ib } => {
+                        framebuffer
+                            .draw((*shape.fill_buffer(), per_instance()?), ib, prog, &u, &self.draw_params)
+                            .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                    }
+                    &IndexType::NoBuffer { ind: ref ib } => {
+                        framebuffer
+                            .draw((*shape.fill_buffer(), per_instance()?), ib, prog, &u, &self.draw_params)
+                            .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                    }
+                }
+            };
+            if self.stroke_stuff {
+                match *shape.stroke_indices() {
+                    &IndexType::NoBuffer { ind: ref ib } => {
+                        framebuffer
+                            .draw((*shape.stroke_buffer(), per_instance()?), ib, prog, &u, &self.draw_params)
+                            .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                    }
+                    &IndexType::Buffer { ind: ref ib } => {
+                        framebuffer
+                            .draw(
+                                (*shape.stroke_buffer(), per_instance()?),
+                                ib,
+                                stroke_prog,
+                                &stroke_u,
+                                &self.draw_params,
+                            )
+                            .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                    }
+                }
+            };
+        } else {
+            let prog = &self.shader_bank[INSTANCED_SHADER];
+            let u = create_uniforms!{self, aspect: aspect};
+            if self.fill_stuff {
+                match *shape.fill_indices() {
+                    &IndexType::Buffer { ind: ref ib } => {
+                        framebuffer
+                            .draw((*shape.fill_buffer(), per_instance()?), ib, prog, &u, &self.draw_params)
+                            .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                    }
+                    &IndexType::NoBuffer { ind: ref ib } => {
+                        framebuffer
+                            .draw((*shape.fill_buffer(), per_instance()?), ib, prog, &u, &self.draw_params)
+                            .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                    }
+                }
+            };
+            if self.stroke_stuff {
+                match *shape.stroke_indices() {
+                    &IndexType::NoBuffer { ind: ref ib } => {
+                        framebuffer
+                            .draw((*shape.stroke_buffer(), per_instance()?), ib, prog, &u, &self.draw_params)
+                            .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                    }
+                    &IndexType::Buffer { ind: ref ib } => {
+                        framebuffer
+                            .draw(
+                                (*shape.stroke_buffer(), per_instance()?),
+                                ib,
+                                stroke_prog,
+                                &stroke_u,
+                                &self.draw_params,
+                            )
+                            .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+                    }
+                }
+            };
+        }
+
+        Ok(())
+    }
+
 	/// The same as screen.draw(), except now a Mould will be drawn to the screen.
 	/// A Mould is just a shape that has been paired with a given shader which
 	/// alters how it is typically drawn to the screen. This allows one to have
 	/// a shader affect only one object instead of the whole drawing process.
-	/// The concept is borrowed from libCinder.
+	/// The concept is borrowed from libCinder. Since your shader replaces the one
+	/// that makes strokes thick, outlines are drawn as plain lines here.
     #[inline]
     pub fn draw_mould<S: Shape, U: Uniforms>(&mut self, mould: &Mould<U, S>) -> Result<(), ProcessingErr> {
+        self.flush()?;
         let shader = mould.get_shader();
         let shape = mould.get_shape();
-        let prog = &self.shader_bank[shader.get_idx()];
-        let uniforms = shader.get_uniforms();
-        let framebuffer = &mut self.fbo;
-        if self.fill_stuff {
-            match *shape.fill_indices() {
-                &IndexType::Buffer { ind: ref ib } => {
-                    framebuffer
-                        .draw(*shape.fill_buffer(), ib, prog, uniforms, &self.draw_params)
-                        .map_err(|e| ProcessingErr::FBDrawFailed(e))?
-                }
-                &IndexType::NoBuffer { ind: ref ib } => {
-                    framebuffer
-                        .draw(*shape.fill_buffer(), ib, prog, uniforms, &self.draw_params)
-                        .map_err(|e| ProcessingErr::FBDrawFailed(e))?
-                }
-            }
-        };
-        if self.stroke_stuff {
-            match *shape.stroke_indices() {
-                &IndexType::NoBuffer { ind: ref ib } => {
-                    framebuffer
-                        .draw(
-                            *shape.stroke_buffer(),
-                            ib,
-                            &prog,
-                            uniforms,
-                            &self.draw_params,
-                        )
-                        .map_err(|e| ProcessingErr::FBDrawFailed(e))?
-                }
-                _ => {}
-            }
-        };
-        
-        Ok(())
+        let tint = if self.tint_stuff { self.tint_col } else { [1.0; 4] };
+        let uniforms = &TintedUniforms::new(shader.get_uniforms(), tint);
+        draw_with_shader(self, shape, shader.get_idx(), uniforms)
     }
 
     // #[inline]
@@ -220,10 +315,67 @@ impl<'a> Screen<'a> {
     // }
 
     pub fn stroke_weight(&mut self, new_weight: f32) {
+        self.stroke_weight = new_weight;
         self.draw_params.point_size = Some(new_weight);
         self.draw_params.line_width = Some(new_weight);
     }
 
+	/// Choose how the loose ends of strokes are drawn: "ROUND" (the default) adds a
+	/// half circle, "SQUARE" cuts the stroke off right at the end point, and
+	/// "PROJECT" extends it past the end point by half of the stroke weight.
+    pub fn stroke_cap(&mut self, cap: &str) {
+        self.stroke_cap = cap.to_owned();
+    }
+
+	/// Choose how strokes are joined where two edges of an outline meet: "MITER"
+	/// (the default) extends both edges until they meet in a sharp point, "BEVEL"
+	/// cuts that point off, and "ROUND" rounds it. Very sharp miters are beveled.
+    pub fn stroke_join(&mut self, join: &str) {
+        self.stroke_join = join.to_owned();
+    }
+
+	/// Tell `processing-rs` whether the shapes that you create from now on will be
+	/// changed after they are created, with methods such as Rect::update() or
+	/// Rect::set_fill(). If so, their buffers are allocated in a way that makes such
+	/// updates cheap, which is worth it for shapes that change on every frame.
+    pub fn dynamic_shapes(&mut self, dynamic: bool) {
+        self.dynamic_shapes = dynamic;
+    }
+
+    // how the shapes squeeze their coordinates to keep the aspect ratio (see the
+    // instanced shaders, which have to undo this before they rotate anything)
+    fn aspect_correction(&self) -> [f32; 2] {
+        if self.preserve_aspect_ratio && self.aspect_ratio > 1f32 {
+            [1.0 / self.aspect_ratio, 1.0]
+        } else if self.preserve_aspect_ratio && self.aspect_ratio < 1f32 {
+            [1.0, self.aspect_ratio]
+        } else {
+            [1.0, 1.0]
+        }
+    }
+
+	/// The current stroke cap and join as the numbers that the stroke shader expects
+	/// for its strokeCap and strokeJoin uniforms (see shaders::init_stroke_shader()).
+	/// This is mostly useful if you want to pass them on to a shader of your own.
+    pub fn stroke_style(&self) -> (i32, i32) {
+        let cap = if self.stroke_cap == "SQUARE" {
+            0
+        } else if self.stroke_cap == "PROJECT" {
+            1
+        } else {
+            2
+        };
+        let join = if self.stroke_join == "BEVEL" {
+            1
+        } else if self.stroke_join == "ROUND" {
+            2
+        } else {
+            0
+        };
+
+        (cap, join)
+    }
+
     pub fn ellipse_mode(&mut self, mode: &str) {
         self.ellipse_mode = mode.to_owned();
     }
@@ -3,10 +3,14 @@ use std::f64;
 
 use glium;
 
-use {Screen, ScreenType};
+use Screen;
 use errors::ProcessingErr;
 
-use shapes::{Shape, ShapeVertex, IndexType, load_colors};
+use shapes::{Shape, ShapeVertex, ShapeData, IndexType, check_lengths, outline_edges,
+             stroke_indices, fan_triangles, triangle_indices};
+
+// how many vertices each ellipse is made of, including the one at its center
+const NUM_SLICES: f64 = 200.0 + 2.0;
 
 /// An ellipse is essentially a streched circle, with the circle itself being a special
 /// kind of ellipse. An ellipse has a width and a height.
@@ -15,6 +19,7 @@ pub struct Ellipse {
     stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
     fill_index_buffer: IndexType,
     stroke_index_buffer: IndexType,
+    data: ShapeData,
 }
 
 impl Shape for Ellipse {
@@ -51,6 +56,32 @@ impl Ellipse {
         wi: &[f64],
         hi: &[f64],
     ) -> Result<Self, ProcessingErr> {
+        let (shape, n) = Ellipse::vertices(screen, xci, yci, zci, wi, hi)?;
+        let data = ShapeData::new(screen, shape, n);
+        let (fill_shape_buffer, stroke_shape_buffer) = data.buffers(screen)?;
+
+        Ok(Ellipse {
+            fill_buffer: fill_shape_buffer,
+            stroke_buffer: stroke_shape_buffer,
+            fill_index_buffer: triangle_indices(screen, &fan_triangles(n, NUM_SLICES as usize))?,
+            stroke_index_buffer: stroke_indices(
+                screen,
+                &outline_edges(n, NUM_SLICES as usize, 1, NUM_SLICES as usize - 1, true),
+            )?,
+            data: data,
+        })
+    }
+
+    // the vertices of all of the sub-shapes, and how many of them there are
+    fn vertices(
+        screen: &Screen,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        wi: &[f64],
+        hi: &[f64],
+    ) -> Result<(Vec<ShapeVertex>, usize), ProcessingErr> {
+        let n = check_lengths(&[xci.len(), yci.len(), zci.len(), wi.len(), hi.len()])?;
         let mut xc = xci.iter().map(|&v| v).collect::<Vec<f64>>();
         let mut yc = yci.iter().map(|&v| v).collect::<Vec<f64>>();
         let zc = zci.iter().map(|&v| v).collect::<Vec<f64>>();
@@ -87,7 +118,7 @@ impl Ellipse {
             //  h = h ./ 2
         }
 
-        let num_slices = 200.0 + 2.0;
+        let num_slices = NUM_SLICES;
         let mut c: Vec<f64> = Vec::with_capacity(num_slices as usize - 1);
         let mut s: Vec<f64> = Vec::with_capacity(num_slices as usize - 1);
         let step = 1.0 / (num_slices - 1.0);
@@ -120,6 +151,7 @@ impl Ellipse {
                         ],
                         color: [0.0, 0.0, 0.0, 0.0],
                         texcoord: [0f32, 0.],
+                        normal: [0.0, 0.0, 0.0],
                     }
                 } else {
                     ShapeVertex {
@@ -134,41 +166,42 @@ impl Ellipse {
                         ],
                         color: [0.0, 0.0, 0.0, 0.0],
                         texcoord: [0f32, 0.],
+                        normal: [0.0, 0.0, 0.0],
                     }
                 };
                 shape.push(vertex);
             }
         }
 
-        load_colors(&mut shape, &screen.fill_col);
-        let fill_shape_buffer = match screen.display {
-            ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-        };
-
-        load_colors(&mut shape, &screen.stroke_col);
-        let stroke_shape_buffer = match screen.display {
-            ScreenType::Window(ref d) => {
-                glium::VertexBuffer::new(d, &shape[1..shape.len() - 1])
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?
-            }
-            ScreenType::Headless(ref d) => {
-                glium::VertexBuffer::new(d, &shape[1..shape.len() - 1])
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?
-            }
-        };
+        Ok((shape, n))
+    }
 
-        Ok(Ellipse {
-            fill_buffer: fill_shape_buffer,
-            stroke_buffer: stroke_shape_buffer,
-            fill_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::TriangleFan),
-            },
-            stroke_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::LineLoop),
-            },
-        })
+	/// Move and resize the ellipses of this Ellipse without creating a new one. The
+	/// arguments are the same as for Ellipse::new() and are interpreted according to
+	/// the current ellipse_mode, but they have to describe the same number of
+	/// ellipses as before. The colors stay as they are.
+    pub fn update(
+        &mut self,
+        screen: &Screen,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        wi: &[f64],
+        hi: &[f64],
+    ) -> Result<(), ProcessingErr> {
+        let (shape, n) = Ellipse::vertices(screen, xci, yci, zci, wi, hi)?;
+        self.data.set_vertices(shape, n, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+	/// Change the fill color of this shape in place. As in screen.fill(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_fill(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_fill(color_mat, &self.fill_buffer)
+    }
+
+	/// Change the stroke color of this shape in place. As in screen.stroke(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_stroke(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_stroke(color_mat, &self.fill_buffer, &self.stroke_buffer)
     }
 }
@@ -0,0 +1,322 @@
+use glium::uniforms::{Uniforms, UniformValue};
+
+use {Screen, Matrix4, Vector3, Unit};
+use color::Color;
+use errors::ProcessingErr;
+use shaders::TintedUniforms;
+
+use shapes::{Shape, draw_with_shader};
+use shapes::mould::Mould;
+
+/// The parts of the drawing style that can be overridden for a single child of a
+/// Group. Anything that is left as None is inherited from whatever is in effect when
+/// the child is drawn, i.e., from its parent group or, at the top, from the screen.
+/// Since the fill and stroke colors of a shape are fixed when it is created, only
+/// whether fill and stroke are drawn at all can be overridden here (use
+/// Rect::set_fill() and friends to change the colors themselves).
+#[derive(Clone, Debug, Default)]
+pub struct Style {
+    pub fill: Option<bool>,
+    pub stroke: Option<bool>,
+    pub stroke_weight: Option<f32>,
+    pub stroke_cap: Option<String>,
+    pub stroke_join: Option<String>,
+    pub tint: Option<Color>,
+}
+
+// everything that a Style can change, as it was before the change
+struct SavedStyle {
+    fill_stuff: bool,
+    stroke_stuff: bool,
+    stroke_weight: f32,
+    stroke_cap: String,
+    stroke_join: String,
+    tint_stuff: bool,
+    tint_col: [f32; 4],
+}
+
+// Moulds are generic over their uniforms, so they are stored behind this trait.
+trait GroupMould {
+    fn draw_placed(&self, screen: &mut Screen) -> Result<(), ProcessingErr>;
+}
+
+impl<U: Uniforms, S: Shape> GroupMould for Mould<U, S> {
+    fn draw_placed(&self, screen: &mut Screen) -> Result<(), ProcessingErr> {
+        screen.draw_placed_mould(self)
+    }
+}
+
+// the matrices that create_uniforms!{} adds to the uniforms of a mould
+const MATRIX_UNIFORMS: [&str; 4] = ["MVP", "modelMatrix", "viewMatrix", "projectionMatrix"];
+
+// Replaces the matrices that the uniforms of a mould were created with by the ones
+// that its place in the group calls for.
+struct PlacedUniforms<'u, U: Uniforms + 'u> {
+    uniforms: &'u U,
+    matrices: [[[f32; 4]; 4]; 4],
+}
+
+impl<'u, U: Uniforms> Uniforms for PlacedUniforms<'u, U> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut f: F) {
+        self.uniforms.visit_values(|name, value| if !MATRIX_UNIFORMS.contains(&name) {
+            f(name, value);
+        });
+        for (name, m) in MATRIX_UNIFORMS.iter().zip(self.matrices.iter()) {
+            f(name, UniformValue::Mat4(*m));
+        }
+    }
+}
+
+enum GroupItem<'g> {
+    Shape(Box<Shape + 'g>),
+    Mould(Box<GroupMould + 'g>),
+    Group(Group<'g>),
+}
+
+/// A single entry of a Group: a shape, a mould, or another group, along with the
+/// transformation matrix that places it inside of its parent, whether it is visible,
+/// and the parts of the drawing style that it overrides. The transformation methods
+/// work just like their counterparts on the screen (e.g., screen.translate()), but
+/// they only affect this child and whatever it contains.
+pub struct GroupChild<'g> {
+    item: GroupItem<'g>,
+    matrix: Matrix4<f32>,
+    visible: bool,
+    style: Style,
+}
+
+impl<'g> GroupChild<'g> {
+    fn new(item: GroupItem<'g>) -> Self {
+        GroupChild {
+            item: item,
+            matrix: Matrix4::identity(),
+            visible: true,
+            style: Style::default(),
+        }
+    }
+
+	/// Move the child by (x, y, z) relative to its parent.
+    pub fn translate(&mut self, x: f32, y: f32, z: f32) -> &mut Self {
+        let m = Matrix4::new(1., 0., 0., x, 0., 1., 0., y, 0., 0., 1., z, 0., 0., 0., 1.);
+        self.matrix = m * self.matrix;
+        self
+    }
+
+	/// Rotate the child by the given angle about a vector in the direction (x, y, z).
+    pub fn rotate(&mut self, angle: f32, x: f32, y: f32, z: f32) -> &mut Self {
+        let m = Matrix4::from_axis_angle(&Unit::new_normalize(Vector3::new(x, y, z)), angle);
+        self.matrix = m * self.matrix;
+        self
+    }
+
+	/// Scale the child along the x-, y-, and z-axes.
+    pub fn scale(&mut self, x: f32, y: f32, z: f32) -> &mut Self {
+        self.matrix.append_nonuniform_scaling_mut(&Vector3::new(x, y, z));
+        self
+    }
+
+	/// Replace the child's transformation matrix altogether.
+    pub fn set_matrix(&mut self, m: Matrix4<f32>) -> &mut Self {
+        self.matrix = m;
+        self
+    }
+
+	/// Undo all of the transformations of the child, so that it is drawn exactly where
+	/// its parent is.
+    pub fn reset_matrix(&mut self) -> &mut Self {
+        self.matrix = Matrix4::identity();
+        self
+    }
+
+    pub fn get_matrix(&self) -> &Matrix4<f32> {
+        &self.matrix
+    }
+
+	/// Show or hide the child. A hidden group hides everything that it contains.
+    pub fn set_visible(&mut self, visible: bool) -> &mut Self {
+        self.visible = visible;
+        self
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+	/// Override parts of the drawing style for this child and, if it is a group,
+	/// for everything that it contains (unless they override the style themselves).
+    pub fn set_style(&mut self, style: Style) -> &mut Self {
+        self.style = style;
+        self
+    }
+
+    pub fn get_style(&self) -> &Style {
+        &self.style
+    }
+
+	/// The group held by this child, if it holds one, so that its own children can be
+	/// reached.
+    pub fn as_group(&self) -> Option<&Group<'g>> {
+        match self.item {
+            GroupItem::Group(ref g) => Some(g),
+            _ => None,
+        }
+    }
+
+    pub fn as_group_mut(&mut self) -> Option<&mut Group<'g>> {
+        match self.item {
+            GroupItem::Group(ref mut g) => Some(g),
+            _ => None,
+        }
+    }
+}
+
+/// A tree of shapes, similar to a PShape group in Processing. A group owns any number
+/// of shapes, moulds, and other groups, each of which has its own transformation
+/// matrix, visibility, and style overrides (see GroupChild). When the group is drawn
+/// with screen.draw_group(), every child is drawn with the screen's transformation
+/// matrix multiplied by the matrices of all of its parents, so that moving a group
+/// moves everything in it. For example, a face made of several ellipses can be put
+/// into one group and then moved around as a unit by nesting that group in another
+/// one or by calling screen.translate() before drawing it.
+pub struct Group<'g> {
+    children: Vec<GroupChild<'g>>,
+}
+
+impl<'g> Group<'g> {
+    pub fn new() -> Self {
+        Group { children: Vec::new() }
+    }
+
+	/// Add a shape to the group and return the new child, so that it can be placed
+	/// right away (e.g., `group.add_shape(eye).translate(-0.2, 0.1, 0.)`).
+    pub fn add_shape<S: Shape + 'g>(&mut self, shape: S) -> &mut GroupChild<'g> {
+        self.push(GroupItem::Shape(Box::new(shape)))
+    }
+
+	/// Add a mould to the group. Its shader receives the MVP and model matrices of the
+	/// mould's place in the group, as well as the current view and projection
+	/// matrices, in place of the ones that its uniforms were created with.
+    pub fn add_mould<U: Uniforms + 'g, S: Shape + 'g>(&mut self, mould: Mould<U, S>) -> &mut GroupChild<'g> {
+        self.push(GroupItem::Mould(Box::new(mould)))
+    }
+
+	/// Add another group as a child of this one.
+    pub fn add_group(&mut self, group: Group<'g>) -> &mut GroupChild<'g> {
+        self.push(GroupItem::Group(group))
+    }
+
+    fn push(&mut self, item: GroupItem<'g>) -> &mut GroupChild<'g> {
+        self.children.push(GroupChild::new(item));
+        self.children.last_mut().unwrap()
+    }
+
+	/// Take the i-th child out of the group.
+    pub fn remove(&mut self, i: usize) -> GroupChild<'g> {
+        self.children.remove(i)
+    }
+
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+	/// The i-th child of the group, in the order in which they were added, which is
+	/// also the order in which they are drawn.
+    pub fn child(&self, i: usize) -> Option<&GroupChild<'g>> {
+        self.children.get(i)
+    }
+
+    pub fn child_mut(&mut self, i: usize) -> Option<&mut GroupChild<'g>> {
+        self.children.get_mut(i)
+    }
+}
+
+impl<'a> Screen<'a> {
+	/// Draw every visible child of a group, in the order in which they were added.
+	/// Each child is drawn with its own transformation matrix applied on top of the
+	/// current one, using the matrix stack (see screen.push_matrix()), and with its
+	/// style overrides in effect. The screen's matrix and style are the same afterwards
+	/// as they were before.
+    pub fn draw_group(&mut self, group: &Group) -> Result<(), ProcessingErr> {
+        for child in &group.children {
+            if !child.visible {
+                continue;
+            }
+            self.push_matrix();
+            self.matrices.curr_matrix = self.matrices.curr_matrix * child.matrix;
+            let saved = self.apply_style(&child.style);
+            let result = match child.item {
+                GroupItem::Shape(ref shape) => self.draw(&**shape),
+                GroupItem::Mould(ref mould) => mould.draw_placed(self),
+                GroupItem::Group(ref g) => self.draw_group(g),
+            };
+            self.restore_style(saved);
+            self.pop_matrix();
+            result?;
+        }
+
+        Ok(())
+    }
+
+    fn apply_style(&mut self, style: &Style) -> SavedStyle {
+        let saved = SavedStyle {
+            fill_stuff: self.fill_stuff,
+            stroke_stuff: self.stroke_stuff,
+            stroke_weight: self.stroke_weight,
+            stroke_cap: self.stroke_cap.clone(),
+            stroke_join: self.stroke_join.clone(),
+            tint_stuff: self.tint_stuff,
+            tint_col: self.tint_col,
+        };
+        if let Some(fill) = style.fill {
+            self.fill_stuff = fill;
+        }
+        if let Some(stroke) = style.stroke {
+            self.stroke_stuff = stroke;
+        }
+        if let Some(weight) = style.stroke_weight {
+            self.stroke_weight(weight);
+        }
+        if let Some(ref cap) = style.stroke_cap {
+            self.stroke_cap(cap);
+        }
+        if let Some(ref join) = style.stroke_join {
+            self.stroke_join(join);
+        }
+        if let Some(c) = style.tint {
+            self.tint_stuff = true;
+            self.tint_col = [c.r, c.g, c.b, c.a];
+        }
+
+        saved
+    }
+
+    fn restore_style(&mut self, saved: SavedStyle) {
+        self.fill_stuff = saved.fill_stuff;
+        self.stroke_stuff = saved.stroke_stuff;
+        self.stroke_weight(saved.stroke_weight);
+        self.stroke_cap = saved.stroke_cap;
+        self.stroke_join = saved.stroke_join;
+        self.tint_stuff = saved.tint_stuff;
+        self.tint_col = saved.tint_col;
+    }
+
+    // The same as screen.draw_mould(), except that the mould's matrices are replaced
+    // by the current ones.
+    fn draw_placed_mould<S: Shape, U: Uniforms>(&mut self, mould: &Mould<U, S>) -> Result<(), ProcessingErr> {
+        self.flush()?;
+        let shader = mould.get_shader();
+        let shape = mould.get_shape();
+        let tint = if self.tint_stuff { self.tint_col } else { [1.0; 4] };
+        let placed = PlacedUniforms {
+            uniforms: shader.get_uniforms(),
+            matrices: [
+                self.matrices.mvp().into(),
+                self.matrices.curr_matrix.into(),
+                self.matrices.view_matrix.into(),
+                self.matrices.projection_matrix.into(),
+            ],
+        };
+        let uniforms = &TintedUniforms::new(&placed, tint);
+        draw_with_shader(self, shape, shader.get_idx(), uniforms)
+    }
+}
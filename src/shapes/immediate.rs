@@ -0,0 +1,588 @@
+use std::f32;
+use std::f64;
+use std::mem;
+
+use glium;
+use glium::Surface;
+
+use {Screen, ScreenType};
+use errors::ProcessingErr;
+use shaders::BATCH_SHADER;
+
+// maximum number of vertices that are sent to the GPU in one go
+const BATCH_CAPACITY: usize = 1 << 16;
+const ELLIPSE_SEGMENTS: usize = 100;
+// these match the stroke shader (see shaders::init_stroke_shader())
+const ROUND_STEPS: usize = 8;
+const MITER_LIMIT: f32 = 4.0;
+
+/// A vertex of the immediate-mode batch. Its position is already in clip space, i.e.,
+/// it has been transformed by the matrices that were current when it was drawn, but
+/// not divided by w yet, so that OpenGL can still clip it against the near plane and
+/// interpolate depth and color across triangles in perspective.
+#[derive(Copy, Clone)]
+pub struct BatchVertex {
+    position: [f32; 4],
+    color: [f32; 4],
+}
+
+implement_vertex!(BatchVertex, position, color);
+
+/// Holds the geometry produced by the immediate-mode drawing functions (screen.rect(),
+/// screen.ellipse(), etc.) until it is sent to the GPU. Everything is stored as
+/// already-transformed triangles, including strokes, so that a whole run of calls
+/// can be drawn with a single draw call, in the same order that they were made.
+pub struct Batch {
+    vertices: Vec<BatchVertex>,
+    buffer: Option<glium::VertexBuffer<BatchVertex>>,
+    blend: glium::Blend,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Batch {
+            vertices: Vec::new(),
+            buffer: None,
+            blend: Default::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
+
+impl<'a> Screen<'a> {
+	/// Draw a rectangle right away, without creating a Rect first. The position
+	/// (x, y) and the size (w, h) are interpreted according to the current rect_mode,
+	/// just as for Rect::new(), and the current fill and stroke colors and the current
+	/// transformation matrix are used. The rectangle is actually sent to the GPU
+	/// together with all of the other immediate-mode shapes the next time that the
+	/// screen is flushed, which happens automatically.
+    pub fn rect(&mut self, x: f64, y: f64, w: f64, h: f64) -> Result<(), ProcessingErr> {
+        let (left, top, w, h) = if self.rect_mode == "CENTER" {
+            (x - w / 2.0, y + h / 2.0, w, h)
+        } else if self.rect_mode == "RADIUS" {
+            (x - w, y + h, 2.0 * w, 2.0 * h)
+        } else if self.rect_mode == "CORNERS" {
+            (x.min(w), y.max(h), (w - x).abs(), (y - h).abs())
+        } else {
+            (x, y, w, h)
+        };
+
+        let corners = [
+            self.batch_position(left, top, 0.0),
+            self.batch_position(left + w, top, 0.0),
+            self.batch_position(left + w, top - h, 0.0),
+            self.batch_position(left, top - h, 0.0),
+        ];
+        self.batch_polygon(&corners)
+    }
+
+	/// Draw an ellipse right away, without creating an Ellipse first. The position
+	/// (x, y) and the size (w, h) are interpreted according to the current
+	/// ellipse_mode.
+    pub fn ellipse(&mut self, x: f64, y: f64, w: f64, h: f64) -> Result<(), ProcessingErr> {
+        let (xc, yc, rx, ry) = if self.ellipse_mode == "RADIUS" {
+            (x, y, w, h)
+        } else if self.ellipse_mode == "CORNER" {
+            (x + w / 2.0, y - h / 2.0, w / 2.0, h / 2.0)
+        } else if self.ellipse_mode == "CORNERS" {
+            ((x + w) / 2.0, (y + h) / 2.0, (w - x).abs() / 2.0, (y - h).abs() / 2.0)
+        } else {
+            (x, y, w / 2.0, h / 2.0)
+        };
+
+        let step = 2.0 * f64::consts::PI / ELLIPSE_SEGMENTS as f64;
+        let outline = (0..ELLIPSE_SEGMENTS)
+            .map(|i| {
+                let a = i as f64 * step;
+                self.batch_position(xc + rx * a.cos(), yc + ry * a.sin(), 0.0)
+            })
+            .collect::<Vec<[f32; 4]>>();
+        self.batch_polygon(&outline)
+    }
+
+	/// Draw a line right away, from (x1, y1) to (x2, y2), using the current stroke
+	/// color and weight. Nothing is drawn if stroke has been turned off.
+    pub fn line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64) -> Result<(), ProcessingErr> {
+        if !self.stroke_stuff {
+            return Ok(());
+        }
+        let ends = [
+            self.batch_position(x1, y1, 0.0),
+            self.batch_position(x2, y2, 0.0),
+        ];
+        let mut verts = vec![];
+        self.stroke_triangles(&ends, false, &mut verts);
+        self.batch_push(verts)
+    }
+
+	/// Draw a triangle right away, with corners at (x1, y1), (x2, y2), and (x3, y3).
+    pub fn triangle(
+        &mut self,
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        x3: f64,
+        y3: f64,
+    ) -> Result<(), ProcessingErr> {
+        let corners = [
+            self.batch_position(x1, y1, 0.0),
+            self.batch_position(x2, y2, 0.0),
+            self.batch_position(x3, y3, 0.0),
+        ];
+        self.batch_polygon(&corners)
+    }
+
+	/// Draw a quadrilateral right away, with its corners given in order around its
+	/// edge.
+    pub fn quad(
+        &mut self,
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        x3: f64,
+        y3: f64,
+        x4: f64,
+        y4: f64,
+    ) -> Result<(), ProcessingErr> {
+        let corners = [
+            self.batch_position(x1, y1, 0.0),
+            self.batch_position(x2, y2, 0.0),
+            self.batch_position(x3, y3, 0.0),
+            self.batch_position(x4, y4, 0.0),
+        ];
+        self.batch_polygon(&corners)
+    }
+
+	/// Draw a single point right away, in the current stroke color and as wide as the
+	/// current stroke weight. The point is round if the stroke cap is "ROUND" and
+	/// square otherwise.
+    pub fn point(&mut self, x: f64, y: f64) -> Result<(), ProcessingErr> {
+        if !self.stroke_stuff {
+            return Ok(());
+        }
+        let p = self.batch_position(x, y, 0.0);
+        if !in_front(p) {
+            return Ok(());
+        }
+        // the size of the point is given in pixels, so it is scaled by w to undo the
+        // perspective division
+        let hw = p[3] * self.stroke_weight / self.fb_size[0] as f32;
+        let hh = p[3] * self.stroke_weight / self.fb_size[1] as f32;
+        let outline = if self.stroke_style().0 == 2 {
+            let step = 2.0 * f32::consts::PI / ELLIPSE_SEGMENTS as f32;
+            (0..ELLIPSE_SEGMENTS)
+                .map(|i| {
+                    let a = i as f32 * step;
+                    [p[0] + hw * a.cos(), p[1] + hh * a.sin(), p[2], p[3]]
+                })
+                .collect::<Vec<[f32; 4]>>()
+        } else {
+            vec![
+                [p[0] - hw, p[1] + hh, p[2], p[3]],
+                [p[0] + hw, p[1] + hh, p[2], p[3]],
+                [p[0] + hw, p[1] - hh, p[2], p[3]],
+                [p[0] - hw, p[1] - hh, p[2], p[3]],
+            ]
+        };
+        let color = self.batch_color(&self.stroke_col);
+        let mut verts = vec![];
+        fan_triangles(&outline, color, &mut verts);
+        self.batch_push(verts)
+    }
+
+	/// Send all of the shapes drawn with the immediate-mode functions so far to the
+	/// GPU. You will usually not need to call this yourself, since it is called
+	/// before anything else is drawn to the screen and before screen.reveal().
+    pub fn flush(&mut self) -> Result<(), ProcessingErr> {
+        if self.batch.vertices.is_empty() {
+            return Ok(());
+        }
+
+        if self.batch.buffer.is_none() {
+            self.batch.buffer = Some(match self.display {
+                ScreenType::Window(ref d) => glium::VertexBuffer::empty_dynamic(d, BATCH_CAPACITY)
+                    .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+                ScreenType::Headless(ref d) => glium::VertexBuffer::empty_dynamic(d, BATCH_CAPACITY)
+                    .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+            });
+        }
+
+        // the vertices were already transformed when they were added to the batch
+        let u = glium::uniforms::EmptyUniforms;
+        let mut params = self.draw_params.clone();
+        params.blend = self.batch.blend;
+
+        let mut vertices = mem::replace(&mut self.batch.vertices, Vec::new());
+        {
+            let buffer = self.batch.buffer.as_ref().unwrap();
+            let framebuffer = &mut self.fbo;
+            for chunk in vertices.chunks(BATCH_CAPACITY - BATCH_CAPACITY % 3) {
+                let slice = buffer.slice(0..chunk.len()).unwrap();
+                slice.invalidate();
+                slice.write(chunk);
+                framebuffer
+                    .draw(
+                        slice,
+                        &glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                        &self.shader_bank[BATCH_SHADER],
+                        &u,
+                        &params,
+                    )
+                    .map_err(|e| ProcessingErr::FBDrawFailed(e))?;
+            }
+        }
+        vertices.clear();
+        self.batch.vertices = vertices;
+
+        Ok(())
+    }
+
+    // apply the aspect ratio correction and the current transformation matrices, so
+    // that shapes drawn under different matrices can share a batch. The result is in
+    // clip space, since dividing by w here would wrongly project points that are
+    // behind the eye in front of it.
+    fn batch_position(&self, x: f64, y: f64, z: f64) -> [f32; 4] {
+        let mut x = x;
+        let mut y = y;
+        if self.preserve_aspect_ratio && self.aspect_ratio != 1f32 {
+            if self.aspect_ratio > 1f32 {
+                x = x / self.aspect_ratio as f64;
+            } else {
+                y = y * self.aspect_ratio as f64;
+            }
+        }
+
+        let m = self.matrices.mvp();
+        let (x, y, z) = (x as f32, y as f32, z as f32);
+        [
+            m[(0, 0)] * x + m[(0, 1)] * y + m[(0, 2)] * z + m[(0, 3)],
+            m[(1, 0)] * x + m[(1, 1)] * y + m[(1, 2)] * z + m[(1, 3)],
+            m[(2, 0)] * x + m[(2, 1)] * y + m[(2, 2)] * z + m[(2, 3)],
+            m[(3, 0)] * x + m[(3, 1)] * y + m[(3, 2)] * z + m[(3, 3)],
+        ]
+    }
+
+    fn batch_color(&self, col: &[f32]) -> [f32; 4] {
+        [col[0], col[1], col[2], col[3]]
+    }
+
+    fn batch_polygon(&mut self, outline: &[[f32; 4]]) -> Result<(), ProcessingErr> {
+        let mut verts = vec![];
+        if self.fill_stuff {
+            let color = self.batch_color(&self.fill_col);
+            fan_triangles(outline, color, &mut verts);
+        }
+        if self.stroke_stuff {
+            self.stroke_triangles(outline, true, &mut verts);
+        }
+        self.batch_push(verts)
+    }
+
+    // Turn the line through the given points into triangles that are stroke_weight
+    // pixels wide, with the same caps and joins that the stroke shader gives to shapes.
+    // The parts of the line that are behind the eye are cut off first, since they
+    // cannot be projected onto the screen.
+    fn stroke_triangles(&self, points: &[[f32; 4]], closed: bool, verts: &mut Vec<BatchVertex>) {
+        for (run, closed) in clip_near(points, closed) {
+            self.stroke_run(&run, closed, verts);
+        }
+    }
+
+    fn stroke_run(&self, points: &[[f32; 4]], closed: bool, verts: &mut Vec<BatchVertex>) {
+        let color = self.batch_color(&self.stroke_col);
+        let half = [self.fb_size[0] as f32 / 2.0, self.fb_size[1] as f32 / 2.0];
+        let r = self.stroke_weight / 2.0;
+        let (cap, join) = self.stroke_style();
+
+        // work in pixels, skipping points that land on top of the previous one, and
+        // keep the depth and w of each point to put the stroke back into clip space
+        let mut px: Vec<([f32; 2], Depth)> = vec![];
+        for p in points {
+            let q = [p[0] / p[3] * half[0], p[1] / p[3] * half[1]];
+            if px.last().map_or(true, |l| length(sub(q, l.0)) > 1e-4) {
+                px.push((q, [p[2] / p[3], p[3]]));
+            }
+        }
+        if closed && px.len() > 2 && length(sub(px[0].0, px[px.len() - 1].0)) <= 1e-4 {
+            px.pop();
+        }
+        let m = px.len();
+        if m < 2 {
+            return;
+        }
+        let closed = closed && m > 2;
+        let segments = if closed { m } else { m - 1 };
+
+        let mut tris = vec![];
+        for i in 0..segments {
+            let (p1, d1) = px[i];
+            let (p2, d2) = px[(i + 1) % m];
+            let prev = if closed || i > 0 { Some(px[(i + m - 1) % m].0) } else { None };
+            let next = if closed || i + 2 < m { Some(px[(i + 2) % m].0) } else { None };
+
+            let dir = normalize(sub(p2, p1));
+            let n = scale(perp(dir), r);
+            let (mut s0, mut s1) = (add(p1, n), sub(p1, n));
+            let (mut e0, mut e1) = (add(p2, n), sub(p2, n));
+
+            match prev {
+                Some(p0) => {
+                    let prev_dir = normalize(sub(p1, p0));
+                    match miter(prev_dir, dir, r).filter(|_| join == 0) {
+                        Some(mv) => {
+                            s0 = add(p1, mv);
+                            s1 = sub(p1, mv);
+                        }
+                        None => {
+                            // the segment that starts at a corner fills its outside
+                            let prev_n = scale(perp(prev_dir), r);
+                            let turn = cross(prev_dir, dir);
+                            let (from, to) = if turn > 0.0 {
+                                (scale(prev_n, -1.0), scale(n, -1.0))
+                            } else {
+                                (prev_n, n)
+                            };
+                            if join == 2 {
+                                fan(p1, d1, from, cross(from, to).atan2(dot(from, to)), &mut tris);
+                            } else {
+                                tris.push([(p1, d1), (add(p1, from), d1), (add(p1, to), d1)]);
+                            }
+                        }
+                    }
+                }
+                None if cap == 1 => {
+                    s0 = sub(s0, scale(dir, r));
+                    s1 = sub(s1, scale(dir, r));
+                }
+                None if cap == 2 => fan(p1, d1, n, f32::consts::PI, &mut tris),
+                None => {}
+            }
+
+            match next {
+                Some(p3) => {
+                    if let Some(mv) = miter(dir, normalize(sub(p3, p2)), r).filter(|_| join == 0) {
+                        e0 = add(p2, mv);
+                        e1 = sub(p2, mv);
+                    }
+                }
+                None if cap == 1 => {
+                    e0 = add(e0, scale(dir, r));
+                    e1 = add(e1, scale(dir, r));
+                }
+                None if cap == 2 => fan(p2, d2, scale(n, -1.0), f32::consts::PI, &mut tris),
+                None => {}
+            }
+
+            tris.push([(s0, d1), (e0, d2), (e1, d2)]);
+            tris.push([(e1, d2), (s1, d1), (s0, d1)]);
+        }
+
+        for t in &tris {
+            for &(p, d) in t {
+                verts.push(BatchVertex {
+                    position: [p[0] / half[0] * d[1], p[1] / half[1] * d[1], d[0] * d[1], d[1]],
+                    color: color,
+                });
+            }
+        }
+    }
+
+    fn batch_push(&mut self, verts: Vec<BatchVertex>) -> Result<(), ProcessingErr> {
+        if !self.batch.vertices.is_empty() && self.batch.blend != self.draw_params.blend {
+            self.flush()?;
+        }
+        self.batch.blend = self.draw_params.blend;
+        self.batch.vertices.extend(verts);
+        if self.batch.vertices.len() >= BATCH_CAPACITY {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+fn fan_triangles(outline: &[[f32; 4]], color: [f32; 4], verts: &mut Vec<BatchVertex>) {
+    for i in 1..outline.len().saturating_sub(1) {
+        for &p in &[outline[0], outline[i], outline[i + 1]] {
+            verts.push(BatchVertex {
+                position: p,
+                color: color,
+            });
+        }
+    }
+}
+
+// the depth (z / w) of a point of a stroke and its w in clip space
+type Depth = [f32; 2];
+
+// whether a point in clip space is on the visible side of the near plane
+fn in_front(p: [f32; 4]) -> bool {
+    p[2] + p[3] >= 0.0 && p[3] > 0.0
+}
+
+// Cut the parts of a line in clip space that are behind the near plane off, which
+// leaves a number of runs of connected points. A closed line only stays closed if
+// none of it was cut off.
+fn clip_near(points: &[[f32; 4]], closed: bool) -> Vec<(Vec<[f32; 4]>, bool)> {
+    let m = points.len();
+    if m == 0 {
+        return vec![];
+    }
+    if points.iter().all(|&p| in_front(p)) {
+        return vec![(points.to_vec(), closed)];
+    }
+
+    // a closed line starts at a point that is cut off, so that no run wraps around
+    let (first, segments) = if closed {
+        ((0..m).find(|&i| !in_front(points[i])).unwrap(), m)
+    } else {
+        (0, m - 1)
+    };
+    let distance = |p: [f32; 4]| p[2] + p[3];
+    let between = |a: [f32; 4], b: [f32; 4]| {
+        let t = distance(a) / (distance(a) - distance(b));
+        [
+            a[0] + t * (b[0] - a[0]),
+            a[1] + t * (b[1] - a[1]),
+            a[2] + t * (b[2] - a[2]),
+            a[3] + t * (b[3] - a[3]),
+        ]
+    };
+
+    let mut runs = vec![];
+    let mut run = vec![];
+    if in_front(points[first]) {
+        run.push(points[first]);
+    }
+    for i in first..first + segments {
+        let (a, b) = (points[i % m], points[(i + 1) % m]);
+        match (in_front(a), in_front(b)) {
+            (true, true) => run.push(b),
+            (true, false) => {
+                run.push(between(a, b));
+                runs.push((mem::replace(&mut run, vec![]), false));
+            }
+            (false, true) => {
+                run.push(between(a, b));
+                run.push(b);
+            }
+            (false, false) => {}
+        }
+    }
+    if run.len() > 1 {
+        runs.push((run, false));
+    }
+
+    runs
+}
+
+// the small amount of 2D vector math needed to build strokes, in pixels
+
+fn add(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn scale(a: [f32; 2], s: f32) -> [f32; 2] {
+    [a[0] * s, a[1] * s]
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn cross(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+fn length(a: [f32; 2]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+fn normalize(a: [f32; 2]) -> [f32; 2] {
+    scale(a, 1.0 / length(a))
+}
+
+fn perp(a: [f32; 2]) -> [f32; 2] {
+    [-a[1], a[0]]
+}
+
+// Half of the miter where a line going in direction a turns into one going in
+// direction b, unless it would be longer than MITER_LIMIT times the half width r.
+fn miter(a: [f32; 2], b: [f32; 2], r: f32) -> Option<[f32; 2]> {
+    let sum = add(perp(a), perp(b));
+    if length(sum) < 1e-4 {
+        return None;
+    }
+    let m = normalize(sum);
+    let d = dot(m, perp(b));
+    if d < 1.0 / MITER_LIMIT {
+        return None;
+    }
+
+    Some(scale(m, r / d))
+}
+
+// triangles around c, covering the sector that v sweeps when it is rotated by angle
+fn fan(c: [f32; 2], d: Depth, v: [f32; 2], angle: f32, tris: &mut Vec<[([f32; 2], Depth); 3]>) {
+    let rotate = |a: f32| [v[0] * a.cos() - v[1] * a.sin(), v[0] * a.sin() + v[1] * a.cos()];
+    for i in 0..ROUND_STEPS {
+        let a0 = angle * i as f32 / ROUND_STEPS as f32;
+        let a1 = angle * (i + 1) as f32 / ROUND_STEPS as f32;
+        tris.push([(c, d), (add(c, rotate(a0)), d), (add(c, rotate(a1)), d)]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::clip_near;
+
+    // points in clip space with z = 0, so that they are in front of the near plane
+    // exactly when w > 0
+    fn p(x: f32, w: f32) -> [f32; 4] {
+        [x, 0.0, 0.0, w]
+    }
+
+    #[test]
+    fn visible_lines_are_kept() {
+        let line = [p(0.0, 1.0), p(1.0, 1.0), p(2.0, 2.0)];
+        assert_eq!(clip_near(&line, true), vec![(line.to_vec(), true)]);
+        assert_eq!(clip_near(&line, false), vec![(line.to_vec(), false)]);
+    }
+
+    #[test]
+    fn hidden_lines_disappear() {
+        let line = [p(0.0, -1.0), p(1.0, -2.0)];
+        assert!(clip_near(&line, false).is_empty());
+        assert!(clip_near(&line, true).is_empty());
+    }
+
+    #[test]
+    fn open_line_is_cut_at_the_near_plane() {
+        let line = [p(0.0, 1.0), p(2.0, -1.0), p(4.0, 1.0)];
+        assert_eq!(
+            clip_near(&line, false),
+            vec![
+                (vec![p(0.0, 1.0), p(1.0, 0.0)], false),
+                (vec![p(3.0, 0.0), p(4.0, 1.0)], false),
+            ]
+        );
+    }
+
+    #[test]
+    fn closed_line_is_opened_where_it_is_cut() {
+        let square = [p(0.0, 1.0), p(2.0, 1.0), p(2.0, -1.0), p(0.0, -1.0)];
+        assert_eq!(
+            clip_near(&square, true),
+            vec![(vec![p(0.0, 0.0), p(0.0, 1.0), p(2.0, 1.0), p(2.0, 0.0)], false)]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use glium;
+
+use {Screen, ScreenType};
+use errors::ProcessingErr;
+
+use shapes::{Shape, check_lengths, nth_color};
+
+#[derive(Copy, Clone)]
+pub struct InstanceAttr {
+    offset: [f32; 3],
+    scale: [f32; 3],
+    rotation: f32,
+    instance_color: [f32; 4],
+}
+
+implement_vertex!(InstanceAttr, offset, scale, rotation, instance_color);
+
+/// Many copies of the same shape, which are all drawn with a single draw call by
+/// screen.draw_instanced(). This is much faster than drawing thousands of separate
+/// shapes, e.g., for a field of dots. Every copy (or instance) has its own offset,
+/// scale, rotation, and color, all of which can be changed on every frame without
+/// touching the shape itself. Each copy is first scaled and rotated around the origin
+/// of the shape's coordinates and then moved by its offset, and its color multiplies
+/// the fill and stroke colors of the shape.
+pub struct Instanced<S: Shape> {
+    shape: S,
+    instances: Vec<InstanceAttr>,
+    instance_buffer: glium::VertexBuffer<InstanceAttr>,
+}
+
+impl<S: Shape> Instanced<S> {
+	/// Make one copy of the shape for every offset (xi, yi, zi). The offsets use the
+	/// same coordinates as the shapes themselves. All copies start out with a scale
+	/// of 1, no rotation, and white as their color, so that they look just like the
+	/// original shape.
+    pub fn new(
+        screen: &Screen,
+        shape: S,
+        xi: &[f64],
+        yi: &[f64],
+        zi: &[f64],
+    ) -> Result<Self, ProcessingErr> {
+        let n = check_lengths(&[xi.len(), yi.len(), zi.len()])?;
+        let mut instances = Vec::with_capacity(n);
+        for i in 0..n {
+            instances.push(InstanceAttr {
+                offset: [xi[i] as f32, yi[i] as f32, zi[i] as f32],
+                scale: [1.0, 1.0, 1.0],
+                rotation: 0.0,
+                instance_color: [1.0, 1.0, 1.0, 1.0],
+            });
+        }
+
+        // the instances are meant to change often, so always ask for a dynamic buffer
+        let instance_buffer = match screen.display {
+            ScreenType::Window(ref d) => glium::VertexBuffer::dynamic(d, &instances)
+                .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+            ScreenType::Headless(ref d) => glium::VertexBuffer::dynamic(d, &instances)
+                .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+        };
+
+        Ok(Instanced {
+            shape: shape,
+            instances: instances,
+            instance_buffer: instance_buffer,
+        })
+    }
+
+    pub fn get_shape(&self) -> &S {
+        &self.shape
+    }
+
+    pub fn get_instance_buffer(&self) -> &glium::VertexBuffer<InstanceAttr> {
+        &self.instance_buffer
+    }
+
+	/// How many copies of the shape there are.
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+	/// Move every copy to a new offset. There has to be exactly one offset per copy.
+    pub fn set_offsets(&mut self, xi: &[f64], yi: &[f64], zi: &[f64]) -> Result<(), ProcessingErr> {
+        check_lengths(&[self.instances.len(), xi.len(), yi.len(), zi.len()])?;
+        for (i, inst) in self.instances.iter_mut().enumerate() {
+            inst.offset = [xi[i] as f32, yi[i] as f32, zi[i] as f32];
+        }
+        self.instance_buffer.write(&self.instances);
+
+        Ok(())
+    }
+
+	/// Scale every copy along the x-, y-, and z-axes. There has to be exactly one
+	/// scale factor per copy and axis.
+    pub fn set_scales(&mut self, sx: &[f64], sy: &[f64], sz: &[f64]) -> Result<(), ProcessingErr> {
+        check_lengths(&[self.instances.len(), sx.len(), sy.len(), sz.len()])?;
+        for (i, inst) in self.instances.iter_mut().enumerate() {
+            inst.scale = [sx[i] as f32, sy[i] as f32, sz[i] as f32];
+        }
+        self.instance_buffer.write(&self.instances);
+
+        Ok(())
+    }
+
+	/// Rotate every copy around the z-axis by the given angle, in radians. There has
+	/// to be exactly one angle per copy.
+    pub fn set_rotations(&mut self, angles: &[f64]) -> Result<(), ProcessingErr> {
+        check_lengths(&[self.instances.len(), angles.len()])?;
+        for (inst, &a) in self.instances.iter_mut().zip(angles.iter()) {
+            inst.rotation = a as f32;
+        }
+        self.instance_buffer.write(&self.instances);
+
+        Ok(())
+    }
+
+	/// Change the colors of the copies. As with screen.fill(), this is either a
+	/// single color for all of them or one color (i.e., four values) per copy.
+    pub fn set_colors(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        let n = self.instances.len();
+        let colors = (0..n)
+            .map(|i| nth_color(color_mat, i, n))
+            .collect::<Result<Vec<[f32; 4]>, ProcessingErr>>()?;
+        for (inst, &c) in self.instances.iter_mut().zip(colors.iter()) {
+            inst.instance_color = c;
+        }
+        self.instance_buffer.write(&self.instances);
+
+        Ok(())
+    }
+}
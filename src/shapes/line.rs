@@ -2,10 +2,11 @@ use std::f32;
 
 use glium;
 
-use {Screen, ScreenType};
+use Screen;
 use errors::ProcessingErr;
 
-use shapes::{Shape, ShapeVertex, IndexType};
+use shapes::{Shape, ShapeVertex, ShapeData, IndexType, check_lengths, outline_edges,
+             stroke_indices};
 
 /// A line joins two points and is straight. It is completely specified by its two
 /// endpoints.
@@ -14,6 +15,7 @@ pub struct Line {
     stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
     fill_index_buffer: IndexType,
     stroke_index_buffer: IndexType,
+    data: ShapeData,
 }
 
 impl Shape for Line {
@@ -51,6 +53,41 @@ impl Line {
         y2i: &[f64],
         z2i: &[f64],
     ) -> Result<Self, ProcessingErr> {
+        let (shape, n) = Line::vertices(screen, x1i, y1i, z1i, x2i, y2i, z2i)?;
+        // nothing is drawn if stroke is turned off
+        let (shape, n) = if screen.stroke_stuff { (shape, n) } else { (vec![], 0) };
+        let data = ShapeData::stroke_only(screen, shape, n);
+        let (fill_shape_buffer, stroke_shape_buffer) = data.buffers(screen)?;
+
+        Ok(Line {
+            fill_buffer: fill_shape_buffer,
+            stroke_buffer: stroke_shape_buffer,
+            fill_index_buffer: IndexType::NoBuffer {
+                ind: glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
+            },
+            stroke_index_buffer: stroke_indices(screen, &outline_edges(n, 2, 0, 2, false))?,
+            data: data,
+        })
+    }
+
+    // the vertices of all of the sub-shapes, and how many of them there are
+    fn vertices(
+        screen: &Screen,
+        x1i: &[f64],
+        y1i: &[f64],
+        z1i: &[f64],
+        x2i: &[f64],
+        y2i: &[f64],
+        z2i: &[f64],
+    ) -> Result<(Vec<ShapeVertex>, usize), ProcessingErr> {
+        let n = check_lengths(&[
+            x1i.len(),
+            y1i.len(),
+            z1i.len(),
+            x2i.len(),
+            y2i.len(),
+            z2i.len(),
+        ])?;
         let mut x1 = x1i.iter().map(|&v| v).collect::<Vec<f64>>();
         let mut y1 = y1i.iter().map(|&v| v).collect::<Vec<f64>>();
         let z1 = z1i.iter().map(|&v| v).collect::<Vec<f64>>();
@@ -71,114 +108,64 @@ impl Line {
             }
         }
 
-        if screen.stroke_stuff {
-            let eps = f32::EPSILON;
-            let mut shape = vec![];
-            for (i, _) in x1.iter().enumerate() {
-                let vertex = ShapeVertex {
-                    position: [
-                        x1[i] as f32,
-                        y1[i] as f32,
-                        if z1[i] == 0.0 {
-                            eps * i as f32
-                        } else {
-                            z1[i] as f32
-                        },
-                    ],
-                    color: [0.0, 0.0, 0.0, 0.0],
-                    texcoord: [0f32, 0.],
-                };
-                shape.push(vertex);
-                let vertex = ShapeVertex {
-                    position: [
-                        x2[i] as f32,
-                        y2[i] as f32,
-                        if z2[i] == 0.0 {
-                            eps * i as f32
-                        } else {
-                            z2[i] as f32
-                        },
-                    ],
-                    color: [0.0, 0.0, 0.0, 0.0],
-                    texcoord: [0f32, 0.],
-                };
-                shape.push(vertex);
-            }
-
-            // lines need a custom load_colors implementation
-            if screen.stroke_col.len() == 4 {
-                for x in (0..shape.len()).filter(|x| x % 2 == 0) {
-                    shape[x].color = [
-                        screen.stroke_col[0],
-                        screen.stroke_col[1],
-                        screen.stroke_col[2],
-                        screen.stroke_col[3],
-                    ];
-                    shape[x + 1].color = [
-                        screen.stroke_col[0],
-                        screen.stroke_col[1],
-                        screen.stroke_col[2],
-                        screen.stroke_col[3],
-                    ];
-                }
-            } else {
-                // for c in 0..screen.stroke_col.len() / 4 {
-                    // let idx = c * 4;
-                    // for x in (c * shapeStride..(c + 1) * shapeStride).filter(|x| x % 4 == 0) {
-                    // $buffer[x].color = [
-                    // $colorMat[idx],
-                    // $colorMat[idx + 1],
-                    // $colorMat[idx + 2],
-                    // $colorMat[idx + 3],
-                    // ];
-                    // }
-                // }
-            }
-
-            let fill_shape_buffer = match screen.display {
-                ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-                ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
+        let eps = f32::EPSILON;
+        let mut shape = vec![];
+        for (i, _) in x1.iter().enumerate() {
+            let vertex = ShapeVertex {
+                position: [
+                    x1[i] as f32,
+                    y1[i] as f32,
+                    if z1[i] == 0.0 {
+                        eps * i as f32
+                    } else {
+                        z1[i] as f32
+                    },
+                ],
+                color: [0.0, 0.0, 0.0, 0.0],
+                texcoord: [0f32, 0.],
+                normal: [0.0, 0.0, 0.0],
             };
-            let stroke_shape_buffer = match screen.display {
-                ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-                ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
+            shape.push(vertex);
+            let vertex = ShapeVertex {
+                position: [
+                    x2[i] as f32,
+                    y2[i] as f32,
+                    if z2[i] == 0.0 {
+                        eps * i as f32
+                    } else {
+                        z2[i] as f32
+                    },
+                ],
+                color: [0.0, 0.0, 0.0, 0.0],
+                texcoord: [0f32, 0.],
+                normal: [0.0, 0.0, 0.0],
             };
-
-            return Ok(Line {
-                fill_buffer: fill_shape_buffer,
-                stroke_buffer: stroke_shape_buffer,
-                fill_index_buffer: IndexType::NoBuffer {
-                    ind: glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
-                },
-                stroke_index_buffer: IndexType::NoBuffer {
-                    ind: glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
-                },
-            });
+            shape.push(vertex);
         }
 
-        return Ok(Line {
-            fill_buffer: match screen.display {
-                ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &vec![])
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-                ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &vec![])
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            },
-            stroke_buffer: match screen.display {
-                ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &vec![])
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-                ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &vec![])
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            },
-            fill_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
-            },
-            stroke_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
-            },
-        });
+        Ok((shape, n))
+    }
+
+	/// Move the endpoints of the lines of this Line without creating a new one. The
+	/// arguments are the same as for Line::new(), but they have to describe the
+	/// same number of lines as before. The color stays as it is.
+    pub fn update(
+        &mut self,
+        screen: &Screen,
+        x1i: &[f64],
+        y1i: &[f64],
+        z1i: &[f64],
+        x2i: &[f64],
+        y2i: &[f64],
+        z2i: &[f64],
+    ) -> Result<(), ProcessingErr> {
+        let (shape, n) = Line::vertices(screen, x1i, y1i, z1i, x2i, y2i, z2i)?;
+        self.data.set_vertices(shape, n, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+	/// Change the stroke color of this shape in place. As in screen.stroke(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_stroke(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_stroke(color_mat, &self.fill_buffer, &self.stroke_buffer)
     }
 }
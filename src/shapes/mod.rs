@@ -1,4 +1,13 @@
+use std::collections::HashMap;
+use std::f64;
+
 use glium;
+use glium::Surface;
+use glium::uniforms::Uniforms;
+
+use {Screen, ScreenType};
+use errors::ProcessingErr;
+use textures::TextureSampling;
 
 pub mod draw;
 pub mod rect;
@@ -10,6 +19,18 @@ pub mod quad;
 pub mod triangle;
 pub mod cube;
 pub mod mould;
+pub mod immediate;
+pub mod custom;
+pub mod curve;
+pub mod instanced;
+pub mod group;
+pub mod sphere;
+pub mod box_shape;
+pub mod cylinder;
+pub mod cone;
+pub mod torus;
+pub mod plane;
+pub mod obj;
 
 pub enum IndexType {
     Buffer { ind: glium::index::IndexBuffer<u32> },
@@ -21,9 +42,10 @@ pub struct ShapeVertex {
     position: [f32; 3],
     color: [f32; 4],
     texcoord: [f32; 2],
+    normal: [f32; 3],
 }
 
-implement_vertex!(ShapeVertex, position, color, texcoord);
+implement_vertex!(ShapeVertex, position, color, texcoord, normal);
 
 /// A shape is a generic concept, as expressed by the Shape trait here. From the point
 /// of view of `processing-rs`, a shape is anything that has fill and stroke buffer
@@ -38,24 +60,627 @@ pub trait Shape {
     fn fill_indices(&self) -> Box<&IndexType>;
     fn stroke_indices(&self) -> Box<&IndexType>;
     fn get_texture(&self) -> Option<Box<&glium::texture::Texture2d>>;
+
+    /// Whether the vertices of the shape carry normals. Only shapes that do are lit
+    /// when there are lights (see screen.lights()); the flat shapes don't.
+    fn has_normals(&self) -> bool {
+        false
+    }
+
+    /// The sampling that the texture of the shape is drawn with, if the shape was
+    /// given its own. Otherwise, the global one from screen.texture_wrap() and friends
+    /// is used.
+    fn get_texture_sampling(&self) -> Option<TextureSampling> {
+        None
+    }
 }
 
-fn load_colors(buffer: &mut [ShapeVertex], color_mat: &[f32]) {
-    if color_mat.len() == 4 {
-        for x in 0..buffer.len() {
-            buffer[x].color = [color_mat[0], color_mat[1], color_mat[2], color_mat[3]];
+/// The vertices of a shape as they were last uploaded to the GPU, along with the fill
+/// and stroke colors that they were given. Shapes hold on to this so that they can be
+/// changed in place (e.g., with Rect::update() or Rect::set_fill()), which only
+/// rewrites their existing buffers instead of allocating new ones.
+struct ShapeData {
+    vertices: Vec<ShapeVertex>,
+    n: usize,
+    fill_col: Option<Vec<f32>>,
+    stroke_col: Vec<f32>,
+}
+
+impl ShapeData {
+    // n sub-shapes with the same number of vertices each, in the current fill and
+    // stroke colors of the screen
+    fn new(screen: &Screen, vertices: Vec<ShapeVertex>, n: usize) -> Self {
+        ShapeData {
+            vertices: vertices,
+            n: n,
+            fill_col: Some(screen.fill_col.clone()),
+            stroke_col: screen.stroke_col.clone(),
+        }
+    }
+
+    // for shapes such as lines and points, which use the stroke color for everything
+    fn stroke_only(screen: &Screen, vertices: Vec<ShapeVertex>, n: usize) -> Self {
+        ShapeData {
+            vertices: vertices,
+            n: n,
+            fill_col: None,
+            stroke_col: screen.stroke_col.clone(),
+        }
+    }
+
+    // upload the vertices into new fill and stroke buffers
+    fn buffers(
+        &self,
+        screen: &Screen,
+    ) -> Result<(glium::VertexBuffer<ShapeVertex>, glium::VertexBuffer<ShapeVertex>), ProcessingErr> {
+        let fill_shape = self.colored(self.fill_color(), self.n)?;
+        let stroke_shape = self.colored(&self.stroke_col, self.n)?;
+
+        Ok((vertex_buffer(screen, &fill_shape)?, vertex_buffer(screen, &stroke_shape)?))
+    }
+
+    // replace the vertices, keeping the colors, and write them into the given buffers
+    fn set_vertices(
+        &mut self,
+        vertices: Vec<ShapeVertex>,
+        n: usize,
+        fill_buffer: &glium::VertexBuffer<ShapeVertex>,
+        stroke_buffer: &glium::VertexBuffer<ShapeVertex>,
+    ) -> Result<(), ProcessingErr> {
+        if n != self.n {
+            return Err(ProcessingErr::LengthMismatch(self.n, n));
+        }
+        self.vertices = vertices;
+        write_buffer(fill_buffer, &self.colored(self.fill_color(), n)?)?;
+        write_buffer(stroke_buffer, &self.colored(&self.stroke_col, n)?)
+    }
+
+    fn set_fill(
+        &mut self,
+        color_mat: &[f32],
+        fill_buffer: &glium::VertexBuffer<ShapeVertex>,
+    ) -> Result<(), ProcessingErr> {
+        write_buffer(fill_buffer, &self.colored(color_mat, self.n)?)?;
+        self.fill_col = Some(color_mat.to_vec());
+
+        Ok(())
+    }
+
+    fn set_stroke(
+        &mut self,
+        color_mat: &[f32],
+        fill_buffer: &glium::VertexBuffer<ShapeVertex>,
+        stroke_buffer: &glium::VertexBuffer<ShapeVertex>,
+    ) -> Result<(), ProcessingErr> {
+        let stroke_shape = self.colored(color_mat, self.n)?;
+        if self.fill_col.is_none() {
+            write_buffer(fill_buffer, &stroke_shape)?;
+        }
+        write_buffer(stroke_buffer, &stroke_shape)?;
+        self.stroke_col = color_mat.to_vec();
+
+        Ok(())
+    }
+
+    fn fill_color(&self) -> &[f32] {
+        match self.fill_col {
+            Some(ref c) => c,
+            None => &self.stroke_col,
+        }
+    }
+
+    fn colored(&self, color_mat: &[f32], n: usize) -> Result<Vec<ShapeVertex>, ProcessingErr> {
+        let mut shape = self.vertices.clone();
+        load_colors(&mut shape, color_mat, n)?;
+
+        Ok(shape)
+    }
+}
+
+// Create a vertex buffer for a shape. If the screen was told that shapes are going to
+// be changed after they are created (see screen.dynamic_shapes()), the buffer is set
+// up for frequent updates.
+fn vertex_buffer(screen: &Screen, shape: &[ShapeVertex]) -> Result<glium::VertexBuffer<ShapeVertex>, ProcessingErr> {
+    if screen.dynamic_shapes {
+        match screen.display {
+            ScreenType::Window(ref d) => glium::VertexBuffer::dynamic(d, shape)
+                .map_err(|e| ProcessingErr::VBNoCreate(e)),
+            ScreenType::Headless(ref d) => glium::VertexBuffer::dynamic(d, shape)
+                .map_err(|e| ProcessingErr::VBNoCreate(e)),
+        }
+    } else {
+        match screen.display {
+            ScreenType::Window(ref d) => glium::VertexBuffer::new(d, shape)
+                .map_err(|e| ProcessingErr::VBNoCreate(e)),
+            ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, shape)
+                .map_err(|e| ProcessingErr::VBNoCreate(e)),
+        }
+    }
+}
+
+// overwrite the contents of a buffer, which has to keep its size
+fn write_buffer(buffer: &glium::VertexBuffer<ShapeVertex>, shape: &[ShapeVertex]) -> Result<(), ProcessingErr> {
+    if buffer.len() != shape.len() {
+        return Err(ProcessingErr::LengthMismatch(buffer.len(), shape.len()));
+    }
+    buffer.write(shape);
+
+    Ok(())
+}
+
+// Give the vertices of each sub-shape their color. The buffer holds n sub-shapes with
+// the same number of vertices each. A single color is used for all of them; otherwise
+// there has to be exactly one color (i.e., four values) per sub-shape.
+fn load_colors(buffer: &mut [ShapeVertex], color_mat: &[f32], n: usize) -> Result<(), ProcessingErr> {
+    if n == 0 {
+        return Ok(());
+    }
+    let stride = buffer.len() / n;
+    for i in 0..n {
+        let color = nth_color(color_mat, i, n)?;
+        for x in i * stride..(i + 1) * stride {
+            buffer[x].color = color;
         }
+    }
+
+    Ok(())
+}
+
+// the color of the i-th of n sub-shapes, following the rules of load_colors()
+fn nth_color(color_mat: &[f32], i: usize, n: usize) -> Result<[f32; 4], ProcessingErr> {
+    if color_mat.len() == 4 {
+        Ok([color_mat[0], color_mat[1], color_mat[2], color_mat[3]])
+    } else if color_mat.len() == 4 * n {
+        let idx = i * 4;
+        Ok([
+            color_mat[idx],
+            color_mat[idx + 1],
+            color_mat[idx + 2],
+            color_mat[idx + 3],
+        ])
     } else {
-        // for c in 0..color_mat.len() / 4 {
-            // let idx = c * 4;
-            // for x in (c * shapeStride..(c + 1) * shapeStride).filter(|x| x % 4 == 0) {
-            // $buffer[x].color = [
-            // $color_mat[idx],
-            // $color_mat[idx + 1],
-            // $color_mat[idx + 2],
-            // $color_mat[idx + 3],
-            // ];
-            // }
-        // }
+        Err(ProcessingErr::ColorCountMismatch(color_mat.len() / 4, n))
+    }
+}
+
+// Make sure that all of the coordinate slices passed to a shape constructor describe
+// the same number of sub-shapes, and return that number.
+fn check_lengths(lengths: &[usize]) -> Result<usize, ProcessingErr> {
+    let n = lengths[0];
+    for &l in &lengths[1..] {
+        if l != n {
+            return Err(ProcessingErr::LengthMismatch(n, l));
+        }
+    }
+
+    Ok(n)
+}
+
+// Never split a curve segment more often than this, no matter how it is transformed.
+const MAX_SUBDIVISION_DEPTH: usize = 8;
+// Largest allowed distance, in pixels, between a curve and the lines approximating it.
+const SUBDIVISION_TOLERANCE: f64 = 0.25;
+
+// Turn the curve f(t), for t from 0 to 1, into a list of points. The curve is first cut
+// into `detail` equal pieces, and any piece that still looks bent on screen under the
+// current transformation matrix is split again, so that curves stay smooth when they are
+// scaled up. The point at t = 0 is not included in the output.
+fn subdivide_curve<F: Fn(f64) -> [f64; 3]>(screen: &Screen, f: &F, detail: usize) -> Vec<[f64; 3]> {
+    let detail = if detail == 0 { 1 } else { detail };
+    let mut points = vec![];
+    let mut t0 = 0.0;
+    let mut p0 = f(t0);
+    for i in 1..detail + 1 {
+        let t1 = i as f64 / detail as f64;
+        let p1 = f(t1);
+        refine_curve(screen, f, t0, p0, t1, p1, 0, &mut points);
+        t0 = t1;
+        p0 = p1;
+    }
+
+    points
+}
+
+fn refine_curve<F: Fn(f64) -> [f64; 3]>(
+    screen: &Screen,
+    f: &F,
+    t0: f64,
+    p0: [f64; 3],
+    t1: f64,
+    p1: [f64; 3],
+    depth: usize,
+    points: &mut Vec<[f64; 3]>,
+) {
+    let tm = (t0 + t1) / 2.0;
+    let pm = f(tm);
+    if depth < MAX_SUBDIVISION_DEPTH {
+        let bent = match (
+            pixel_position(screen, p0),
+            pixel_position(screen, p1),
+            pixel_position(screen, pm),
+        ) {
+            (Some(a), Some(b), Some(m)) => {
+                let dx = m.0 - (a.0 + b.0) / 2.0;
+                let dy = m.1 - (a.1 + b.1) / 2.0;
+                (dx * dx + dy * dy).sqrt() > SUBDIVISION_TOLERANCE
+            }
+            // a piece that is entirely behind the eye is never seen, while one that
+            // crosses it is split until the visible part of it is smooth
+            (None, None, None) => false,
+            _ => true,
+        };
+        if bent {
+            refine_curve(screen, f, t0, p0, tm, pm, depth + 1, points);
+            refine_curve(screen, f, tm, pm, t1, p1, depth + 1, points);
+            return;
+        }
+    }
+    points.push(p1);
+}
+
+// where a point ends up on the framebuffer, in pixels, unless it is behind the eye
+fn pixel_position(screen: &Screen, p: [f64; 3]) -> Option<(f64, f64)> {
+    let mut x = p[0];
+    let mut y = p[1];
+    if screen.preserve_aspect_ratio && screen.aspect_ratio != 1f32 {
+        if screen.aspect_ratio > 1f32 {
+            x = x / screen.aspect_ratio as f64;
+        } else {
+            y = y * screen.aspect_ratio as f64;
+        }
+    }
+    let m = screen.matrices.mvp();
+    let tx = m[(0, 0)] as f64 * x + m[(0, 1)] as f64 * y + m[(0, 2)] as f64 * p[2] + m[(0, 3)] as f64;
+    let ty = m[(1, 0)] as f64 * x + m[(1, 1)] as f64 * y + m[(1, 2)] as f64 * p[2] + m[(1, 3)] as f64;
+    let tw = m[(3, 0)] as f64 * x + m[(3, 1)] as f64 * y + m[(3, 2)] as f64 * p[2] + m[(3, 3)] as f64;
+    if tw <= 0.0 {
+        return None;
+    }
+    let (tx, ty) = (tx / tw, ty / tw);
+
+    Some((
+        tx * screen.fb_size[0] as f64 / 2.0,
+        ty * screen.fb_size[1] as f64 / 2.0,
+    ))
+}
+
+// The edges of n outlines, where the i-th outline is made of the `count` consecutive
+// vertices starting at `first + i * stride`. Closed outlines also get an edge from their
+// last vertex back to their first one.
+fn outline_edges(n: usize, stride: usize, first: usize, count: usize, closed: bool) -> Vec<[u32; 2]> {
+    let mut edges = vec![];
+    for i in 0..n {
+        let start = (first + i * stride) as u32;
+        let count = count as u32;
+        for j in 1..count {
+            edges.push([start + j - 1, start + j]);
+        }
+        if closed && count > 2 {
+            edges.push([start + count - 1, start]);
+        }
+    }
+
+    edges
+}
+
+// Draw the fill and the stroke of a shape with the program at prog in the shader bank
+// and the given uniforms, which is what drawing a Mould comes down to, whether on its
+// own or inside of a Group. Since the program replaces the stroke shader, outlines are
+// drawn as plain lines.
+fn draw_with_shader<S: Shape, U: Uniforms>(
+    screen: &mut Screen,
+    shape: &S,
+    prog: usize,
+    uniforms: &U,
+) -> Result<(), ProcessingErr> {
+    let prog = &screen.shader_bank[prog];
+    let framebuffer = &mut screen.fbo;
+    if screen.fill_stuff {
+        match *shape.fill_indices() {
+            &IndexType::Buffer { ind: ref ib } => {
+                framebuffer
+                    .draw(*shape.fill_buffer(), ib, prog, uniforms, &screen.draw_params)
+                    .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+            }
+            &IndexType::NoBuffer { ind: ref ib } => {
+                framebuffer
+                    .draw(*shape.fill_buffer(), ib, prog, uniforms, &screen.draw_params)
+                    .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+            }
+        }
+    };
+    if screen.stroke_stuff {
+        match *shape.stroke_indices() {
+            &IndexType::NoBuffer { ind: ref ib } => {
+                framebuffer
+                    .draw(*shape.stroke_buffer(), ib, prog, uniforms, &screen.draw_params)
+                    .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+            }
+            &IndexType::Buffer { ind: ref ib } => {
+                framebuffer
+                    .draw(*shape.stroke_buffer(), ib, prog, uniforms, &screen.draw_params)
+                    .map_err(|e| ProcessingErr::FBDrawFailed(e))?
+            }
+        }
+    };
+
+    Ok(())
+}
+
+// The triangles that fill n fans of `stride` vertices each, whose first vertex is the
+// center that all of the others are connected to. Drawing them all as one TriangleFan
+// would connect every fan to the center of the first one.
+fn fan_triangles(n: usize, stride: usize) -> Vec<u32> {
+    let mut elements = Vec::with_capacity(3 * n * stride);
+    for i in 0..n {
+        let center = (i * stride) as u32;
+        for j in 1..stride as u32 - 1 {
+            elements.extend_from_slice(&[center, center + j, center + j + 1]);
+        }
+    }
+
+    elements
+}
+
+// Build the index buffer that strokes are drawn with. Every edge (a, b) becomes one
+// lines-with-adjacency primitive (prev, a, b, next), where prev is the other neighbour
+// of a and next is the other neighbour of b. This is what lets the stroke shader join
+// connected edges together. Only ends where exactly two edges meet are joined, in
+// whichever direction those edges were given; all other ends point back at themselves,
+// which tells the shader to put a cap there instead.
+fn stroke_indices(screen: &Screen, edges: &[[u32; 2]]) -> Result<IndexType, ProcessingErr> {
+    let elements = stroke_adjacency(edges);
+
+    let prim = glium::index::PrimitiveType::LinesListAdjacency;
+    let index_buffer = match screen.display {
+        ScreenType::Window(ref d) => glium::IndexBuffer::new(d, prim, &elements)
+            .map_err(|e| ProcessingErr::IBNoCreate(e))?,
+        ScreenType::Headless(ref d) => glium::IndexBuffer::new(d, prim, &elements)
+            .map_err(|e| ProcessingErr::IBNoCreate(e))?,
+    };
+
+    Ok(IndexType::Buffer { ind: index_buffer })
+}
+
+fn stroke_adjacency(edges: &[[u32; 2]]) -> Vec<u32> {
+    let mut neighbours: HashMap<u32, Vec<u32>> = HashMap::new();
+    for e in edges {
+        if e[0] == e[1] {
+            continue;
+        }
+        for &(a, b) in &[(e[0], e[1]), (e[1], e[0])] {
+            let n = neighbours.entry(a).or_insert_with(Vec::new);
+            if !n.contains(&b) {
+                n.push(b);
+            }
+        }
+    }
+
+    // the neighbour of v on the far side from the one it shares an edge with
+    let other = |v: u32, along: u32| match neighbours.get(&v) {
+        Some(n) if n.len() == 2 => if n[0] == along { n[1] } else { n[0] },
+        _ => v,
+    };
+
+    let mut elements = Vec::with_capacity(4 * edges.len());
+    for e in edges {
+        elements.push(other(e[0], e[1]));
+        elements.push(e[0]);
+        elements.push(e[1]);
+        elements.push(other(e[1], e[0]));
+    }
+
+    elements
+}
+
+// The triangles of a solid in its own coordinates, with a normal and a texture
+// coordinate for every vertex, and the edges that its stroke is made of.
+struct Mesh {
+    positions: Vec<[f64; 3]>,
+    normals: Vec<[f64; 3]>,
+    texcoords: Vec<[f64; 2]>,
+    triangles: Vec<[u32; 3]>,
+    edges: Vec<[u32; 2]>,
+}
+
+impl Mesh {
+    fn new() -> Mesh {
+        Mesh {
+            positions: vec![],
+            normals: vec![],
+            texcoords: vec![],
+            triangles: vec![],
+            edges: vec![],
+        }
+    }
+
+    // A surface that is given by f(u, v), which returns a position and a normal, with u
+    // and v running from 0 to 1 in ures and vres steps. The texture coordinates are
+    // (u, 1 - v), so v = 0 is the top of the image, and the edges are the lines of the
+    // grid, except for those that have shrunk to a single point (e.g., at a pole).
+    fn grid<F: Fn(f64, f64) -> ([f64; 3], [f64; 3])>(ures: usize, vres: usize, f: F) -> Mesh {
+        let ures = if ures == 0 { 1 } else { ures };
+        let vres = if vres == 0 { 1 } else { vres };
+        let mut mesh = Mesh::new();
+        for j in 0..vres + 1 {
+            for i in 0..ures + 1 {
+                let u = i as f64 / ures as f64;
+                let v = j as f64 / vres as f64;
+                let (p, normal) = f(u, v);
+                mesh.positions.push(p);
+                mesh.normals.push(normal);
+                mesh.texcoords.push([u, 1.0 - v]);
+            }
+        }
+
+        let idx = |i: usize, j: usize| (j * (ures + 1) + i) as u32;
+        for j in 0..vres {
+            for i in 0..ures {
+                let (a, b, c, d) = (idx(i, j), idx(i + 1, j), idx(i + 1, j + 1), idx(i, j + 1));
+                mesh.triangles.push([a, d, c]);
+                mesh.triangles.push([a, c, b]);
+            }
+        }
+        let mut edges = vec![];
+        for j in 0..vres + 1 {
+            for i in 0..ures {
+                edges.push([idx(i, j), idx(i + 1, j)]);
+            }
+        }
+        for i in 0..ures + 1 {
+            for j in 0..vres {
+                edges.push([idx(i, j), idx(i, j + 1)]);
+            }
+        }
+        mesh.edges = edges
+            .into_iter()
+            .filter(|e| {
+                mesh.positions[e[0] as usize] != mesh.positions[e[1] as usize]
+            })
+            .collect();
+
+        mesh
+    }
+
+    // A flat disc of radius 1 around the y-axis at height y, facing up or down. The
+    // texture is mapped onto it as seen from that side. It has no edges, because its rim
+    // is already part of the side of the solid that it closes off.
+    fn disc(ures: usize, y: f64, up: bool) -> Mesh {
+        let sign = if up { 1.0 } else { -1.0 };
+        let mut disc = Mesh::grid(ures, 1, |u, r| {
+            let phi = sign * (2.0 * f64::consts::PI * u - f64::consts::PI);
+            ([r * phi.sin(), y, r * phi.cos()], [0.0, sign, 0.0])
+        });
+        for k in 0..disc.positions.len() {
+            let p = disc.positions[k];
+            disc.texcoords[k] = [0.5 + 0.5 * p[0], 0.5 - 0.5 * sign * p[2]];
+        }
+        disc.edges.clear();
+
+        disc
+    }
+
+    // add the vertices, triangles, and edges of another mesh to this one
+    fn append(&mut self, other: Mesh) {
+        let offset = self.positions.len() as u32;
+        self.positions.extend(other.positions);
+        self.normals.extend(other.normals);
+        self.texcoords.extend(other.texcoords);
+        self.triangles.extend(other.triangles.iter().map(|t| {
+            [t[0] + offset, t[1] + offset, t[2] + offset]
+        }));
+        self.edges.extend(other.edges.iter().map(|e| [e[0] + offset, e[1] + offset]));
+    }
+
+    // Add a copy of the mesh to a shape, scaled along each axis by `scale` and then
+    // moved to `center`. The normals are scaled by the inverse, so that they stay
+    // perpendicular to the surface. The copy is squeezed to keep the aspect ratio in
+    // the same way as all of the flat shapes.
+    fn place(&self, screen: &Screen, center: [f64; 3], scale: [f64; 3], shape: &mut Vec<ShapeVertex>) {
+        let mut squeeze = [1.0, 1.0, 1.0];
+        if screen.preserve_aspect_ratio && screen.aspect_ratio != 1f32 {
+            if screen.aspect_ratio > 1f32 {
+                squeeze[0] = 1.0 / screen.aspect_ratio as f64;
+            } else {
+                squeeze[1] = screen.aspect_ratio as f64;
+            }
+        }
+
+        for k in 0..self.positions.len() {
+            let p = self.positions[k];
+            let nrm = self.normals[k];
+            let mut position = [0f32; 3];
+            let mut normal = [0f64; 3];
+            for a in 0..3 {
+                position[a] = ((p[a] * scale[a] + center[a]) * squeeze[a]) as f32;
+                normal[a] = nrm[a] / (scale[a] * squeeze[a]);
+            }
+            let len = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+            let len = if len > 0.0 { len } else { 1.0 };
+            shape.push(ShapeVertex {
+                position: position,
+                color: [0.0, 0.0, 0.0, 0.0],
+                texcoord: [self.texcoords[k][0] as f32, self.texcoords[k][1] as f32],
+                normal: [
+                    (normal[0] / len) as f32,
+                    (normal[1] / len) as f32,
+                    (normal[2] / len) as f32,
+                ],
+            });
+        }
+    }
+
+    // The fill and stroke indices of n copies of the mesh that were placed one after
+    // the other.
+    fn indices(&self, screen: &Screen, n: usize) -> Result<(IndexType, IndexType), ProcessingErr> {
+        let stride = self.positions.len() as u32;
+        let mut elements = Vec::with_capacity(3 * n * self.triangles.len());
+        let mut edges = Vec::with_capacity(n * self.edges.len());
+        for i in 0..n as u32 {
+            for t in &self.triangles {
+                elements.extend_from_slice(&[t[0] + i * stride, t[1] + i * stride, t[2] + i * stride]);
+            }
+            for e in &self.edges {
+                edges.push([e[0] + i * stride, e[1] + i * stride]);
+            }
+        }
+
+        Ok((triangle_indices(screen, &elements)?, stroke_indices(screen, &edges)?))
+    }
+}
+
+// the index buffer that a list of triangles is filled with
+fn triangle_indices(screen: &Screen, elements: &[u32]) -> Result<IndexType, ProcessingErr> {
+    let prim = glium::index::PrimitiveType::TrianglesList;
+    let index_buffer = match screen.display {
+        ScreenType::Window(ref d) => glium::IndexBuffer::new(d, prim, elements)
+            .map_err(|e| ProcessingErr::IBNoCreate(e))?,
+        ScreenType::Headless(ref d) => glium::IndexBuffer::new(d, prim, elements)
+            .map_err(|e| ProcessingErr::IBNoCreate(e))?,
+    };
+
+    Ok(IndexType::Buffer { ind: index_buffer })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stroke_adjacency;
+
+    #[test]
+    fn closed_outline_joins_everywhere() {
+        let square = [[0, 1], [1, 2], [2, 3], [3, 0]];
+        assert_eq!(
+            stroke_adjacency(&square),
+            vec![3, 0, 1, 2, 0, 1, 2, 3, 1, 2, 3, 0, 2, 3, 0, 1]
+        );
+    }
+
+    #[test]
+    fn open_ends_get_caps() {
+        let line = [[0, 1], [1, 2]];
+        assert_eq!(stroke_adjacency(&line), vec![0, 0, 1, 2, 0, 1, 2, 2]);
+    }
+
+    #[test]
+    fn edge_direction_does_not_matter() {
+        // the same open polyline as above, but with its second edge reversed
+        let line = [[0, 1], [2, 1]];
+        assert_eq!(stroke_adjacency(&line), vec![0, 0, 1, 2, 2, 2, 1, 0]);
+    }
+
+    #[test]
+    fn duplicate_edges_count_once() {
+        let line = [[0, 1], [1, 0], [1, 2]];
+        assert_eq!(&stroke_adjacency(&line)[8..], &[0, 1, 2, 2]);
+    }
+
+    #[test]
+    fn branches_get_caps() {
+        // three edges meeting at 0, like the corner of a box
+        let corner = [[0, 1], [0, 2], [0, 3], [1, 4]];
+        assert_eq!(
+            stroke_adjacency(&corner),
+            vec![0, 0, 1, 4, 0, 0, 2, 2, 0, 0, 3, 3, 0, 1, 4, 4]
+        );
     }
 }
@@ -0,0 +1,710 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use glium;
+
+use Screen;
+use errors::ProcessingErr;
+use image::load_image;
+use lights::Material;
+use textures::ScreenTexture;
+
+use shapes::{Shape, ShapeVertex, IndexType, Mesh, nth_color, vertex_buffer, triangle_indices, stroke_indices};
+
+/// The material of one part of a model, as it was read from the model's MTL file. The
+/// diffuse color and alpha become the fill color of the part, the diffuse map (if
+/// any) is attached to it as its texture, and the rest is used for lighting when the
+/// model is drawn with screen.draw_model() (see screen.ambient() and friends).
+#[derive(Clone, Debug)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub ambient: Option<[f32; 3]>,
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub emissive: [f32; 3],
+    pub shininess: f32,
+    pub alpha: f32,
+    pub diffuse_map: Option<PathBuf>,
+}
+
+impl ObjMaterial {
+    fn new(name: &str) -> Self {
+        ObjMaterial {
+            name: name.to_owned(),
+            ambient: None,
+            diffuse: [1.0; 3],
+            specular: [0.0; 3],
+            emissive: [0.0; 3],
+            shininess: 1.0,
+            alpha: 1.0,
+            diffuse_map: None,
+        }
+    }
+
+	/// The material in the form that the lit shaders use.
+    pub fn lighting(&self) -> Material {
+        Material::new(self.ambient, self.specular, self.emissive, self.shininess)
+    }
+}
+
+// a corner of a face: the indices of its position, texture coordinate, and normal
+type Corner = (usize, Option<usize>, Option<usize>);
+
+// all of the faces that use the same material
+struct FaceGroup {
+    material: Option<String>,
+    faces: Vec<Vec<Corner>>,
+}
+
+// everything in an OBJ file, before it is turned into a mesh
+struct ObjData {
+    positions: Vec<[f64; 3]>,
+    texcoords: Vec<[f64; 2]>,
+    normals: Vec<[f64; 3]>,
+    groups: Vec<FaceGroup>,
+    materials: HashMap<String, ObjMaterial>,
+}
+
+// the faces of a single material, which can be drawn on their own
+struct ObjPartData {
+    material: Option<ObjMaterial>,
+    texture: Option<ScreenTexture>,
+    fill_index_buffer: IndexType,
+    stroke_index_buffer: IndexType,
+}
+
+/// A model that was loaded from a Wavefront OBJ file with load_obj(). It carries
+/// the positions, normals, and texture coordinates of the file, split into one part
+/// per material. All of the parts share the same vertex buffers, so drawing the whole
+/// model with screen.draw() is as fast as drawing any other shape, but it can only
+/// show a texture if there is just one part. Use screen.draw_model() to draw every
+/// part with its own texture and material instead.
+pub struct ObjModel {
+    fill_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    fill_index_buffer: IndexType,
+    stroke_index_buffer: IndexType,
+    parts: Vec<ObjPartData>,
+}
+
+impl Shape for ObjModel {
+    fn fill_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.fill_buffer)
+    }
+
+    fn stroke_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.stroke_buffer)
+    }
+
+    fn fill_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.fill_index_buffer)
+    }
+
+    fn stroke_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.stroke_index_buffer)
+    }
+
+    fn get_texture(&self) -> Option<Box<&glium::texture::Texture2d>> {
+        if self.parts.len() != 1 {
+            return None;
+        }
+        match self.parts[0].texture {
+            Some(ref t) => Some(Box::new(&**t)),
+            _ => None,
+        }
+    }
+
+    fn has_normals(&self) -> bool {
+        true
+    }
+}
+
+/// A single part of an ObjModel, i.e., all of its faces that use the same material.
+/// It borrows the vertex buffers of the model and can be drawn like any other shape.
+pub struct ObjPart<'m> {
+    model: &'m ObjModel,
+    part: &'m ObjPartData,
+}
+
+impl<'m> Shape for ObjPart<'m> {
+    fn fill_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.model.fill_buffer)
+    }
+
+    fn stroke_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.model.stroke_buffer)
+    }
+
+    fn fill_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.part.fill_index_buffer)
+    }
+
+    fn stroke_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.part.stroke_index_buffer)
+    }
+
+    fn get_texture(&self) -> Option<Box<&glium::texture::Texture2d>> {
+        match self.part.texture {
+            Some(ref t) => Some(Box::new(&**t)),
+            _ => None,
+        }
+    }
+
+    fn has_normals(&self) -> bool {
+        true
+    }
+}
+
+impl<'m> ObjPart<'m> {
+	/// The material of the part, or None if its faces did not use one.
+    pub fn material(&self) -> Option<&ObjMaterial> {
+        self.part.material.as_ref()
+    }
+}
+
+impl ObjModel {
+	/// How many parts (i.e., materials) the model has.
+    pub fn len(&self) -> usize {
+        self.parts.len()
+    }
+
+	/// The i-th part of the model, in the order in which their materials were first
+	/// used in the OBJ file.
+    pub fn part<'m>(&'m self, i: usize) -> Option<ObjPart<'m>> {
+        self.parts.get(i).map(|p| ObjPart { model: self, part: p })
+    }
+}
+
+/// Load a model from a Wavefront OBJ file, along with the materials and diffuse
+/// textures of any MTL files that it refers to, which are looked for relative to
+/// the OBJ file. Polygons with more than three corners are split into triangles, and
+/// vertices without normals get the average normal of the faces around them. The
+/// model is placed in the same coordinates as all other shapes, so you will usually
+/// want to scale it with screen.scale() or place it in a Group. Parts without a
+/// material are filled with the current fill color, and all edges of the faces are
+/// stroked with the current stroke color.
+pub fn load_obj(screen: &mut Screen, filename: &str) -> Result<ObjModel, ProcessingErr> {
+    let path = Path::new(filename);
+    let data = parse_obj(path)?;
+
+    let smooth = smooth_normals(&data);
+    let mut mesh = Mesh::new();
+    let mut ranges = vec![];
+    for group in &data.groups {
+        let (first_triangle, first_edge) = (mesh.triangles.len(), mesh.edges.len());
+        add_faces(&data, group, &smooth, &mut mesh);
+        ranges.push((first_triangle, first_edge, mesh.positions.len()));
+    }
+
+    let fill_col = nth_color(&screen.fill_col, 0, 1)?;
+    let stroke_col = nth_color(&screen.stroke_col, 0, 1)?;
+    let mut fill_shape = Vec::with_capacity(mesh.positions.len());
+    mesh.place(screen, [0.0; 3], [1.0; 3], &mut fill_shape);
+    let mut stroke_shape = fill_shape.clone();
+    for v in &mut stroke_shape {
+        v.color = stroke_col;
+    }
+
+    let mut parts = Vec::with_capacity(data.groups.len());
+    let mut first_vertex = 0;
+    for (k, group) in data.groups.iter().enumerate() {
+        let (first_triangle, first_edge, end_vertex) = ranges[k];
+        let end_triangle = ranges.get(k + 1).map(|r| r.0).unwrap_or(mesh.triangles.len());
+        let end_edge = ranges.get(k + 1).map(|r| r.1).unwrap_or(mesh.edges.len());
+
+        let material = group.material.as_ref().and_then(|m| data.materials.get(m)).cloned();
+        let color = match material {
+            Some(ref m) => [m.diffuse[0], m.diffuse[1], m.diffuse[2], m.alpha],
+            None => fill_col,
+        };
+        for v in &mut fill_shape[first_vertex..end_vertex] {
+            v.color = color;
+        }
+        first_vertex = end_vertex;
+
+        let texture = match material.as_ref().and_then(|m| m.diffuse_map.as_ref()) {
+            Some(map) => {
+                let img = load_image(&map.to_string_lossy())?;
+                Some(screen.texture(&img)?.0)
+            }
+            None => None,
+        };
+
+        let elements = flatten(&mesh.triangles[first_triangle..end_triangle]);
+        parts.push(ObjPartData {
+            material: material,
+            texture: texture,
+            fill_index_buffer: triangle_indices(screen, &elements)?,
+            stroke_index_buffer: stroke_indices(screen, &mesh.edges[first_edge..end_edge])?,
+        });
+    }
+
+    Ok(ObjModel {
+        fill_buffer: vertex_buffer(screen, &fill_shape)?,
+        stroke_buffer: vertex_buffer(screen, &stroke_shape)?,
+        fill_index_buffer: triangle_indices(screen, &flatten(&mesh.triangles))?,
+        stroke_index_buffer: stroke_indices(screen, &mesh.edges)?,
+        parts: parts,
+    })
+}
+
+impl<'a> Screen<'a> {
+	/// Draw every part of a model with its own texture and material, which replaces
+	/// the material set by screen.ambient() and friends while the part is drawn. Parts
+	/// without a material are drawn just as screen.draw() would draw them.
+    pub fn draw_model(&mut self, model: &ObjModel) -> Result<(), ProcessingErr> {
+        for p in &model.parts {
+            let part = ObjPart { model: model, part: p };
+            let saved = self.material;
+            if let Some(m) = part.material() {
+                self.material = m.lighting();
+            }
+            let result = self.draw(&part);
+            self.material = saved;
+            result?;
+        }
+
+        Ok(())
+    }
+}
+
+// Add the faces of one group to the mesh. Every distinct combination of position,
+// texture coordinate, and normal in the group becomes one vertex, and every edge
+// that faces share is only stroked once. Corners without a normal get the smooth one.
+fn add_faces(data: &ObjData, group: &FaceGroup, smooth: &[[f64; 3]], mesh: &mut Mesh) {
+    let mut vertices = HashMap::new();
+    let mut edges = HashSet::new();
+    for face in &group.faces {
+        let mut idx = Vec::with_capacity(face.len());
+        for &corner in face {
+            let k = *vertices.entry(corner).or_insert_with(|| {
+                let (p, t, n) = corner;
+                mesh.positions.push(data.positions[p]);
+                mesh.texcoords.push(t.map(|t| data.texcoords[t]).unwrap_or([0.0, 0.0]));
+                mesh.normals.push(n.map(|n| data.normals[n]).unwrap_or(smooth[p]));
+                (mesh.positions.len() - 1) as u32
+            });
+            idx.push(k);
+        }
+
+        for i in 1..idx.len() - 1 {
+            mesh.triangles.push([idx[0], idx[i], idx[i + 1]]);
+        }
+        for i in 0..idx.len() {
+            let (a, b) = (idx[i], idx[(i + 1) % idx.len()]);
+            if a != b && edges.insert((a.min(b), a.max(b))) {
+                mesh.edges.push([a, b]);
+            }
+        }
+    }
+}
+
+// the normal of every position, averaged over all of the faces that use it and
+// weighted by their area. Positions that are only used by faces without an area
+// (or by none at all) get one that points out of the screen, since a zero normal
+// would turn into NaN in the lit shaders.
+fn smooth_normals(data: &ObjData) -> Vec<[f64; 3]> {
+    let mut normals = vec![[0.0; 3]; data.positions.len()];
+    for group in &data.groups {
+        for face in &group.faces {
+            let p0 = data.positions[face[0].0];
+            for i in 1..face.len() - 1 {
+                let p1 = data.positions[face[i].0];
+                let p2 = data.positions[face[i + 1].0];
+                let u = [p1[0] - p0[0], p1[1] - p0[1], p1[2] - p0[2]];
+                let v = [p2[0] - p0[0], p2[1] - p0[1], p2[2] - p0[2]];
+                let n = [
+                    u[1] * v[2] - u[2] * v[1],
+                    u[2] * v[0] - u[0] * v[2],
+                    u[0] * v[1] - u[1] * v[0],
+                ];
+                for &(p, _, _) in &[face[0], face[i], face[i + 1]] {
+                    for a in 0..3 {
+                        normals[p][a] += n[a];
+                    }
+                }
+            }
+        }
+    }
+
+    for n in &mut normals {
+        let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        *n = if len > 1e-12 && len.is_finite() {
+            [n[0] / len, n[1] / len, n[2] / len]
+        } else {
+            [0.0, 0.0, 1.0]
+        };
+    }
+
+    normals
+}
+
+fn flatten(triangles: &[[u32; 3]]) -> Vec<u32> {
+    triangles.iter().flat_map(|t| t.iter().cloned()).collect()
+}
+
+fn parse_obj(path: &Path) -> Result<ObjData, ProcessingErr> {
+    let f = File::open(path).map_err(|e| ProcessingErr::ModelNotFound(e))?;
+    read_obj(BufReader::new(f), path.parent().unwrap_or(Path::new("")))
+}
+
+// read an OBJ file, whose MTL files are looked for in dir
+fn read_obj<R: BufRead>(reader: R, dir: &Path) -> Result<ObjData, ProcessingErr> {
+    let mut data = ObjData {
+        positions: vec![],
+        texcoords: vec![],
+        normals: vec![],
+        groups: vec![],
+        materials: HashMap::new(),
+    };
+    let mut current = 0;
+    let mut line_num = 0;
+    for line in reader.lines() {
+        line_num += 1;
+        let l = line.map_err(|e| ProcessingErr::ErrorReadingModel(line_num, e))?;
+        let bad = |msg: &str| {
+            ProcessingErr::ErrorReadingModel(line_num, io::Error::new(io::ErrorKind::InvalidData, msg))
+        };
+        let mut tokens = l.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let v = parse_floats(tokens, 3).ok_or_else(|| bad("expected three coordinates"))?;
+                data.positions.push([v[0], v[1], v[2]]);
+            }
+            Some("vt") => {
+                let v = parse_floats(tokens, 1).ok_or_else(|| bad("expected texture coordinates"))?;
+                data.texcoords.push([v[0], *v.get(1).unwrap_or(&0.0)]);
+            }
+            Some("vn") => {
+                let v = parse_floats(tokens, 3).ok_or_else(|| bad("expected three coordinates"))?;
+                data.normals.push([v[0], v[1], v[2]]);
+            }
+            Some("f") => {
+                let mut face = vec![];
+                for t in tokens {
+                    face.push(parse_corner(&data, t).ok_or_else(|| bad("invalid face corner"))?);
+                }
+                if face.len() < 3 {
+                    return Err(bad("a face needs at least three corners"));
+                }
+                if data.groups.is_empty() {
+                    data.groups.push(FaceGroup { material: None, faces: vec![] });
+                }
+                data.groups[current].faces.push(face);
+            }
+            Some("usemtl") => {
+                let name = tokens.collect::<Vec<&str>>().join(" ");
+                current = match data.groups.iter().position(|g| g.material.as_ref() == Some(&name)) {
+                    Some(k) => k,
+                    None => {
+                        data.groups.push(FaceGroup { material: Some(name), faces: vec![] });
+                        data.groups.len() - 1
+                    }
+                };
+            }
+            Some("mtllib") => {
+                for name in tokens {
+                    parse_mtl(&dir.join(name), &mut data.materials)?;
+                }
+            }
+            // groups, objects, smoothing groups, lines, and points are not needed
+            _ => {}
+        }
+    }
+    data.groups.retain(|g| !g.faces.is_empty());
+
+    Ok(data)
+}
+
+fn parse_mtl(path: &Path, materials: &mut HashMap<String, ObjMaterial>) -> Result<(), ProcessingErr> {
+    let f = File::open(path).map_err(|e| ProcessingErr::MaterialNotFound(e))?;
+    read_mtl(BufReader::new(f), path.parent().unwrap_or(Path::new("")), materials)
+}
+
+// read an MTL file, whose textures are looked for in dir
+fn read_mtl<R: BufRead>(
+    reader: R,
+    dir: &Path,
+    materials: &mut HashMap<String, ObjMaterial>,
+) -> Result<(), ProcessingErr> {
+    let mut current: Option<ObjMaterial> = None;
+    let mut line_num = 0;
+    for line in reader.lines() {
+        line_num += 1;
+        let l = line.map_err(|e| ProcessingErr::ErrorReadingMaterial(line_num, e))?;
+        let bad = |msg: &str| {
+            ProcessingErr::ErrorReadingMaterial(line_num, io::Error::new(io::ErrorKind::InvalidData, msg))
+        };
+        let mut tokens = l.split_whitespace();
+        let key = match tokens.next() {
+            Some(k) => k,
+            None => continue,
+        };
+        if key == "newmtl" {
+            if let Some(m) = current.take() {
+                materials.insert(m.name.clone(), m);
+            }
+            current = Some(ObjMaterial::new(&tokens.collect::<Vec<&str>>().join(" ")));
+            continue;
+        }
+        let m = match current {
+            Some(ref mut m) => m,
+            None => continue,
+        };
+        match key {
+            "Ka" => m.ambient = Some(parse_color(tokens).ok_or_else(|| bad("expected a color"))?),
+            "Kd" => m.diffuse = parse_color(tokens).ok_or_else(|| bad("expected a color"))?,
+            "Ks" => m.specular = parse_color(tokens).ok_or_else(|| bad("expected a color"))?,
+            "Ke" => m.emissive = parse_color(tokens).ok_or_else(|| bad("expected a color"))?,
+            "Ns" => m.shininess = parse_floats(tokens, 1).ok_or_else(|| bad("expected a number"))?[0] as f32,
+            "d" => m.alpha = parse_floats(tokens, 1).ok_or_else(|| bad("expected a number"))?[0] as f32,
+            "Tr" => m.alpha = 1.0 - parse_floats(tokens, 1).ok_or_else(|| bad("expected a number"))?[0] as f32,
+            // any options come before the file name, which is the last token
+            "map_Kd" => m.diffuse_map = Some(dir.join(tokens.last().ok_or_else(|| bad("expected a file name"))?)),
+            _ => {}
+        }
+    }
+    if let Some(m) = current.take() {
+        materials.insert(m.name.clone(), m);
+    }
+
+    Ok(())
+}
+
+// at least `min` numbers, or None if there are fewer or one of them is not a number
+fn parse_floats<'s, I: Iterator<Item = &'s str>>(tokens: I, min: usize) -> Option<Vec<f64>> {
+    let v = tokens.map(|t| t.parse::<f64>().ok()).collect::<Option<Vec<f64>>>()?;
+    if v.len() >= min { Some(v) } else { None }
+}
+
+fn parse_color<'s, I: Iterator<Item = &'s str>>(tokens: I) -> Option<[f32; 3]> {
+    let v = parse_floats(tokens, 1)?;
+    // a single value is used for all three channels
+    if v.len() < 3 {
+        Some([v[0] as f32; 3])
+    } else {
+        Some([v[0] as f32, v[1] as f32, v[2] as f32])
+    }
+}
+
+// A corner of a face is written as v, v/vt, v//vn, or v/vt/vn. The indices start at 1,
+// and negative ones count back from the most recent entry.
+fn parse_corner(data: &ObjData, token: &str) -> Option<Corner> {
+    let mut parts = token.split('/');
+    let p = resolve_index(parts.next()?, data.positions.len())?;
+    let t = match parts.next() {
+        Some(s) if !s.is_empty() => Some(resolve_index(s, data.texcoords.len())?),
+        _ => None,
+    };
+    let n = match parts.next() {
+        Some(s) if !s.is_empty() => Some(resolve_index(s, data.normals.len())?),
+        _ => None,
+    };
+
+    Some((p, t, n))
+}
+
+fn resolve_index(s: &str, len: usize) -> Option<usize> {
+    let i = s.parse::<isize>().ok()?;
+    let idx = if i > 0 { i - 1 } else { len as isize + i };
+    if i != 0 && idx >= 0 && (idx as usize) < len {
+        Some(idx as usize)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Cursor;
+    use std::path::{Path, PathBuf};
+
+    use shapes::Mesh;
+    use super::{ObjData, read_obj, read_mtl, parse_corner, resolve_index, add_faces, smooth_normals};
+
+    fn obj(text: &str) -> ObjData {
+        read_obj(Cursor::new(text), Path::new("models")).unwrap()
+    }
+
+    #[test]
+    fn indices() {
+        assert_eq!(resolve_index("1", 3), Some(0));
+        assert_eq!(resolve_index("3", 3), Some(2));
+        assert_eq!(resolve_index("-1", 3), Some(2));
+        assert_eq!(resolve_index("-3", 3), Some(0));
+        assert_eq!(resolve_index("0", 3), None);
+        assert_eq!(resolve_index("4", 3), None);
+        assert_eq!(resolve_index("-4", 3), None);
+        assert_eq!(resolve_index("one", 3), None);
+    }
+
+    #[test]
+    fn corners() {
+        let data = obj("v 0 0 0\nv 1 0 0\nvt 0 0\nvt 1 0\nvn 0 0 1\n");
+        assert_eq!(parse_corner(&data, "2"), Some((1, None, None)));
+        assert_eq!(parse_corner(&data, "2/1"), Some((1, Some(0), None)));
+        assert_eq!(parse_corner(&data, "1//1"), Some((0, None, Some(0))));
+        assert_eq!(parse_corner(&data, "-1/-1/-1"), Some((1, Some(1), Some(0))));
+        assert_eq!(parse_corner(&data, "1//2"), None);
+        assert_eq!(parse_corner(&data, "3"), None);
+    }
+
+    #[test]
+    fn negative_and_normal_only_indices() {
+        let data = obj("v 0 0 0\nv 1 0 0\nv 1 1 0\nvn 0 0 1\nf -3//-1 -2//-1 -1//-1\n");
+        assert_eq!(data.groups.len(), 1);
+        assert_eq!(
+            data.groups[0].faces[0],
+            vec![(0, None, Some(0)), (1, None, Some(0)), (2, None, Some(0))]
+        );
+    }
+
+    #[test]
+    fn bad_faces() {
+        assert!(read_obj(Cursor::new("v 0 0 0\nf 1 2 3\n"), Path::new("")).is_err());
+        assert!(read_obj(Cursor::new("v 0 0 0\nv 1 0 0\nf 1 2\n"), Path::new("")).is_err());
+    }
+
+    #[test]
+    fn quads_are_split_into_triangles() {
+        let data = obj("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n");
+        let smooth = smooth_normals(&data);
+        let mut mesh = Mesh::new();
+        add_faces(&data, &data.groups[0], &smooth, &mut mesh);
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+        // the diagonal is not an edge of the face, so it is not stroked
+        assert_eq!(mesh.edges, vec![[0, 1], [1, 2], [2, 3], [3, 0]]);
+        for n in &mesh.normals {
+            assert_eq!(*n, [0.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn faces_are_grouped_by_material() {
+        let data = obj(
+            "v 0 0 0\nv 1 0 0\nv 1 1 0\n\
+             f 1 2 3\n\
+             usemtl red\nf 1 2 3\n\
+             usemtl blue\nf 1 2 3\n\
+             usemtl red\nf 3 2 1\n\
+             usemtl unused\n",
+        );
+        let materials = data.groups.iter().map(|g| g.material.clone()).collect::<Vec<_>>();
+        assert_eq!(materials, vec![None, Some("red".to_owned()), Some("blue".to_owned())]);
+        let faces = data.groups.iter().map(|g| g.faces.len()).collect::<Vec<_>>();
+        assert_eq!(faces, vec![1, 2, 1]);
+    }
+
+    #[test]
+    fn materials() {
+        let mut materials = HashMap::new();
+        read_mtl(
+            Cursor::new(
+                "# a comment\n\
+                 newmtl shiny red\n\
+                 Ka 0.1 0.2 0.3\n\
+                 Kd 1 0 0\n\
+                 Ks 0.5\n\
+                 Ns 32\n\
+                 d 0.5\n\
+                 map_Kd -s 2 2 1 textures/red.png\n\
+                 newmtl glass\n\
+                 Tr 0.75\n",
+            ),
+            Path::new("models"),
+            &mut materials,
+        ).unwrap();
+
+        let red = &materials["shiny red"];
+        assert_eq!(red.ambient, Some([0.1, 0.2, 0.3]));
+        assert_eq!(red.diffuse, [1.0, 0.0, 0.0]);
+        assert_eq!(red.specular, [0.5; 3]);
+        assert_eq!(red.shininess, 32.0);
+        assert_eq!(red.alpha, 0.5);
+        assert_eq!(red.diffuse_map, Some(PathBuf::from("models/textures/red.png")));
+
+        let glass = &materials["glass"];
+        assert_eq!(glass.ambient, None);
+        assert_eq!(glass.diffuse, [1.0; 3]);
+        assert_eq!(glass.alpha, 0.25);
+        assert_eq!(glass.diffuse_map, None);
+
+        assert!(read_mtl(Cursor::new("newmtl x\nKd red\n"), Path::new(""), &mut materials).is_err());
+    }
+
+    #[test]
+    fn smooth_normals_are_unit_length() {
+        // a tent of two faces that meet at a ridge, plus a face without an area
+        let data = obj(
+            "v 0 0 0\nv 1 0 0\nv 0 1 1\nv 1 1 1\nv 0 2 0\nv 1 2 0\n\
+             f 1 2 4 3\nf 3 4 6 5\nf 1 1 2\n",
+        );
+        let normals = smooth_normals(&data);
+        for n in &normals {
+            let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+            assert!((len - 1.0).abs() < 1e-9, "{:?}", n);
+        }
+        // the corners at the foot of the tent only belong to one side, while the ridge
+        // averages both sides, whose slopes partly cancel out
+        let side = 0.5f64.sqrt();
+        assert!((normals[0][1] + side).abs() < 1e-9 && (normals[0][2] - side).abs() < 1e-9);
+        assert!((normals[4][1] - side).abs() < 1e-9 && (normals[4][2] - side).abs() < 1e-9);
+        assert!(normals[2][1].abs() < normals[2][2]);
+
+        let flat = obj("v 0 0 0\nv 1 0 0\nv 2 0 0\nf 1 2 3\n");
+        assert_eq!(smooth_normals(&flat), vec![[0.0, 0.0, 1.0]; 3]);
+    }
+
+    #[test]
+    #[ignore]
+    #[cfg(not(feature = "glfw"))]
+    fn load_two_materials() {
+        // this needs an OpenGL context, so run it with `cargo test -- --ignored` on a
+        // machine that has one
+        use std::env;
+        use std::fs;
+        use Screen;
+        use shapes::IndexType;
+        use super::load_obj;
+
+        let dir = env::temp_dir().join(format!("processing-rs-test-obj-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("parts.mtl"), "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\nd 0.5\n").unwrap();
+        fs::write(dir.join("parts.obj"),
+                  "mtllib parts.mtl\n\
+                   v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 2 0 0\n\
+                   usemtl red\nf 1 2 3 4\n\
+                   usemtl blue\nf 2 5 3\n").unwrap();
+
+        let mut screen = Screen::new_headless(8, 8, false).unwrap();
+        let model = load_obj(&mut screen, dir.join("parts.obj").to_str().unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(model.len(), 2);
+        assert!(model.part(2).is_none());
+        let red = model.part(0).unwrap();
+        let blue = model.part(1).unwrap();
+        assert_eq!(red.material().unwrap().name, "red");
+        assert_eq!(red.material().unwrap().diffuse, [1.0, 0.0, 0.0]);
+        assert_eq!(blue.material().unwrap().name, "blue");
+        assert_eq!(blue.material().unwrap().diffuse, [0.0, 0.0, 1.0]);
+        assert_eq!(blue.material().unwrap().alpha, 0.5);
+
+        let count = |ind: &IndexType| match *ind {
+            IndexType::Buffer { ref ind } => ind.len(),
+            IndexType::NoBuffer { .. } => panic!("expected an index buffer"),
+        };
+        // the quad is split into two triangles, and every edge is stroked as a line
+        // with adjacency
+        assert_eq!(count(&model.parts[0].fill_index_buffer), 6);
+        assert_eq!(count(&model.parts[1].fill_index_buffer), 3);
+        assert_eq!(count(&model.parts[0].stroke_index_buffer), 4 * 4);
+        assert_eq!(count(&model.parts[1].stroke_index_buffer), 3 * 4);
+        assert_eq!(count(&model.fill_index_buffer), 9);
+    }
+}
@@ -2,10 +2,10 @@ use std::f32;
 
 use glium;
 
-use {Screen, ScreenType};
+use Screen;
 use errors::ProcessingErr;
 
-use shapes::{Shape, ShapeVertex, IndexType, load_colors};
+use shapes::{Shape, ShapeVertex, ShapeData, IndexType, check_lengths};
 
 /// For the purposes of `processing-rs`, a point is basically just a pixel, specifed
 /// by a single position.
@@ -14,6 +14,7 @@ pub struct Point {
     stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
     fill_index_buffer: IndexType,
     stroke_index_buffer: IndexType,
+    data: ShapeData,
 }
 
 impl Shape for Point {
@@ -43,6 +44,28 @@ impl Point {
 	/// (xi, yi, zi).
     #[inline]
     pub fn new(screen: &mut Screen, xi: &[f64], yi: &[f64], zi: &[f64]) -> Result<Self, ProcessingErr> {
+        let (shape, n) = Point::vertices(screen, xi, yi, zi)?;
+        // nothing is drawn if stroke is turned off
+        let (shape, n) = if screen.stroke_stuff { (shape, n) } else { (vec![], 0) };
+        let data = ShapeData::stroke_only(screen, shape, n);
+        let (fill_shape_buffer, stroke_shape_buffer) = data.buffers(screen)?;
+
+        Ok(Point {
+            fill_buffer: fill_shape_buffer,
+            stroke_buffer: stroke_shape_buffer,
+            fill_index_buffer: IndexType::NoBuffer {
+                ind: glium::index::NoIndices(glium::index::PrimitiveType::Points),
+            },
+            stroke_index_buffer: IndexType::NoBuffer {
+                ind: glium::index::NoIndices(glium::index::PrimitiveType::Points),
+            },
+            data: data,
+        })
+    }
+
+    // the vertices of all of the sub-shapes, and how many of them there are
+    fn vertices(screen: &Screen, xi: &[f64], yi: &[f64], zi: &[f64]) -> Result<(Vec<ShapeVertex>, usize), ProcessingErr> {
+        let n = check_lengths(&[xi.len(), yi.len(), zi.len()])?;
         let mut x: Vec<f64> = xi.iter().map(|&v| v).collect::<Vec<f64>>();
         let mut y: Vec<f64> = yi.iter().map(|&v| v).collect::<Vec<f64>>();
         let z: Vec<f64> = zi.iter().map(|&v| v).collect::<Vec<f64>>();
@@ -58,78 +81,40 @@ impl Point {
             }
         }
 
-        if screen.stroke_stuff {
-            let eps = f32::EPSILON;
-            let mut shape = vec![];
-            for (i, _) in x.iter().enumerate() {
-                let vertex = ShapeVertex {
-                    position: [
-                        x[i] as f32,
-                        y[i] as f32,
-                        if z[i] == 0.0 {
-                            eps * i as f32
-                        } else {
-                            z[i] as f32
-                        },
-                    ],
-                    color: [0.0, 0.0, 0.0, 0.0],
-                    texcoord: [0f32, 0.],
-                };
-                shape.push(vertex);
-            }
-
-            load_colors(&mut shape, &screen.stroke_col);
-            let fill_shape_buffer = match screen.display {
-                ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-                ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            };
-            let stroke_shape_buffer = match screen.display {
-                ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-                ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
+        let eps = f32::EPSILON;
+        let mut shape = vec![];
+        for (i, _) in x.iter().enumerate() {
+            let vertex = ShapeVertex {
+                position: [
+                    x[i] as f32,
+                    y[i] as f32,
+                    if z[i] == 0.0 {
+                        eps * i as f32
+                    } else {
+                        z[i] as f32
+                    },
+                ],
+                color: [0.0, 0.0, 0.0, 0.0],
+                texcoord: [0f32, 0.],
+                normal: [0.0, 0.0, 0.0],
             };
-
-            // screen.draw_params = glium::draw_parameters::DrawParameters {
-            // smooth: None,
-            // ..screen.draw_params.clone()
-            // };
-
-            // screen.drew_points = true;
-
-            return Ok(Point {
-                fill_buffer: fill_shape_buffer,
-                stroke_buffer: stroke_shape_buffer,
-                fill_index_buffer: IndexType::NoBuffer {
-                    ind: glium::index::NoIndices(glium::index::PrimitiveType::Points),
-                },
-                stroke_index_buffer: IndexType::NoBuffer {
-                    ind: glium::index::NoIndices(glium::index::PrimitiveType::Points),
-                },
-            });
+            shape.push(vertex);
         }
 
-        return Ok(Point {
-            fill_buffer: match screen.display {
-                ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &vec![])
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-                ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &vec![])
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            },
-            stroke_buffer: match screen.display {
-                ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &vec![])
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-                ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &vec![])
-                	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            },
-            fill_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::Points),
-            },
-            stroke_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::Points),
-            },
-        });
+        Ok((shape, n))
+    }
+
+	/// Move the points of this Point without creating a new one. The arguments are
+	/// the same as for Point::new(), but they have to describe the same number of
+	/// points as before. The color stays as it is.
+    pub fn update(&mut self, screen: &Screen, xi: &[f64], yi: &[f64], zi: &[f64]) -> Result<(), ProcessingErr> {
+        let (shape, n) = Point::vertices(screen, xi, yi, zi)?;
+        self.data.set_vertices(shape, n, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+	/// Change the stroke color of this shape in place. As in screen.stroke(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_stroke(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_stroke(color_mat, &self.fill_buffer, &self.stroke_buffer)
     }
 }
@@ -4,8 +4,10 @@ use glium;
 
 use {Screen, ScreenType};
 use errors::ProcessingErr;
+use textures::TextureSampling;
 
-use shapes::{Shape, ShapeVertex, IndexType, load_colors};
+use shapes::{Shape, ShapeVertex, ShapeData, IndexType, check_lengths, outline_edges,
+             stroke_indices};
 
 /// A quadrilateral is a shape with four sides that are straight lines, so it is
 /// completely specified by the four points (vertices) at the corners where the
@@ -16,6 +18,8 @@ pub struct Quad<'a> {
     fill_index_buffer: IndexType,
     stroke_index_buffer: IndexType,
     texture: Option<&'a glium::texture::Texture2d>,
+    texture_sampling: Option<TextureSampling>,
+    data: ShapeData,
 }
 
 impl<'a> Shape for Quad<'a> {
@@ -41,6 +45,10 @@ impl<'a> Shape for Quad<'a> {
             _ => None,
         }
     }
+
+    fn get_texture_sampling(&self) -> Option<TextureSampling> {
+        self.texture_sampling
+    }
 }
 
 impl<'a> Quad<'a> {
@@ -62,6 +70,82 @@ impl<'a> Quad<'a> {
         y4i: &[f64],
         z4i: &[f64],
     ) -> Result<Self, ProcessingErr> {
+        let (shape, n) = Quad::vertices(screen, x1i, y1i, z1i, x2i, y2i, z2i, x3i, y3i, z3i, x4i, y4i, z4i)?;
+        let mut elements = vec![0u32; 6 * n];
+
+        elements[0] = 0;
+        elements[1] = 1;
+        elements[2] = 2;
+        elements[3] = 2;
+        elements[4] = 3;
+        elements[5] = 0;
+
+        let mut index = 6;
+        for _ in 1..n {
+            elements[index] = elements[index - 6] + 4;
+            elements[index + 1] = elements[(index - 6) + 1] + 4;
+            elements[index + 2] = elements[(index - 6) + 2] + 4;
+            elements[index + 3] = elements[(index - 6) + 3] + 4;
+            elements[index + 4] = elements[(index - 6) + 4] + 4;
+            elements[index + 5] = elements[(index - 6) + 5] + 4;
+            index += 6;
+        }
+
+        let index_buffer = match screen.display {
+            ScreenType::Window(ref d) => {
+                glium::IndexBuffer::new(d, glium::index::PrimitiveType::TrianglesList, &elements)
+                    .map_err(|e| ProcessingErr::IBNoCreate(e))?
+            }
+            ScreenType::Headless(ref d) => {
+                glium::IndexBuffer::new(d, glium::index::PrimitiveType::TrianglesList, &elements)
+                    .map_err(|e| ProcessingErr::IBNoCreate(e))?
+            }
+        };
+
+        let data = ShapeData::new(screen, shape, n);
+        let (fill_shape_buffer, stroke_shape_buffer) = data.buffers(screen)?;
+
+        Ok(Quad {
+            fill_buffer: fill_shape_buffer,
+            stroke_buffer: stroke_shape_buffer,
+            fill_index_buffer: IndexType::Buffer { ind: index_buffer },
+            stroke_index_buffer: stroke_indices(screen, &outline_edges(n, 4, 0, 4, true))?,
+            texture: None,
+            texture_sampling: None,
+            data: data,
+        })
+    }
+
+    // the vertices of all of the sub-shapes, and how many of them there are
+    fn vertices(
+        screen: &Screen,
+        x1i: &[f64],
+        y1i: &[f64],
+        z1i: &[f64],
+        x2i: &[f64],
+        y2i: &[f64],
+        z2i: &[f64],
+        x3i: &[f64],
+        y3i: &[f64],
+        z3i: &[f64],
+        x4i: &[f64],
+        y4i: &[f64],
+        z4i: &[f64],
+    ) -> Result<(Vec<ShapeVertex>, usize), ProcessingErr> {
+        let n = check_lengths(&[
+            x1i.len(),
+            y1i.len(),
+            z1i.len(),
+            x2i.len(),
+            y2i.len(),
+            z2i.len(),
+            x3i.len(),
+            y3i.len(),
+            z3i.len(),
+            x4i.len(),
+            y4i.len(),
+            z4i.len(),
+        ])?;
         let mut x1 = x1i.iter().map(|&v| v).collect::<Vec<f64>>();
         let mut y1 = y1i.iter().map(|&v| v).collect::<Vec<f64>>();
         let z1 = z1i.iter().map(|&v| v).collect::<Vec<f64>>();
@@ -107,6 +191,7 @@ impl<'a> Quad<'a> {
                 ],
                 color: [0.0, 0.0, 0.0, 0.0],
                 texcoord: [0f32, 0.],
+                normal: [0.0, 0.0, 0.0],
             };
             shape.push(vertex);
             let vertex = ShapeVertex {
@@ -121,6 +206,7 @@ impl<'a> Quad<'a> {
                 ],
                 color: [0.0, 0.0, 0.0, 0.0],
                 texcoord: [1f32, 0.],
+                normal: [0.0, 0.0, 0.0],
             };
             shape.push(vertex);
             let vertex = ShapeVertex {
@@ -135,6 +221,7 @@ impl<'a> Quad<'a> {
                 ],
                 color: [0.0, 0.0, 0.0, 0.0],
                 texcoord: [1f32, 1.],
+                normal: [0.0, 0.0, 0.0],
             };
             shape.push(vertex);
             let vertex = ShapeVertex {
@@ -149,71 +236,58 @@ impl<'a> Quad<'a> {
                 ],
                 color: [0.0, 0.0, 0.0, 0.0],
                 texcoord: [0f32, 1.],
+                normal: [0.0, 0.0, 0.0],
             };
             shape.push(vertex);
         }
 
-        let mut elements = vec![0u32; 6 * x1.len()];
-
-        elements[0] = 0;
-        elements[1] = 1;
-        elements[2] = 2;
-        elements[3] = 2;
-        elements[4] = 3;
-        elements[5] = 0;
-
-        let mut index = 6;
-        for _ in 1..x1.len() {
-            elements[index] = elements[index - 6] + 4;
-            elements[index + 1] = elements[(index - 6) + 1] + 4;
-            elements[index + 2] = elements[(index - 6) + 2] + 4;
-            elements[index + 3] = elements[(index - 6) + 3] + 4;
-            elements[index + 4] = elements[(index - 6) + 4] + 4;
-            elements[index + 5] = elements[(index - 6) + 5] + 4;
-            index += 6;
-        }
-
-        let index_buffer = match screen.display {
-            ScreenType::Window(ref d) => {
-                glium::IndexBuffer::new(d, glium::index::PrimitiveType::TrianglesList, &elements)
-                    .map_err(|e| ProcessingErr::IBNoCreate(e))?
-            }
-            ScreenType::Headless(ref d) => {
-                glium::IndexBuffer::new(d, glium::index::PrimitiveType::TrianglesList, &elements)
-                    .map_err(|e| ProcessingErr::IBNoCreate(e))?
-            }
-        };
+        Ok((shape, n))
+    }
 
-        load_colors(&mut shape, &screen.fill_col);
-        let fill_shape_buffer = match screen.display {
-            ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
-            	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
-            	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-        };
+	/// Move the corners of the quadrilaterals of this Quad without creating a new
+	/// one. The arguments are the same as for Quad::new(), but they have to describe
+	/// the same number of quadrilaterals as before. The colors stay as they are.
+    pub fn update(
+        &mut self,
+        screen: &Screen,
+        x1i: &[f64],
+        y1i: &[f64],
+        z1i: &[f64],
+        x2i: &[f64],
+        y2i: &[f64],
+        z2i: &[f64],
+        x3i: &[f64],
+        y3i: &[f64],
+        z3i: &[f64],
+        x4i: &[f64],
+        y4i: &[f64],
+        z4i: &[f64],
+    ) -> Result<(), ProcessingErr> {
+        let (shape, n) = Quad::vertices(screen, x1i, y1i, z1i, x2i, y2i, z2i, x3i, y3i, z3i, x4i, y4i, z4i)?;
+        self.data.set_vertices(shape, n, &self.fill_buffer, &self.stroke_buffer)
+    }
 
-        load_colors(&mut shape, &screen.stroke_col);
-        let stroke_shape_buffer = match screen.display {
-            ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
-            	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
-            	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-        };
+	/// Change the fill color of this shape in place. As in screen.fill(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_fill(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_fill(color_mat, &self.fill_buffer)
+    }
 
-        // screen.draw(fill_shape_buffer, stroke_shape_buffer, Some(index_buffer));
-        Ok(Quad {
-            fill_buffer: fill_shape_buffer,
-            stroke_buffer: stroke_shape_buffer,
-            fill_index_buffer: IndexType::Buffer { ind: index_buffer },
-            stroke_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::LineLoop),
-            },
-            texture: None,
-        })
+	/// Change the stroke color of this shape in place. As in screen.stroke(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_stroke(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_stroke(color_mat, &self.fill_buffer, &self.stroke_buffer)
     }
 
     pub fn attach_texture(mut self, tex: &'a glium::texture::Texture2d) -> Self {
         self.texture = Some(tex);
         self
     }
+
+	/// Give this shape its own texture sampling, which takes the place of the global
+	/// one (see screen.texture_wrap() and friends) whenever it is drawn.
+    pub fn texture_sampling(mut self, sampling: TextureSampling) -> Self {
+        self.texture_sampling = Some(sampling);
+        self
+    }
 }
@@ -0,0 +1,165 @@
+use std::f64;
+
+use glium;
+
+use Screen;
+use errors::ProcessingErr;
+use textures::TextureSampling;
+
+use shapes::{Shape, ShapeVertex, ShapeData, IndexType, Mesh, check_lengths};
+
+/// A sphere is the set of all points that are the same distance (its radius) from its
+/// center. It is built from a grid of longitude and latitude lines, whose resolution
+/// is set by screen.sphere_detail(), and has normals that point straight out from its
+/// center. A texture is wrapped around it like a map around a globe, with its left
+/// and right edges meeting at the back of the sphere.
+pub struct Sphere<'a> {
+    fill_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    fill_index_buffer: IndexType,
+    stroke_index_buffer: IndexType,
+    texture: Option<&'a glium::texture::Texture2d>,
+    texture_sampling: Option<TextureSampling>,
+    detail: (usize, usize),
+    data: ShapeData,
+}
+
+impl<'a> Shape for Sphere<'a> {
+    fn fill_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.fill_buffer)
+    }
+
+    fn stroke_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.stroke_buffer)
+    }
+
+    fn fill_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.fill_index_buffer)
+    }
+
+    fn stroke_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.stroke_index_buffer)
+    }
+
+    fn get_texture(&self) -> Option<Box<&glium::texture::Texture2d>> {
+        match self.texture {
+            Some(t) => Some(Box::new(t)),
+            _ => None,
+        }
+    }
+
+    fn get_texture_sampling(&self) -> Option<TextureSampling> {
+        self.texture_sampling
+    }
+
+    fn has_normals(&self) -> bool {
+        true
+    }
+}
+
+impl<'a> Sphere<'a> {
+	/// Create a new Sphere to be drawn later. It has a center position
+	/// (xci, yci, zci) and a radius (ri).
+    pub fn new(
+        screen: &Screen,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        ri: &[f64],
+    ) -> Result<Self, ProcessingErr> {
+        let detail = screen.sphere_detail;
+        let mesh = Sphere::mesh(detail);
+        let (shape, n) = Sphere::vertices(screen, &mesh, xci, yci, zci, ri)?;
+        let (fill_index_buffer, stroke_index_buffer) = mesh.indices(screen, n)?;
+        let data = ShapeData::new(screen, shape, n);
+        let (fill_shape_buffer, stroke_shape_buffer) = data.buffers(screen)?;
+
+        Ok(Sphere {
+            fill_buffer: fill_shape_buffer,
+            stroke_buffer: stroke_shape_buffer,
+            fill_index_buffer: fill_index_buffer,
+            stroke_index_buffer: stroke_index_buffer,
+            texture: None,
+            texture_sampling: None,
+            detail: detail,
+            data: data,
+        })
+    }
+
+    // a sphere of radius 1 around the origin, with its poles on the y-axis
+    fn mesh(detail: (usize, usize)) -> Mesh {
+        Mesh::grid(detail.0, detail.1, |u, v| {
+            let phi = 2.0 * f64::consts::PI * u - f64::consts::PI;
+            let theta = f64::consts::PI * v;
+            let p = [theta.sin() * phi.sin(), theta.cos(), theta.sin() * phi.cos()];
+            (p, p)
+        })
+    }
+
+    // the vertices of all of the sub-shapes, and how many of them there are
+    fn vertices(
+        screen: &Screen,
+        mesh: &Mesh,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        ri: &[f64],
+    ) -> Result<(Vec<ShapeVertex>, usize), ProcessingErr> {
+        let n = check_lengths(&[xci.len(), yci.len(), zci.len(), ri.len()])?;
+        let mut shape = Vec::with_capacity(n * mesh.positions.len());
+        for i in 0..n {
+            mesh.place(screen, [xci[i], yci[i], zci[i]], [ri[i], ri[i], ri[i]], &mut shape);
+        }
+
+        Ok((shape, n))
+    }
+
+	/// Move and resize the spheres of this Sphere without creating a new one. There
+	/// have to be as many spheres as before. They keep the detail that they were
+	/// created with, as well as their colors.
+    pub fn update(
+        &mut self,
+        screen: &Screen,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        ri: &[f64],
+    ) -> Result<(), ProcessingErr> {
+        let mesh = Sphere::mesh(self.detail);
+        let (shape, n) = Sphere::vertices(screen, &mesh, xci, yci, zci, ri)?;
+        self.data.set_vertices(shape, n, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+	/// Change the fill color of this shape in place. As in screen.fill(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_fill(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_fill(color_mat, &self.fill_buffer)
+    }
+
+	/// Change the stroke color of this shape in place. As in screen.stroke(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_stroke(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_stroke(color_mat, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+    pub fn attach_texture(&mut self, tex: &'a glium::texture::Texture2d) {
+        self.texture = Some(tex);
+    }
+
+	/// Give this shape its own texture sampling, which takes the place of the global
+	/// one (see screen.texture_wrap() and friends) whenever it is drawn.
+    pub fn texture_sampling(&mut self, sampling: TextureSampling) {
+        self.texture_sampling = Some(sampling);
+    }
+}
+
+impl<'a> Screen<'a> {
+	/// Set how finely spheres are divided: into ures pieces around their equator and
+	/// into vres pieces from pole to pole. The default is 30 for both. Cylinders,
+	/// cones, and tori use the same numbers for how finely they go around (ures) and,
+	/// for tori, around their tube (vres). This only affects shapes that are created
+	/// afterwards.
+    pub fn sphere_detail(&mut self, ures: usize, vres: usize) {
+        self.sphere_detail = (ures, vres);
+    }
+}
@@ -0,0 +1,161 @@
+use std::f64;
+
+use glium;
+
+use Screen;
+use errors::ProcessingErr;
+use textures::TextureSampling;
+
+use shapes::{Shape, ShapeVertex, ShapeData, IndexType, Mesh, check_lengths};
+
+/// A torus is a ring-shaped tube, like a donut. It faces the viewer, i.e., the hole in
+/// its middle runs along the z-axis. It has smooth normals, and an attached texture
+/// runs once around the ring and once around the tube.
+pub struct Torus<'a> {
+    fill_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
+    fill_index_buffer: IndexType,
+    stroke_index_buffer: IndexType,
+    texture: Option<&'a glium::texture::Texture2d>,
+    texture_sampling: Option<TextureSampling>,
+    detail: (usize, usize),
+    data: ShapeData,
+}
+
+impl<'a> Shape for Torus<'a> {
+    fn fill_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.fill_buffer)
+    }
+
+    fn stroke_buffer(&self) -> Box<&glium::vertex::VertexBuffer<ShapeVertex>> {
+        Box::new(&self.stroke_buffer)
+    }
+
+    fn fill_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.fill_index_buffer)
+    }
+
+    fn stroke_indices(&self) -> Box<&IndexType> {
+        Box::new(&self.stroke_index_buffer)
+    }
+
+    fn get_texture(&self) -> Option<Box<&glium::texture::Texture2d>> {
+        match self.texture {
+            Some(t) => Some(Box::new(t)),
+            _ => None,
+        }
+    }
+
+    fn get_texture_sampling(&self) -> Option<TextureSampling> {
+        self.texture_sampling
+    }
+
+    fn has_normals(&self) -> bool {
+        true
+    }
+}
+
+impl<'a> Torus<'a> {
+	/// Create a new Torus to be drawn later. It has a center position
+	/// (xci, yci, zci), a radius (ri), which is the distance from its center to the
+	/// middle of the tube, and the radius of the tube itself (ti). The number of
+	/// pieces it is made of around the ring and around the tube is taken from
+	/// screen.sphere_detail().
+    pub fn new(
+        screen: &Screen,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        ri: &[f64],
+        ti: &[f64],
+    ) -> Result<Self, ProcessingErr> {
+        let detail = screen.sphere_detail;
+        let (shape, n) = Torus::vertices(screen, detail, xci, yci, zci, ri, ti)?;
+        // all tori have the same triangles, no matter how thick they are
+        let (fill_index_buffer, stroke_index_buffer) = Torus::mesh(detail, 1.0, 0.5).indices(screen, n)?;
+        let data = ShapeData::new(screen, shape, n);
+        let (fill_shape_buffer, stroke_shape_buffer) = data.buffers(screen)?;
+
+        Ok(Torus {
+            fill_buffer: fill_shape_buffer,
+            stroke_buffer: stroke_shape_buffer,
+            fill_index_buffer: fill_index_buffer,
+            stroke_index_buffer: stroke_index_buffer,
+            texture: None,
+            texture_sampling: None,
+            detail: detail,
+            data: data,
+        })
+    }
+
+    // A torus of radius r and tube radius t around the origin. It cannot simply be
+    // scaled from a single torus, because the ratio of r and t changes its shape.
+    fn mesh(detail: (usize, usize), r: f64, t: f64) -> Mesh {
+        Mesh::grid(detail.0, detail.1, |u, v| {
+            let phi = 2.0 * f64::consts::PI * u - f64::consts::PI;
+            let theta = f64::consts::PI / 2.0 - 2.0 * f64::consts::PI * v;
+            let ring = r + t * theta.cos();
+            (
+                [ring * phi.cos(), ring * phi.sin(), t * theta.sin()],
+                [theta.cos() * phi.cos(), theta.cos() * phi.sin(), theta.sin()],
+            )
+        })
+    }
+
+    // the vertices of all of the sub-shapes, and how many of them there are
+    fn vertices(
+        screen: &Screen,
+        detail: (usize, usize),
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        ri: &[f64],
+        ti: &[f64],
+    ) -> Result<(Vec<ShapeVertex>, usize), ProcessingErr> {
+        let n = check_lengths(&[xci.len(), yci.len(), zci.len(), ri.len(), ti.len()])?;
+        let mut shape = vec![];
+        for i in 0..n {
+            Torus::mesh(detail, ri[i], ti[i]).place(screen, [xci[i], yci[i], zci[i]], [1.0, 1.0, 1.0], &mut shape);
+        }
+
+        Ok((shape, n))
+    }
+
+	/// Move and resize the tori of this Torus without creating a new one. There
+	/// have to be as many tori as before. They keep the detail that they were
+	/// created with, as well as their colors.
+    pub fn update(
+        &mut self,
+        screen: &Screen,
+        xci: &[f64],
+        yci: &[f64],
+        zci: &[f64],
+        ri: &[f64],
+        ti: &[f64],
+    ) -> Result<(), ProcessingErr> {
+        let (shape, n) = Torus::vertices(screen, self.detail, xci, yci, zci, ri, ti)?;
+        self.data.set_vertices(shape, n, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+	/// Change the fill color of this shape in place. As in screen.fill(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_fill(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_fill(color_mat, &self.fill_buffer)
+    }
+
+	/// Change the stroke color of this shape in place. As in screen.stroke(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_stroke(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_stroke(color_mat, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+    pub fn attach_texture(&mut self, tex: &'a glium::texture::Texture2d) {
+        self.texture = Some(tex);
+    }
+
+	/// Give this shape its own texture sampling, which takes the place of the global
+	/// one (see screen.texture_wrap() and friends) whenever it is drawn.
+    pub fn texture_sampling(&mut self, sampling: TextureSampling) {
+        self.texture_sampling = Some(sampling);
+    }
+}
@@ -2,10 +2,11 @@ use std::f32;
 
 use glium;
 
-use {Screen, ScreenType};
+use Screen;
 use errors::ProcessingErr;
 
-use shapes::{Shape, ShapeVertex, IndexType, load_colors};
+use shapes::{Shape, ShapeVertex, ShapeData, IndexType, check_lengths, outline_edges,
+             stroke_indices};
 
 /// A triangle has three straight lines as its sides, so it is completely specified
 /// by three points.
@@ -14,6 +15,7 @@ pub struct Triangle {
     stroke_buffer: glium::vertex::VertexBuffer<ShapeVertex>,
     fill_index_buffer: IndexType,
     stroke_index_buffer: IndexType,
+    data: ShapeData,
 }
 
 impl Shape for Triangle {
@@ -54,6 +56,45 @@ impl Triangle {
         y3i: &[f64],
         z3i: &[f64],
     ) -> Result<Self, ProcessingErr> {
+        let (shape, n) = Triangle::vertices(screen, x1i, y1i, z1i, x2i, y2i, z2i, x3i, y3i, z3i)?;
+        let data = ShapeData::new(screen, shape, n);
+        let (fill_shape_buffer, stroke_shape_buffer) = data.buffers(screen)?;
+
+        Ok(Triangle {
+            fill_buffer: fill_shape_buffer,
+            stroke_buffer: stroke_shape_buffer,
+            fill_index_buffer: IndexType::NoBuffer {
+                ind: glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+            },
+            stroke_index_buffer: stroke_indices(screen, &outline_edges(n, 3, 0, 3, true))?,
+            data: data,
+        })
+    }
+
+    // the vertices of all of the sub-shapes, and how many of them there are
+    fn vertices(
+        screen: &Screen,
+        x1i: &[f64],
+        y1i: &[f64],
+        z1i: &[f64],
+        x2i: &[f64],
+        y2i: &[f64],
+        z2i: &[f64],
+        x3i: &[f64],
+        y3i: &[f64],
+        z3i: &[f64],
+    ) -> Result<(Vec<ShapeVertex>, usize), ProcessingErr> {
+        let n = check_lengths(&[
+            x1i.len(),
+            y1i.len(),
+            z1i.len(),
+            x2i.len(),
+            y2i.len(),
+            z2i.len(),
+            x3i.len(),
+            y3i.len(),
+            z3i.len(),
+        ])?;
         let mut x1 = x1i.iter().map(|&v| v).collect::<Vec<f64>>();
         let mut y1 = y1i.iter().map(|&v| v).collect::<Vec<f64>>();
         let z1 = z1i.iter().map(|&v| v).collect::<Vec<f64>>();
@@ -94,6 +135,7 @@ impl Triangle {
                 ],
                 color: [0.0, 0.0, 0.0, 0.0],
                 texcoord: [0f32, 0.],
+                normal: [0.0, 0.0, 0.0],
             };
             shape.push(vertex);
             let vertex = ShapeVertex {
@@ -108,6 +150,7 @@ impl Triangle {
                 ],
                 color: [0.0, 0.0, 0.0, 0.0],
                 texcoord: [1f32, 0.],
+                normal: [0.0, 0.0, 0.0],
             };
             shape.push(vertex);
             let vertex = ShapeVertex {
@@ -122,6 +165,7 @@ impl Triangle {
                 ],
                 color: [0.0, 0.0, 0.0, 0.0],
                 texcoord: [1f32, 1.],
+                normal: [0.0, 0.0, 0.0],
             };
             shape.push(vertex);
         }
@@ -142,32 +186,38 @@ impl Triangle {
         // gl.BufferData(gl.ARRAY_BUFFER, len(texData)*4, gl.Ptr(texData), gl.STATIC_DRAW)
         // }
 
-        load_colors(&mut shape, &screen.fill_col);
-        let fill_shape_buffer = match screen.display {
-            ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
-            	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
-            	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-        };
-
-        load_colors(&mut shape, &screen.stroke_col);
-        let stroke_shape_buffer = match screen.display {
-            ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
-            	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-            ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
-            	.map_err(|e| ProcessingErr::VBNoCreate(e))?,
-        };
-
-        // screen.draw(fill_shape_buffer, stroke_shape_buffer, Some(index_buffer));
-        Ok(Triangle {
-            fill_buffer: fill_shape_buffer,
-            stroke_buffer: stroke_shape_buffer,
-            fill_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
-            },
-            stroke_index_buffer: IndexType::NoBuffer {
-                ind: glium::index::NoIndices(glium::index::PrimitiveType::LineLoop),
-            },
-        })
+        Ok((shape, n))
+    }
+
+	/// Move the corners of the triangles of this Triangle without creating a new one.
+	/// The arguments are the same as for Triangle::new(), but they have to describe
+	/// the same number of triangles as before. The colors stay as they are.
+    pub fn update(
+        &mut self,
+        screen: &Screen,
+        x1i: &[f64],
+        y1i: &[f64],
+        z1i: &[f64],
+        x2i: &[f64],
+        y2i: &[f64],
+        z2i: &[f64],
+        x3i: &[f64],
+        y3i: &[f64],
+        z3i: &[f64],
+    ) -> Result<(), ProcessingErr> {
+        let (shape, n) = Triangle::vertices(screen, x1i, y1i, z1i, x2i, y2i, z2i, x3i, y3i, z3i)?;
+        self.data.set_vertices(shape, n, &self.fill_buffer, &self.stroke_buffer)
+    }
+
+	/// Change the fill color of this shape in place. As in screen.fill(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_fill(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_fill(color_mat, &self.fill_buffer)
+    }
+
+	/// Change the stroke color of this shape in place. As in screen.stroke(), this is
+	/// either one color for everything or one color per sub-shape.
+    pub fn set_stroke(&mut self, color_mat: &[f32]) -> Result<(), ProcessingErr> {
+        self.data.set_stroke(color_mat, &self.fill_buffer, &self.stroke_buffer)
     }
 }
@@ -0,0 +1,292 @@
+// Typography
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use glium;
+use glium::Surface;
+use glium::backend::Facade;
+use rusttype;
+use rusttype::gpu_cache::Cache;
+
+use {Screen, ScreenType};
+use errors::ProcessingErr;
+
+// side length, in texels, of the texture that glyphs are cached in
+const CACHE_SIZE: u32 = 1024;
+
+#[derive(Copy, Clone)]
+pub struct TextVertex {
+    position: [f32; 2],
+    texcoord: [f32; 2],
+}
+
+implement_vertex!(TextVertex, position, texcoord);
+
+/// Everything needed to draw text with a given font. The glyphs are rasterized on the
+/// CPU by `rusttype` the first time they are needed and then stored in a single
+/// texture (a glyph atlas) on the GPU, so that drawing the same characters again
+/// only costs a few triangles.
+pub struct FontState {
+    font: rusttype::Font<'static>,
+    cache: Cache<'static>,
+    cache_texture: glium::texture::Texture2d,
+}
+
+/// Find a font that is installed on the current system. A few common locations are
+/// tried for every operating system and the first font that exists is used. If
+/// nothing is found, an empty string is returned and you will need to call
+/// screen.text_font() before drawing any text.
+pub fn default_font_face() -> String {
+    let candidates: &[&str] = if cfg!(target_os = "windows") {
+        &[
+            "C:/Windows/Fonts/arial.ttf",
+            "C:/Windows/Fonts/segoeui.ttf",
+            "C:/Windows/Fonts/tahoma.ttf",
+        ]
+    } else if cfg!(target_os = "macos") {
+        &[
+            "/Library/Fonts/Arial.ttf",
+            "/System/Library/Fonts/Supplemental/Arial.ttf",
+            "/Library/Fonts/Verdana.ttf",
+            "/System/Library/Fonts/Supplemental/Verdana.ttf",
+            "/System/Library/Fonts/SFNSMono.ttf",
+        ]
+    } else {
+        &[
+            "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+            "/usr/share/fonts/TTF/DejaVuSans.ttf",
+            "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+            "/usr/share/fonts/ttf-dejavu-ib/DejaVuSansMono.ttf",
+            "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+            "/usr/share/fonts/liberation/LiberationSans-Regular.ttf",
+            "/usr/share/fonts/truetype/freefont/FreeSans.ttf",
+        ]
+    };
+
+    for c in candidates {
+        if Path::new(c).exists() {
+            return c.to_string();
+        }
+    }
+
+    "".to_owned()
+}
+
+fn load_font<F: Facade>(facade: &F, font_face: &str) -> Result<FontState, ProcessingErr> {
+    if font_face == "" {
+        return Err(ProcessingErr::NoFontAvailable);
+    }
+
+    let mut data = Vec::new();
+    File::open(font_face)
+        .and_then(|mut f| f.read_to_end(&mut data))
+        .map_err(|e| ProcessingErr::FontNotFound(e))?;
+    let font = rusttype::Font::from_bytes(data).map_err(|e| ProcessingErr::FontNotParsed(e))?;
+
+    let cache = Cache::builder()
+        .dimensions(CACHE_SIZE, CACHE_SIZE)
+        .build();
+    let cache_texture = glium::texture::Texture2d::with_format(
+        facade,
+        glium::texture::RawImage2d {
+            data: Cow::Owned(vec![0u8; CACHE_SIZE as usize * CACHE_SIZE as usize]),
+            width: CACHE_SIZE,
+            height: CACHE_SIZE,
+            format: glium::texture::ClientFormat::U8,
+        },
+        glium::texture::UncompressedFloatFormat::U8,
+        glium::texture::MipmapsOption::NoMipmap,
+    ).map_err(|e| ProcessingErr::TextureNoCreate(e))?;
+
+    Ok(FontState {
+        font: font,
+        cache: cache,
+        cache_texture: cache_texture,
+    })
+}
+
+impl<'a> Screen<'a> {
+	/// Draw a string of text onto the screen, with the left end of its baseline at
+	/// (x, y). The text is drawn in the current fill color, with the current font
+	/// (see screen.text_font()) and size (see screen.text_size()), and it follows the
+	/// current transformation matrix, just like any other shape. If fill has been
+	/// turned off, nothing is drawn. The font is loaded the first time this is called,
+	/// so that first call will take a little longer than the rest.
+    pub fn text(&mut self, s: &str, x: f64, y: f64) -> Result<(), ProcessingErr> {
+        if !self.fill_stuff {
+            return Ok(());
+        }
+        self.init_font()?;
+        self.flush()?;
+
+        let mut x = x;
+        let mut y = y;
+        if self.preserve_aspect_ratio && self.aspect_ratio != 1f32 {
+            if self.aspect_ratio > 1f32 {
+                x = x / self.aspect_ratio as f64;
+            } else {
+                y = y * self.aspect_ratio as f64;
+            }
+        }
+
+        // glyphs are laid out in pixels, so convert them to normalized screen
+        // coordinates before they are handed to the shader
+        let sx = 2.0 / self.fb_size[0] as f32;
+        let sy = 2.0 / self.fb_size[1] as f32;
+        let x = x as f32;
+        let y = y as f32;
+
+        let mut shape = vec![];
+        {
+            let FontState {
+                ref font,
+                ref mut cache,
+                ref cache_texture,
+            } = *self.font_state.as_mut().unwrap();
+
+            let glyphs: Vec<_> = font.layout(
+                s,
+                rusttype::Scale::uniform(self.text_size),
+                rusttype::point(0.0, 0.0),
+            ).collect();
+            for g in &glyphs {
+                cache.queue_glyph(0, g.clone());
+            }
+            cache
+                .cache_queued(|rect, data| {
+                    cache_texture.main_level().write(
+                        glium::Rect {
+                            left: rect.min.x,
+                            bottom: rect.min.y,
+                            width: rect.width(),
+                            height: rect.height(),
+                        },
+                        glium::texture::RawImage2d {
+                            data: Cow::Borrowed(data),
+                            width: rect.width(),
+                            height: rect.height(),
+                            format: glium::texture::ClientFormat::U8,
+                        },
+                    );
+                })
+                .map_err(|e| ProcessingErr::GlyphNotCached(e))?;
+
+            for g in &glyphs {
+                if let Some((uv, r)) = cache.rect_for(0, g).map_err(|e| ProcessingErr::GlyphNotRead(e))? {
+                    let left = x + r.min.x as f32 * sx;
+                    let right = x + r.max.x as f32 * sx;
+                    let top = y - r.min.y as f32 * sy;
+                    let bottom = y - r.max.y as f32 * sy;
+                    shape.push(TextVertex {
+                        position: [left, top],
+                        texcoord: [uv.min.x, uv.min.y],
+                    });
+                    shape.push(TextVertex {
+                        position: [right, top],
+                        texcoord: [uv.max.x, uv.min.y],
+                    });
+                    shape.push(TextVertex {
+                        position: [right, bottom],
+                        texcoord: [uv.max.x, uv.max.y],
+                    });
+                    shape.push(TextVertex {
+                        position: [right, bottom],
+                        texcoord: [uv.max.x, uv.max.y],
+                    });
+                    shape.push(TextVertex {
+                        position: [left, bottom],
+                        texcoord: [uv.min.x, uv.max.y],
+                    });
+                    shape.push(TextVertex {
+                        position: [left, top],
+                        texcoord: [uv.min.x, uv.min.y],
+                    });
+                }
+            }
+        }
+
+        let text_buffer = match self.display {
+            ScreenType::Window(ref d) => glium::VertexBuffer::new(d, &shape)
+                .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+            ScreenType::Headless(ref d) => glium::VertexBuffer::new(d, &shape)
+                .map_err(|e| ProcessingErr::VBNoCreate(e))?,
+        };
+
+        let font_state = self.font_state.as_ref().unwrap();
+        let color = [
+            self.fill_col[0],
+            self.fill_col[1],
+            self.fill_col[2],
+            self.fill_col[3],
+        ];
+        let sampler = font_state
+            .cache_texture
+            .sampled()
+            .magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest)
+            .minify_filter(glium::uniforms::MinifySamplerFilter::Nearest);
+        let u = create_uniforms!{self, text: sampler, textColor: color};
+        let framebuffer = &mut self.fbo;
+        framebuffer
+            .draw(
+                &text_buffer,
+                &glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                &self.shader_bank[2],
+                &u,
+                &self.draw_params,
+            )
+            .map_err(|e| ProcessingErr::FBDrawFailed(e))?;
+
+        Ok(())
+    }
+
+	/// Set the size of the text drawn by screen.text(), in pixels. The default is 12,
+	/// the same as in Processing.
+    pub fn text_size(&mut self, size: f32) {
+        self.text_size = size;
+    }
+
+	/// Use the TrueType font found at the given path for all text that is drawn from
+	/// now on. The font is loaded right away, so that a missing or broken font file
+	/// is reported here rather than at the next call to screen.text().
+    pub fn text_font(&mut self, font_face: &str) -> Result<(), ProcessingErr> {
+        let font_state = match self.display {
+            ScreenType::Window(ref d) => load_font(d, font_face)?,
+            ScreenType::Headless(ref d) => load_font(d, font_face)?,
+        };
+        self.font_face = font_face.to_owned();
+        self.font_state = Some(font_state);
+        self.fonts_initialized = true;
+
+        Ok(())
+    }
+
+	/// How wide the given string would be if it was drawn with the current font and
+	/// text size, in normalized screen coordinates.
+    pub fn text_width(&mut self, s: &str) -> Result<f64, ProcessingErr> {
+        self.init_font()?;
+        let font = &self.font_state.as_ref().unwrap().font;
+        let width = font.layout(
+            s,
+            rusttype::Scale::uniform(self.text_size),
+            rusttype::point(0.0, 0.0),
+        ).filter_map(|g| g.pixel_bounding_box().map(|r| r.max.x))
+            .fold(0, |acc, v| if v > acc { v } else { acc });
+
+        Ok(2.0 * width as f64 / self.fb_size[0] as f64)
+    }
+
+    fn init_font(&mut self) -> Result<(), ProcessingErr> {
+        if !self.fonts_initialized {
+            let font_state = match self.display {
+                ScreenType::Window(ref d) => load_font(d, &self.font_face)?,
+                ScreenType::Headless(ref d) => load_font(d, &self.font_face)?,
+            };
+            self.font_state = Some(font_state);
+            self.fonts_initialized = true;
+        }
+
+        Ok(())
+    }
+}
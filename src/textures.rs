@@ -1,13 +1,369 @@
-use std::ops::DerefMut;
+use std::ops::{Deref, DerefMut};
 
+use gl;
 use glium;
+use glium::GlObject;
+use glium::backend::Facade;
+use glium::texture::{Texture2d, Texture2dArray, SrgbTexture2d, SrgbTexture2dArray, MipmapsOption,
+                     UncompressedFloatFormat, SrgbFormat, Dimensions};
+use glium::uniforms::{Sampler, SamplerWrapFunction, MinifySamplerFilter, MagnifySamplerFilter, AsUniformValue,
+                      UniformValue};
 use image_ext;
 
 use {Screen, ScreenType};
+use shapes::Shape;
 use errors::ProcessingErr;
 
-// not a part of the standard OpenGL specs?...
-const GL_TEXTURE_MAX_ANISOTROPY_EXT: u32 = 0x84FE;
+/// How a texture is sampled when it is drawn: what happens outside of its edges
+/// (wrap), how it is filtered when it is drawn smaller (minify) or larger (magnify)
+/// than its actual size, and how much anisotropic filtering is applied to it when it
+/// is seen at a steep angle. The minify filters that mention mipmaps make use of the
+/// smaller versions of the texture that are generated when it is created. For
+/// pixel-exact stimuli, where every texel has to end up on exactly one pixel without
+/// being blended with its neighbours, use TextureSampling::nearest().
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextureSampling {
+    pub wrap: SamplerWrapFunction,
+    pub minify: MinifySamplerFilter,
+    pub magnify: MagnifySamplerFilter,
+    pub anisotropy: u16,
+}
+
+impl Default for TextureSampling {
+    fn default() -> Self {
+        TextureSampling {
+            wrap: SamplerWrapFunction::Repeat,
+            minify: MinifySamplerFilter::LinearMipmapLinear,
+            magnify: MagnifySamplerFilter::Linear,
+            anisotropy: 1,
+        }
+    }
+}
+
+impl TextureSampling {
+	/// Sampling without any filtering, so that every pixel gets the color of exactly
+	/// one texel, and that clamps at the edges of the texture.
+    pub fn nearest() -> Self {
+        TextureSampling {
+            wrap: SamplerWrapFunction::Clamp,
+            minify: MinifySamplerFilter::Nearest,
+            magnify: MagnifySamplerFilter::Nearest,
+            anisotropy: 1,
+        }
+    }
+
+	/// Pair a texture with this sampling, in the form that shaders expect. This is
+	/// what the built-in shapes use, but you can also pass it to a custom shader in
+	/// create_uniforms!{}.
+    pub fn sampler<'t>(&self, tex: &'t glium::texture::Texture2d) -> Sampler<'t, glium::texture::Texture2d> {
+        tex.sampled()
+            .wrap_function(self.wrap)
+            .minify_filter(self.minify)
+            .magnify_filter(self.magnify)
+            .anisotropy(self.anisotropy)
+    }
+}
+
+/// The formats in which textures and framebuffers can store their colors. RGBA8 is
+/// the usual 8 bits per channel, RGB10A2 gives 10 bits to each color channel (e.g.,
+/// for 10-bit monitors), and RGBA16 gives 16. RGBA16F and RGBA32F store half and full
+/// floats, which can hold values outside of [0, 1]. SRGB8A8 stores 8 bits per channel
+/// in sRGB encoding and turns them into linear values whenever it is sampled. Since
+/// `processing-rs` otherwise passes colors through unchanged, i.e., treats them as
+/// already encoded for the monitor, textures in this format look darker on the
+/// built-in shapes and are meant for shaders that work in linear light. The default
+/// is RGBA32F, which is the most precise, but also takes four times the memory of
+/// RGBA8.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextureFormat {
+    RGBA8,
+    SRGB8A8,
+    RGB10A2,
+    RGBA16,
+    RGBA16F,
+    RGBA32F,
+}
+
+impl Default for TextureFormat {
+    fn default() -> Self {
+        TextureFormat::RGBA32F
+    }
+}
+
+impl TextureFormat {
+	/// The format that glium is told that a texture in this format has. sRGB textures
+	/// are a separate type in glium, so their Texture2d view (see ScreenTexture) is
+	/// given the closest linear format.
+    pub fn glium_format(&self) -> UncompressedFloatFormat {
+        match *self {
+            TextureFormat::RGBA8 | TextureFormat::SRGB8A8 => UncompressedFloatFormat::U8U8U8U8,
+            TextureFormat::RGB10A2 => UncompressedFloatFormat::U10U10U10U2,
+            TextureFormat::RGBA16 => UncompressedFloatFormat::U16U16U16U16,
+            TextureFormat::RGBA16F => UncompressedFloatFormat::F16F16F16F16,
+            TextureFormat::RGBA32F => UncompressedFloatFormat::F32F32F32F32,
+        }
+    }
+
+	/// How many bits the color channels (together) and the alpha channel of a window's
+	/// framebuffer need to have to match this format.
+    pub fn pixel_format(&self) -> (u8, u8) {
+        match *self {
+            TextureFormat::RGBA8 | TextureFormat::SRGB8A8 => (24, 8),
+            TextureFormat::RGB10A2 => (30, 2),
+            TextureFormat::RGBA16 | TextureFormat::RGBA16F => (48, 16),
+            TextureFormat::RGBA32F => (96, 32),
+        }
+    }
+}
+
+/// The formats that a screen asks for when it is created (see Screen::new_with_format()).
+/// The framebuffer is the hidden one that everything is drawn into before
+/// screen.reveal() copies it onto the window. The display is the framebuffer of the
+/// window itself; if it is None, the backend's usual format is used. The driver may
+/// not grant a display format, in which case the screen falls back to the usual one,
+/// so check screen.display_format() to see what you actually got.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ScreenFormat {
+    pub framebuffer: TextureFormat,
+    pub display: Option<TextureFormat>,
+}
+
+impl Default for ScreenFormat {
+    fn default() -> Self {
+        ScreenFormat {
+            framebuffer: TextureFormat::RGBA32F,
+            display: None,
+        }
+    }
+}
+
+/// The format that the driver actually gave a texture or framebuffer, which is not
+/// always the one that was asked for, given as the number of bits of each channel,
+/// whether they hold floats, and whether they are sRGB encoded.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct GrantedFormat {
+    pub red_bits: u32,
+    pub green_bits: u32,
+    pub blue_bits: u32,
+    pub alpha_bits: u32,
+    pub float: bool,
+    pub srgb: bool,
+}
+
+/// A texture that was made by the Screen, in the format set by screen.texture_format().
+/// glium keeps sRGB textures in a type of their own, so for SRGB8A8 this holds the
+/// SrgbTexture2d along with a Texture2d view of it that does not own it; for every
+/// other format, it is just the Texture2d. Either way, it derefs to a Texture2d, so
+/// that it can be attached to shapes and turned into a framebuffer, and it can be
+/// passed to create_uniforms!{} as it is.
+pub struct ScreenTexture {
+    // declared first, so that the view is dropped before the texture that it shows
+    texture: Texture2d,
+    srgb: Option<SrgbTexture2d>,
+}
+
+impl ScreenTexture {
+	/// The sRGB texture, if the texture has the format SRGB8A8.
+    pub fn srgb(&self) -> Option<&SrgbTexture2d> {
+        self.srgb.as_ref()
+    }
+}
+
+impl Deref for ScreenTexture {
+    type Target = Texture2d;
+
+    fn deref(&self) -> &Texture2d {
+        &self.texture
+    }
+}
+
+impl<'t> AsUniformValue for &'t ScreenTexture {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
+        match self.srgb {
+            Some(ref t) => UniformValue::SrgbTexture2d(t, None),
+            None => UniformValue::Texture2d(&self.texture, None),
+        }
+    }
+}
+
+/// The same as ScreenTexture, but for a texture array (see screen.texture_array()).
+pub struct ScreenTextureArray {
+    texture: Texture2dArray,
+    srgb: Option<SrgbTexture2dArray>,
+}
+
+impl ScreenTextureArray {
+	/// The sRGB texture array, if the textures have the format SRGB8A8.
+    pub fn srgb(&self) -> Option<&SrgbTexture2dArray> {
+        self.srgb.as_ref()
+    }
+}
+
+impl Deref for ScreenTextureArray {
+    type Target = Texture2dArray;
+
+    fn deref(&self) -> &Texture2dArray {
+        &self.texture
+    }
+}
+
+impl<'t> AsUniformValue for &'t ScreenTextureArray {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
+        match self.srgb {
+            Some(ref t) => UniformValue::SrgbTexture2dArray(t, None),
+            None => UniformValue::Texture2dArray(&self.texture, None),
+        }
+    }
+}
+
+/// Create an empty texture in the given format. This is what screen.empty_texture()
+/// and the screen's own framebuffer use.
+pub fn empty_texture_with_format(
+    display: &Facade,
+    format: TextureFormat,
+    mipmaps: MipmapsOption,
+    w: u32,
+    h: u32,
+) -> Result<ScreenTexture, ProcessingErr> {
+    if format == TextureFormat::SRGB8A8 {
+        let t = SrgbTexture2d::empty_with_format(display, SrgbFormat::U8U8U8U8, mipmaps, w, h)
+            .map_err(|e| ProcessingErr::TextureNoCreate(e))?;
+        Ok(srgb_texture(display, t, mipmaps))
+    } else {
+        Texture2d::empty_with_format(display, format.glium_format(), mipmaps, w, h)
+            .map(|t| ScreenTexture { texture: t, srgb: None })
+            .map_err(|e| ProcessingErr::TextureNoCreate(e))
+    }
+}
+
+// Keep the sRGB texture alive next to a Texture2d view of it, which does not own it.
+fn srgb_texture(display: &Facade, t: SrgbTexture2d, mipmaps: MipmapsOption) -> ScreenTexture {
+    let dims = Dimensions::Texture2d {
+        width: t.get_width(),
+        height: t.get_height().unwrap_or(1),
+    };
+    let view = unsafe { Texture2d::from_id(display, UncompressedFloatFormat::U8U8U8U8, t.get_id(), false, mipmaps, dims) };
+    ScreenTexture {
+        texture: view,
+        srgb: Some(t),
+    }
+}
+
+fn texture_with_format<'d, T: Clone + 'd + glium::texture::PixelValue>(
+    display: &Facade,
+    image: glium::texture::RawImage2d<'d, T>,
+    format: TextureFormat,
+) -> Result<ScreenTexture, ProcessingErr> {
+    let mipmaps = MipmapsOption::AutoGeneratedMipmaps;
+    if format == TextureFormat::SRGB8A8 {
+        let t = SrgbTexture2d::with_format(display, image, SrgbFormat::U8U8U8U8, mipmaps)
+            .map_err(|e| ProcessingErr::TextureNoCreate(e))?;
+        Ok(srgb_texture(display, t, mipmaps))
+    } else {
+        Texture2d::with_format(display, image, format.glium_format(), mipmaps)
+            .map(|t| ScreenTexture { texture: t, srgb: None })
+            .map_err(|e| ProcessingErr::TextureNoCreate(e))
+    }
+}
+
+fn texture_array_with_format<'d, T: Clone + 'd + glium::texture::PixelValue>(
+    display: &Facade,
+    images: Vec<glium::texture::RawImage2d<'d, T>>,
+    format: TextureFormat,
+) -> Result<ScreenTextureArray, ProcessingErr> {
+    let mipmaps = MipmapsOption::AutoGeneratedMipmaps;
+    if format == TextureFormat::SRGB8A8 {
+        let t = SrgbTexture2dArray::with_format(display, images, SrgbFormat::U8U8U8U8, mipmaps)
+            .map_err(|e| ProcessingErr::TextureNoCreate(e))?;
+        let dims = Dimensions::Texture2dArray {
+            width: t.get_width(),
+            height: t.get_height().unwrap_or(1),
+            array_size: t.get_array_size().unwrap_or(1),
+        };
+        let view = unsafe {
+            Texture2dArray::from_id(display, UncompressedFloatFormat::U8U8U8U8, t.get_id(), false, mipmaps, dims)
+        };
+        Ok(ScreenTextureArray {
+            texture: view,
+            srgb: Some(t),
+        })
+    } else {
+        Texture2dArray::with_format(display, images, format.glium_format(), mipmaps)
+            .map(|t| ScreenTextureArray { texture: t, srgb: None })
+            .map_err(|e| ProcessingErr::TextureNoCreate(e))
+    }
+}
+
+/// Ask OpenGL which format it gave the texture with the given id.
+pub fn granted_texture_format(id: u32) -> GrantedFormat {
+    let mut f = GrantedFormat::default();
+    let (mut prev, mut internal, mut ty) = (0, 0, 0);
+    unsafe {
+        // glium keeps track of which texture is bound, so put the old one back afterwards
+        gl::GetIntegerv(gl::TEXTURE_BINDING_2D, &mut prev);
+        gl::BindTexture(gl::TEXTURE_2D, id);
+        let mut bits = |pname| {
+            let mut b = 0;
+            gl::GetTexLevelParameteriv(gl::TEXTURE_2D, 0, pname, &mut b);
+            b as u32
+        };
+        f.red_bits = bits(gl::TEXTURE_RED_SIZE);
+        f.green_bits = bits(gl::TEXTURE_GREEN_SIZE);
+        f.blue_bits = bits(gl::TEXTURE_BLUE_SIZE);
+        f.alpha_bits = bits(gl::TEXTURE_ALPHA_SIZE);
+        gl::GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_INTERNAL_FORMAT, &mut internal);
+        gl::GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_RED_TYPE, &mut ty);
+        gl::BindTexture(gl::TEXTURE_2D, prev as u32);
+    }
+    f.float = ty as u32 == gl::FLOAT;
+    f.srgb = internal as u32 == gl::SRGB8_ALPHA8 || internal as u32 == gl::SRGB8;
+
+    f
+}
+
+/// Ask OpenGL which format the framebuffer of the window has. This has to happen while
+/// it is still bound, i.e., before glium draws anything. Windows are double buffered,
+/// so this looks at the back buffer, which is where screen.reveal() draws. The value
+/// of GL_DRAW_BUFFER (GL_BACK) can't be used for this, since it is not a valid
+/// attachment of the default framebuffer.
+pub fn granted_display_format() -> GrantedFormat {
+    let mut f = GrantedFormat::default();
+    let (mut ty, mut encoding) = (0, 0);
+    let buffer = gl::BACK_LEFT;
+    unsafe {
+        let mut bits = |pname| {
+            let mut b = 0;
+            gl::GetFramebufferAttachmentParameteriv(gl::DRAW_FRAMEBUFFER, buffer, pname, &mut b);
+            b as u32
+        };
+        f.red_bits = bits(gl::FRAMEBUFFER_ATTACHMENT_RED_SIZE);
+        f.green_bits = bits(gl::FRAMEBUFFER_ATTACHMENT_GREEN_SIZE);
+        f.blue_bits = bits(gl::FRAMEBUFFER_ATTACHMENT_BLUE_SIZE);
+        f.alpha_bits = bits(gl::FRAMEBUFFER_ATTACHMENT_ALPHA_SIZE);
+        gl::GetFramebufferAttachmentParameteriv(gl::DRAW_FRAMEBUFFER, buffer,
+            gl::FRAMEBUFFER_ATTACHMENT_COMPONENT_TYPE, &mut ty);
+        gl::GetFramebufferAttachmentParameteriv(gl::DRAW_FRAMEBUFFER, buffer,
+            gl::FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING, &mut encoding);
+    }
+    f.float = ty as u32 == gl::FLOAT;
+    f.srgb = encoding as u32 == gl::SRGB;
+
+    f
+}
+
+/// The format of the framebuffer of a headless screen. There is no window whose
+/// framebuffer could be asked about, and glutin always builds headless renderers
+/// with 8 bits per channel.
+pub fn headless_display_format() -> GrantedFormat {
+    GrantedFormat {
+        red_bits: 8,
+        green_bits: 8,
+        blue_bits: 8,
+        alpha_bits: 8,
+        float: false,
+        srgb: false,
+    }
+}
 
 impl<'a> Screen<'a> {
 	/// Take an image (in particular anything that implements image::ImageBuffer,
@@ -18,7 +374,7 @@ impl<'a> Screen<'a> {
     pub fn texture<P, S, C>(
         &mut self,
         img: &image_ext::ImageBuffer<P, C>,
-    ) -> Result<(glium::texture::Texture2d, f64, f64), ProcessingErr>
+    ) -> Result<(ScreenTexture, f64, f64), ProcessingErr>
     where
         P: image_ext::Pixel<Subpixel = S> + 'static,
         S: image_ext::Primitive + 'static + glium::texture::ToClientFormat + glium::texture::PixelValue,
@@ -28,31 +384,10 @@ impl<'a> Screen<'a> {
         // let raw = img.into_raw().clone();
         let image_ext = glium::texture::RawImage2d::from_raw_rgba_reversed(&img, wh);
         let texture = match self.display {
-            ScreenType::Window(ref d) => {
-                glium::texture::Texture2d::with_format(
-                    d,
-                    image_ext,
-                    glium::texture::UncompressedFloatFormat::F32F32F32F32,
-                    glium::texture::MipmapsOption::AutoGeneratedMipmaps,
-                ).map_err(|e| ProcessingErr::TextureNoCreate(e))?
-            }
-            ScreenType::Headless(ref d) => {
-                glium::texture::Texture2d::with_format(
-                    d,
-                    image_ext,
-                    glium::texture::UncompressedFloatFormat::F32F32F32F32,
-                    glium::texture::MipmapsOption::AutoGeneratedMipmaps,
-                ).map_err(|e| ProcessingErr::TextureNoCreate(e))?
-            }
+            ScreenType::Window(ref d) => texture_with_format(d, image_ext, self.texture_format)?,
+            ScreenType::Headless(ref d) => texture_with_format(d, image_ext, self.texture_format)?,
         };
 
-        // maxAniso := []float32{0.0}
-        // gl::GetFloatv(GL_TEXTURE_MAX_ANISOTROPY_EXT, &maxAniso[0])
-        // gl::TexParameterf(gl::TEXTURE_2D, GL_TEXTURE_MAX_ANISOTROPY_EXT, maxAniso[0])
-        // this will require some performance testing. it might be too heavy for
-        // psychophysics
-        // gl::TexParameterf(gl::TEXTURE_2D, GL_TEXTURE_MAX_ANISOTROPY_EXT, 16f32);
-
         Ok((
             texture,
             wh.0 as f64 / self.width as f64,
@@ -64,26 +399,11 @@ impl<'a> Screen<'a> {
 	/// so see that for more info. This function is useful in circumstances where you
 	/// want to draw something onto a texture and use that for a later purpose, rather
 	/// than load an external image. See the framebuffers module for more info.
-    pub fn empty_texture(&self, w: u32, h: u32) -> Result<(glium::texture::Texture2d, f64, f64), ProcessingErr> {
+    pub fn empty_texture(&self, w: u32, h: u32) -> Result<(ScreenTexture, f64, f64), ProcessingErr> {
+        let mipmaps = MipmapsOption::AutoGeneratedMipmaps;
         let texture = match self.display {
-            ScreenType::Window(ref d) => {
-                glium::texture::Texture2d::empty_with_format(
-                    d,
-                    glium::texture::UncompressedFloatFormat::F32F32F32F32,
-                    glium::texture::MipmapsOption::AutoGeneratedMipmaps,
-                    w,
-                    h,
-                ).map_err(|e| ProcessingErr::TextureNoCreate(e))?
-            }
-            ScreenType::Headless(ref d) => {
-                glium::texture::Texture2d::empty_with_format(
-                    d,
-                    glium::texture::UncompressedFloatFormat::F32F32F32F32,
-                    glium::texture::MipmapsOption::AutoGeneratedMipmaps,
-                    w,
-                    h,
-                ).map_err(|e| ProcessingErr::TextureNoCreate(e))?
-            }
+            ScreenType::Window(ref d) => empty_texture_with_format(d, self.texture_format, mipmaps, w, h)?,
+            ScreenType::Headless(ref d) => empty_texture_with_format(d, self.texture_format, mipmaps, w, h)?,
         };
 
         Ok((
@@ -132,38 +452,100 @@ impl<'a> Screen<'a> {
 	/// 2-dimensional textures (i.e., pre-processed RGBA images) in one part of memory.     /// For the user, this means that you can upload a movie for instance and step
 	/// through the frames in your shader via an overloaded call to texture().
 	/// Please see OpenGL tutorials and documentation for more info. This function
-	/// returns a glium::texture::Texture2dArray, which is accepted by the
-	/// `create_uniforms{}` macro.
-    pub fn texture_array<T: Clone + 'a + glium::texture::PixelValue>(&mut self, images: Vec<glium::texture::RawImage2d<T>>) -> Result<glium::texture::Texture2dArray, ProcessingErr> {
-            match self.display {
-                ScreenType::Window(ref d) => {
-                    glium::texture::Texture2dArray::with_format(d,
-                        images,
-                        glium::texture::UncompressedFloatFormat::F32F32F32F32,
-                        glium::texture::MipmapsOption::AutoGeneratedMipmaps)
-                        	.map_err(|e| ProcessingErr::TextureNoCreate(e))
-                }
-                ScreenType::Headless(ref d) => {
-                    glium::texture::Texture2dArray::with_format(d,
-                        images,
-                        glium::texture::UncompressedFloatFormat::F32F32F32F32,
-                        glium::texture::MipmapsOption::AutoGeneratedMipmaps)
-                        	.map_err(|e| ProcessingErr::TextureNoCreate(e))
-                }
+	/// returns a ScreenTextureArray, which derefs to a glium::texture::Texture2dArray
+	/// and is accepted by the `create_uniforms{}` macro.
+    pub fn texture_array<T: Clone + 'a + glium::texture::PixelValue>(&mut self, images: Vec<glium::texture::RawImage2d<T>>) -> Result<ScreenTextureArray, ProcessingErr> {
+        match self.display {
+            ScreenType::Window(ref d) => texture_array_with_format(d, images, self.texture_format),
+            ScreenType::Headless(ref d) => texture_array_with_format(d, images, self.texture_format),
         }
     }
 
 
+	/// Set the format of the textures that are created from now on by
+	/// screen.texture(), screen.empty_texture(), and screen.texture_array(), and
+	/// thereby also of any framebuffers that are made from them. The default is
+	/// RGBA32F. See screen.granted_texture_format() for what the driver actually
+	/// made of it.
+    pub fn texture_format(&mut self, format: TextureFormat) {
+        self.texture_format = format;
+    }
+
+	/// The format that the driver actually gave a texture.
+    pub fn granted_texture_format(&self, tex: &Texture2d) -> GrantedFormat {
+        granted_texture_format(tex.get_id())
+    }
+
+	/// The format that the driver actually gave the screen's hidden framebuffer, into
+	/// which everything is drawn (see ScreenFormat).
+    pub fn framebuffer_format(&self) -> GrantedFormat {
+        granted_texture_format(self.fbtexture.get_id())
+    }
+
+	/// The format that the driver actually gave the framebuffer of the window, i.e.,
+	/// what ends up on the monitor.
+    pub fn display_format(&self) -> GrantedFormat {
+        self.display_format
+    }
+
 	/// When you sample outside the boundaries of a texture, should it wrap around and
-	/// repeat ("REPEAT", the default) or should it clamp ("CLAMP") at the edge. See
-	/// the official Processing reference for more info and examples.
+	/// repeat ("REPEAT", the default), repeat mirrored ("MIRROR"), or should it clamp
+	/// ("CLAMP") at the edge. See the official Processing reference for more info and
+	/// examples. This applies to the textures of all shapes that were not given their
+	/// own sampling with their texture_sampling() method.
     pub fn texture_wrap(&mut self, wrap: &str) {
         if wrap == "CLAMP" {
-            self.wrap = glium::uniforms::SamplerWrapFunction::Clamp;
+            self.texture_sampling.wrap = SamplerWrapFunction::Clamp;
         } else if wrap == "REPEAT" {
-            self.wrap = glium::uniforms::SamplerWrapFunction::Repeat;
+            self.texture_sampling.wrap = SamplerWrapFunction::Repeat;
+        } else if wrap == "MIRROR" {
+            self.texture_sampling.wrap = SamplerWrapFunction::Mirror;
         }
     }
+
+	/// Set how textures are filtered, as in Processing's textureSampling(): "POINT"
+	/// uses the nearest texel without any filtering or mipmaps, which keeps stimuli
+	/// pixel-exact, "LINEAR" blends the nearest texels without mipmaps, "BILINEAR"
+	/// also picks the nearest mipmap when a texture is drawn smaller, and "TRILINEAR"
+	/// (the default) blends between the two nearest mipmaps. This applies to the
+	/// textures of all shapes that were not given their own sampling with their
+	/// texture_sampling() method.
+    pub fn texture_filter(&mut self, mode: &str) {
+        let filters = if mode == "POINT" {
+            Some((MinifySamplerFilter::Nearest, MagnifySamplerFilter::Nearest))
+        } else if mode == "LINEAR" {
+            Some((MinifySamplerFilter::Linear, MagnifySamplerFilter::Linear))
+        } else if mode == "BILINEAR" {
+            Some((MinifySamplerFilter::LinearMipmapNearest, MagnifySamplerFilter::Linear))
+        } else if mode == "TRILINEAR" {
+            Some((MinifySamplerFilter::LinearMipmapLinear, MagnifySamplerFilter::Linear))
+        } else {
+            None
+        };
+        if let Some((minify, magnify)) = filters {
+            self.texture_sampling.minify = minify;
+            self.texture_sampling.magnify = magnify;
+        }
+    }
+
+	/// Set the level of anisotropic filtering for all textures that were not given
+	/// their own sampling. 1 (the default) turns it off, and higher values keep
+	/// textures sharp when they are seen at a steep angle. The level is limited to
+	/// what the graphics card supports.
+    pub fn texture_anisotropy(&mut self, level: u16) {
+        self.texture_sampling.anisotropy = if level == 0 { 1 } else { level };
+    }
+
+	/// The sampling that is used for all textures that were not given their own.
+    pub fn get_texture_sampling(&self) -> TextureSampling {
+        self.texture_sampling
+    }
+
+	/// The sampling that a shape's texture is drawn with: the shape's own, if it was
+	/// given one, and the global one otherwise.
+    pub fn texture_sampling_of<S: Shape + ?Sized>(&self, shape: &S) -> TextureSampling {
+        shape.get_texture_sampling().unwrap_or(self.texture_sampling)
+    }
 }
 
 // pub fn del_textures(texs: &[u32]) {
@@ -0,0 +1,524 @@
+// Timing of frames
+use std::io;
+use std::io::{Write, BufWriter};
+use std::fs::File;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use gl;
+
+use Screen;
+use errors::ProcessingErr;
+
+// how many of the most recent flips are used to detect the refresh interval
+const REFRESH_WINDOW: usize = 60;
+// how many flips there must have been before the refresh interval is trusted for pacing
+const REFRESH_WARMUP: usize = 10;
+// how far off a whole number of refreshes a frame period may be and still count as one
+const DIVISOR_TOLERANCE: f64 = 0.05;
+// sleeping is only accurate to a millisecond or two, so the rest of the wait is spun
+const SPIN_MARGIN: f64 = 0.002;
+// how much a new frame counts in the moving average of the frame rate, as in Processing
+const FRAME_RATE_SMOOTHING: f64 = 0.1;
+
+/// What happened when a frame was shown by screen.reveal(). All times are in seconds
+/// since the Screen was created, measured with a monotonic clock.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FlipInfo {
+    /// The number of the frame, i.e., screen.frame_count() while it was drawn.
+    pub frame: isize,
+    /// When the buffers had been swapped. This is taken right after the swap returns,
+    /// or after glFinish() with screen.precise_flip_timing(true), which is more
+    /// accurate, since many drivers return from the swap before it is done.
+    pub timestamp: f64,
+    /// The estimated time of the vertical blank in which the frame was shown, based on
+    /// the refresh interval of the monitor. Without one, it is the same as the timestamp.
+    pub vblank: f64,
+    /// The time since the previous flip, if there was one.
+    pub interval: Option<f64>,
+    /// Whether more refresh intervals passed since the previous flip than the frame rate
+    /// asked for (one, unless it is below the refresh rate), i.e., whether at least one
+    /// frame was missed and the previous one was shown for too long.
+    pub missed: bool,
+}
+
+/// Keeps track of the flips of a Screen: when they happened, how long the refresh
+/// interval of the monitor is, and, if asked to, a log of all of them. It also paces
+/// the flips to the frame rate set by screen.set_frame_rate(). The refresh interval
+/// comes from the monitor, if the backend can tell, or from
+/// screen.set_refresh_rate(). Only without either is it detected from the flips
+/// themselves, which cannot tell a monitor that the sketch keeps up with from one
+/// that is twice as fast, where every other refresh is missed.
+pub struct FlipTimer {
+    start: Instant,
+    precise: bool,
+    vsync: bool,
+    last: Option<FlipInfo>,
+    intervals: Vec<f64>,
+    nominal: Option<f64>,
+    detected: Option<f64>,
+    deadline: Option<f64>,
+    paced: bool,
+    expected: f64,
+    measured: Option<f64>,
+    logging: bool,
+    log: Vec<FlipInfo>,
+}
+
+impl FlipTimer {
+	/// A timer for a Screen whose swaps do (vsync) or do not wait for the vertical blank,
+	/// on a monitor with the given refresh rate in Hz, if it is known.
+    pub fn new(vsync: bool, refresh_rate: Option<f64>) -> Self {
+        FlipTimer {
+            start: Instant::now(),
+            precise: false,
+            vsync: vsync,
+            last: None,
+            intervals: Vec::with_capacity(REFRESH_WINDOW),
+            nominal: refresh_rate.and_then(interval_of),
+            detected: None,
+            deadline: None,
+            paced: false,
+            expected: 1.0,
+            measured: None,
+            logging: false,
+            log: Vec::new(),
+        }
+    }
+
+    // the refresh interval of the monitor, if it is known, and the detected one otherwise
+    fn refresh(&self) -> Option<f64> {
+        self.nominal.or(self.detected)
+    }
+
+    fn now(&self) -> f64 {
+        let d = self.start.elapsed();
+        d.as_secs() as f64 + d.subsec_nanos() as f64 / 1e9
+    }
+
+    fn flip(&mut self, frame: isize) -> FlipInfo {
+        let timestamp = self.now();
+        self.flip_at(frame, timestamp)
+    }
+
+    // record a flip of the given frame that happened at the given time
+    fn flip_at(&mut self, frame: isize, timestamp: f64) -> FlipInfo {
+        let mut info = FlipInfo {
+            frame: frame,
+            timestamp: timestamp,
+            vblank: timestamp,
+            interval: None,
+            missed: false,
+        };
+
+        if let Some(last) = self.last {
+            let interval = timestamp - last.timestamp;
+            info.interval = Some(interval);
+
+            if let Some(refresh) = self.refresh() {
+                // how many refreshes went by since the previous vertical blank
+                let refreshes = ((timestamp - last.vblank) / refresh).round().max(1.0);
+                info.missed = refreshes > self.expected.ceil();
+                // the swap returns shortly after the vertical blank, so a prediction that
+                // is later than that, or too much earlier, means that it drifted
+                let predicted = last.vblank + refreshes * refresh;
+                if predicted <= timestamp && timestamp - predicted < refresh / 2.0 {
+                    info.vblank = predicted;
+                }
+            }
+
+            // without a known refresh rate, the refresh interval is the median of the
+            // recent intervals, which is robust against the occasional missed frame.
+            // Flips that were held back for a lower frame rate say nothing about the
+            // monitor, so they are left out.
+            if !(self.vsync && self.paced) {
+                if self.intervals.len() == REFRESH_WINDOW {
+                    self.intervals.remove(0);
+                }
+                self.intervals.push(interval);
+                let mut sorted = self.intervals.clone();
+                sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(::std::cmp::Ordering::Equal));
+                self.detected = Some(sorted[sorted.len() / 2]);
+            }
+
+            if interval > 0.0 {
+                let rate = 1.0 / interval;
+                self.measured = Some(match self.measured {
+                    Some(m) => m * (1.0 - FRAME_RATE_SMOOTHING) + rate * FRAME_RATE_SMOOTHING,
+                    None => rate,
+                });
+            }
+        }
+
+        self.last = Some(info);
+        if self.logging {
+            self.log.push(info);
+        }
+
+        info
+    }
+
+    // Wait until it is time to swap the buffers for a frame rate of frame_rate (none
+    // if it is not positive). With vsync, the swap itself waits for the next vertical
+    // blank, so there is only something to do if the frame rate is below the refresh
+    // rate: if it divides it, the swap is done in the middle of the refresh before the
+    // Nth vertical blank after the previous flip, so that the frame is shown exactly
+    // then; otherwise, the swap is done early enough for the next vertical blank after
+    // the ideal time. Without vsync, the swap is done at the ideal time.
+    fn pace(&mut self, frame_rate: isize) {
+        let now = self.now();
+        if let Some(deadline) = self.pace_at(frame_rate, now) {
+            self.wait_until(deadline);
+        }
+    }
+
+    // when to swap the buffers for the next frame at the given frame rate, as asked at
+    // the given time, unless there is no need to wait
+    fn pace_at(&mut self, frame_rate: isize, now: f64) -> Option<f64> {
+        self.paced = false;
+        self.expected = 1.0;
+        let last = match self.last {
+            Some(last) if frame_rate > 0 => last,
+            _ => {
+                self.deadline = None;
+                return None;
+            }
+        };
+        let period = 1.0 / frame_rate as f64;
+
+        let deadline = if self.vsync {
+            let refresh = match self.refresh() {
+                Some(r) if self.nominal.is_some() || self.intervals.len() >= REFRESH_WARMUP => r,
+                _ => {
+                    self.deadline = None;
+                    return None;
+                }
+            };
+            let refreshes = period / refresh;
+            if refreshes < 1.0 + DIVISOR_TOLERANCE {
+                self.deadline = None;
+                return None;
+            }
+            self.paced = true;
+            self.expected = refreshes;
+
+            let whole = refreshes.round();
+            if (refreshes - whole).abs() < DIVISOR_TOLERANCE {
+                self.expected = whole;
+                self.deadline = None;
+                last.vblank + (whole - 0.5) * refresh
+            } else {
+                self.schedule(period, last.timestamp, now) - refresh / 2.0
+            }
+        } else {
+            self.paced = true;
+            self.schedule(period, last.timestamp, now)
+        };
+
+        Some(deadline)
+    }
+
+    // The ideal time of the next flip: one period after the ideal time of the previous
+    // one, so that the frame rate does not drift, unless that one was already too late.
+    fn schedule(&mut self, period: f64, last: f64, now: f64) -> f64 {
+        let next = match self.deadline {
+            Some(d) if d + period > now => d + period,
+            _ => last + period,
+        };
+        self.deadline = Some(next);
+        next
+    }
+
+    fn wait_until(&self, deadline: f64) {
+        let sleep = deadline - self.now() - SPIN_MARGIN;
+        if sleep > 0.0 {
+            thread::sleep(Duration::new(sleep as u64, (sleep.fract() * 1e9) as u32));
+        }
+        while self.now() < deadline {
+            thread::yield_now();
+        }
+    }
+}
+
+// the interval of a refresh rate in Hz, if it is a sensible one
+fn interval_of(refresh_rate: f64) -> Option<f64> {
+    if refresh_rate.is_finite() && refresh_rate > 0.0 {
+        Some(1.0 / refresh_rate)
+    } else {
+        None
+    }
+}
+
+impl<'a> Screen<'a> {
+	/// Wait until it is time to show the next frame at the frame rate set by
+	/// screen.set_frame_rate(). This is called by screen.reveal() right before the
+	/// buffers are swapped, so there is no need to call it yourself.
+    pub fn pace_frame(&mut self) {
+        let frame_rate = self.frame_rate;
+        self.flip_timer.pace(frame_rate);
+    }
+
+	/// Record the flip that screen.reveal() just did. This is called by screen.reveal(),
+	/// so there is no need to call it yourself.
+    pub fn record_flip(&mut self) -> FlipInfo {
+        if self.flip_timer.precise {
+            unsafe {
+                gl::Finish();
+            }
+        }
+        let frame = self.frame_count;
+        self.flip_timer.flip(frame)
+    }
+
+	/// Whether to call glFinish() right after swapping the buffers in screen.reveal(),
+	/// so that the timestamp of a flip is taken only once the swap has really happened.
+	/// This makes the timestamps more accurate, but it blocks until the GPU is done,
+	/// which can cost some time for drawing the next frame. It is off by default.
+    pub fn precise_flip_timing(&mut self, precise: bool) {
+        self.flip_timer.precise = precise;
+    }
+
+	/// What happened at the last screen.reveal(), if there was one.
+    pub fn last_flip(&self) -> Option<FlipInfo> {
+        self.flip_timer.last
+    }
+
+	/// The refresh interval of the monitor in seconds. It comes from the monitor (with
+	/// the GLFW backend) or from screen.set_refresh_rate(), and is otherwise detected
+	/// from the recent flips, which is only meaningful if the Screen is synchronized to
+	/// the monitor and mostly keeps up with it.
+    pub fn refresh_interval(&self) -> Option<f64> {
+        self.flip_timer.refresh()
+    }
+
+	/// Tell the Screen the refresh rate of the monitor in Hz, for detecting missed
+	/// frames and pacing, when the backend cannot ask the monitor for it (glutin) or
+	/// gets it wrong. A rate that is not positive goes back to detecting the refresh
+	/// interval from the flips.
+    pub fn set_refresh_rate(&mut self, refresh_rate: f64) {
+        self.flip_timer.nominal = interval_of(refresh_rate);
+    }
+
+	/// The number of frames per second that are actually being shown, as a moving
+	/// average over the recent flips, like frameRate in Processing. It is 0 until there
+	/// have been two flips.
+    pub fn measured_frame_rate(&self) -> f64 {
+        self.flip_timer.measured.unwrap_or(0.0)
+    }
+
+	/// Start keeping every flip from now on in the flip log, e.g., to report the onsets
+	/// of stimuli later. The log is kept in memory until it is cleared.
+    pub fn start_flip_log(&mut self) {
+        self.flip_timer.logging = true;
+    }
+
+	/// Stop adding flips to the flip log. The flips that are already in it are kept.
+    pub fn stop_flip_log(&mut self) {
+        self.flip_timer.logging = false;
+    }
+
+	/// All of the flips in the flip log.
+    pub fn flip_log(&self) -> &[FlipInfo] {
+        &self.flip_timer.log
+    }
+
+	/// Remove all of the flips from the flip log.
+    pub fn clear_flip_log(&mut self) {
+        self.flip_timer.log.clear();
+    }
+
+	/// Write the flip log to a CSV file, with one line per flip and the columns frame,
+	/// timestamp, vblank, interval, and missed. Times are in seconds and the interval
+	/// of the first flip is empty.
+    pub fn save_flip_log(&self, filename: &str) -> Result<(), ProcessingErr> {
+        let write = || -> io::Result<()> {
+            let mut f = BufWriter::new(File::create(filename)?);
+            writeln!(f, "frame,timestamp,vblank,interval,missed")?;
+            for flip in &self.flip_timer.log {
+                let interval = flip.interval.map_or("".to_owned(), |i| format!("{:.9}", i));
+                writeln!(
+                    f,
+                    "{},{:.9},{:.9},{},{}",
+                    flip.frame,
+                    flip.timestamp,
+                    flip.vblank,
+                    interval,
+                    flip.missed
+                )?;
+            }
+            f.flush()
+        };
+
+        write().map_err(|e| ProcessingErr::FlipLogNotSaved(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FlipInfo, FlipTimer, interval_of, REFRESH_WARMUP};
+
+    const REFRESH: f64 = 0.02;
+
+    fn flips(timer: &mut FlipTimer, times: &[f64]) -> Vec<FlipInfo> {
+        times.iter().enumerate().map(|(k, &t)| timer.flip_at(k as isize, t)).collect()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn steady_refresh() {
+        let mut timer = FlipTimer::new(true, Some(1.0 / REFRESH));
+        // the swaps return a little after the vertical blanks, by varying amounts
+        let times: Vec<f64> = (0..8).map(|k| 0.1 + k as f64 * REFRESH + 0.001 * (k % 3) as f64).collect();
+        let infos = flips(&mut timer, &times);
+
+        assert_eq!(infos[0].interval, None);
+        assert!(close(infos[0].vblank, 0.1));
+        for (k, info) in infos.iter().enumerate().skip(1) {
+            assert_eq!(info.frame, k as isize);
+            assert!(close(info.timestamp, times[k]));
+            assert!(close(info.interval.unwrap(), times[k] - times[k - 1]));
+            assert!(close(info.vblank, 0.1 + k as f64 * REFRESH), "{:?}", info);
+            assert!(!info.missed, "{:?}", info);
+        }
+    }
+
+    #[test]
+    fn dropped_frame() {
+        let mut timer = FlipTimer::new(true, Some(1.0 / REFRESH));
+        let infos = flips(&mut timer, &[0.1, 0.12, 0.14, 0.18, 0.2]);
+
+        assert!(!infos[2].missed);
+        assert!(infos[3].missed);
+        assert!(close(infos[3].vblank, 0.18));
+        assert!(close(infos[3].interval.unwrap(), 0.04));
+        assert!(!infos[4].missed);
+        assert!(close(infos[4].vblank, 0.2));
+    }
+
+    #[test]
+    fn drift() {
+        // a monitor that is a little faster than it says it is
+        let mut timer = FlipTimer::new(true, Some(1.0 / REFRESH));
+        let infos = flips(&mut timer, &[0.1, 0.1195, 0.139]);
+
+        // the predicted vertical blank would be after the swap returned, so it is not
+        // trusted and the timestamp is used instead
+        assert!(close(infos[1].vblank, 0.1195));
+        assert!(close(infos[2].vblank, 0.139));
+        assert!(!infos[1].missed && !infos[2].missed);
+    }
+
+    #[test]
+    fn detected_refresh() {
+        let mut timer = FlipTimer::new(true, None);
+        assert_eq!(timer.refresh(), None);
+        let times: Vec<f64> = (0..12).map(|k| k as f64 * REFRESH).collect();
+        let infos = flips(&mut timer, &times);
+
+        assert!(close(timer.refresh().unwrap(), REFRESH));
+        assert!(infos.iter().all(|info| !info.missed));
+    }
+
+    #[test]
+    fn nominal_refresh_beats_detected() {
+        // a sketch that only keeps up with every other refresh of a 100 Hz monitor looks
+        // like it runs on a 50 Hz one
+        let mut timer = FlipTimer::new(true, None);
+        let times: Vec<f64> = (0..12).map(|k| k as f64 * REFRESH).collect();
+        flips(&mut timer, &times);
+        assert!(close(timer.refresh().unwrap(), REFRESH));
+
+        timer.nominal = interval_of(100.0);
+        assert!(close(timer.refresh().unwrap(), 0.01));
+        let info = timer.flip_at(12, 12.0 * REFRESH);
+        assert!(info.missed);
+        assert!(close(info.vblank, 12.0 * REFRESH));
+
+        timer.nominal = interval_of(0.0);
+        assert!(close(timer.refresh().unwrap(), REFRESH));
+    }
+
+    #[test]
+    fn divisors() {
+        let mut timer = FlipTimer::new(true, Some(100.0));
+        timer.flip_at(0, 0.1);
+
+        // frame rates that divide the refresh rate, or nearly do, are shown for a whole
+        // number of refreshes, and the swap is done half a refresh before the last one
+        for &frame_rate in &[50, 49, 51] {
+            assert!(close(timer.pace_at(frame_rate, 0.101).unwrap(), 0.1 + 1.5 * 0.01));
+            assert!(timer.paced);
+            assert_eq!(timer.expected, 2.0);
+        }
+        assert!(close(timer.pace_at(25, 0.101).unwrap(), 0.1 + 3.5 * 0.01));
+        assert_eq!(timer.expected, 4.0);
+
+        // others are scheduled at their ideal time, early enough for the vertical blank
+        let deadline = timer.pace_at(48, 0.101).unwrap();
+        assert!(close(deadline, 0.1 + 1.0 / 48.0 - 0.005));
+        assert!(close(timer.expected, 100.0 / 48.0));
+
+        // the swap waits for the vertical blank anyway at (nearly) the refresh rate or
+        // above it
+        for &frame_rate in &[100, 97, 120, 0] {
+            assert_eq!(timer.pace_at(frame_rate, 0.101), None);
+            assert!(!timer.paced);
+            assert_eq!(timer.expected, 1.0);
+        }
+    }
+
+    #[test]
+    fn deadline_follows_vblank() {
+        let mut timer = FlipTimer::new(true, Some(1.0 / REFRESH));
+        let infos = flips(&mut timer, &[0.1, 0.1215]);
+        assert!(close(infos[1].vblank, 0.12));
+
+        assert!(close(timer.pace_at(25, 0.125).unwrap(), 0.12 + 1.5 * REFRESH));
+    }
+
+    #[test]
+    fn catch_up() {
+        let mut timer = FlipTimer::new(false, None);
+        timer.flip_at(0, 0.0);
+        assert!(close(timer.pace_at(10, 0.01).unwrap(), 0.1));
+
+        // a flip that is a little late does not push back the ones after it
+        timer.flip_at(1, 0.1003);
+        assert!(close(timer.pace_at(10, 0.11).unwrap(), 0.2));
+
+        // but once it is too late for that, the schedule starts over from the last flip
+        timer.flip_at(2, 0.35);
+        assert!(close(timer.pace_at(10, 0.36).unwrap(), 0.45));
+        timer.flip_at(3, 0.45);
+        assert!(close(timer.pace_at(10, 0.46).unwrap(), 0.55));
+
+        assert_eq!(timer.pace_at(0, 0.5), None);
+        assert_eq!(timer.deadline, None);
+    }
+
+    #[test]
+    fn warm_up() {
+        // without a nominal refresh rate, there is no pacing until the refresh interval
+        // has been detected from enough flips
+        let mut timer = FlipTimer::new(true, None);
+        for k in 0..REFRESH_WARMUP {
+            timer.flip_at(k as isize, k as f64 * REFRESH);
+            assert_eq!(timer.pace_at(25, k as f64 * REFRESH + 0.001), None);
+        }
+        let t = REFRESH_WARMUP as f64 * REFRESH;
+        timer.flip_at(REFRESH_WARMUP as isize, t);
+        assert!(close(timer.pace_at(25, t + 0.001).unwrap(), t + 1.5 * REFRESH));
+
+        // the flips that are held back say nothing about the refresh interval
+        timer.flip_at(REFRESH_WARMUP as isize + 1, t + 2.0 * REFRESH);
+        assert_eq!(timer.intervals.len(), REFRESH_WARMUP);
+        assert!(close(timer.refresh().unwrap(), REFRESH));
+
+        let mut timer = FlipTimer::new(true, Some(1.0 / REFRESH));
+        timer.flip_at(0, 0.0);
+        assert!(close(timer.pace_at(25, 0.001).unwrap(), 1.5 * REFRESH));
+    }
+}
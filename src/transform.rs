@@ -1,9 +1,24 @@
-use Screen;
+use {Screen, GLmatStruct};
+use errors::ProcessingErr;
 
-use {Matrix4, Vector3, Unit};
+use {Matrix4, Vector3, Point3, Unit};
+
+impl GLmatStruct {
+	/// The combined model-view-projection matrix that shapes are drawn with, i.e.,
+	/// projection * view * model.
+    pub fn mvp(&self) -> Matrix4<f32> {
+        self.projection_matrix * self.view_matrix * self.curr_matrix
+    }
+
+	/// The model matrix followed by the view matrix, which takes shapes into the
+	/// coordinates of the camera. This is where lighting happens.
+    pub fn modelview(&self) -> Matrix4<f32> {
+        self.view_matrix * self.curr_matrix
+    }
+}
 
 impl<'a> Screen<'a> {
-	/// Pre-multiply the current MVP transformation matrix with a matrix formed
+	/// Pre-multiply the current model transformation matrix with a matrix formed
 	/// from the given values.
     pub fn apply_matrix(
         &mut self,
@@ -46,7 +61,7 @@ impl<'a> Screen<'a> {
         self.matrices.curr_matrix = m * self.matrices.curr_matrix;
     }
 
-	/// Remove the current MVP transformation matrix from the stack and use the most
+	/// Remove the current model transformation matrix from the stack and use the most
 	/// recently used one instead.
     pub fn pop_matrix(&mut self) {
         match self.matrices.matrix_stack.pop() {
@@ -57,7 +72,7 @@ impl<'a> Screen<'a> {
         };
     }
 
-	/// Push the current MVP transformation matrix onto the stack, so that it can be 
+	/// Push the current model transformation matrix onto the stack, so that it can be 
 	/// saved for later. Useful for when you want to temporarily apply some rotation
 	/// or translation to a single object and don't want to disturb the rest of the
 	/// scene.
@@ -65,13 +80,13 @@ impl<'a> Screen<'a> {
         self.matrices.matrix_stack.push(self.matrices.curr_matrix);
     }
 
-	/// Remove the current MVP transfomation matrix and set it to the standard 4x4
+	/// Remove the current model transfomation matrix and set it to the standard 4x4
 	/// identity matrix.
     pub fn reset_matrix(&mut self) {
         self.matrices.curr_matrix = Matrix4::identity();
     }
 
-	/// Pre-multiply the current MVP transformation matrix by a rotation matrix which
+	/// Pre-multiply the current model transformation matrix by a rotation matrix which
 	/// is derived from a rotation angle about a vector in the direction (x, y, z).
     pub fn rotate(&mut self, angle: f32, x: f32, y: f32, z: f32) {
         // let m = Matrix4::new(
@@ -97,21 +112,21 @@ impl<'a> Screen<'a> {
         self.matrices.curr_matrix = m * self.matrices.curr_matrix;
     }
 
-	/// Apply a rotation matrix for a given angle around the x-axis to the current MVP
+	/// Apply a rotation matrix for a given angle around the x-axis to the current model
 	/// transformation matrix.
     pub fn rotate_x(&mut self, angle: f32) {
         let m = Matrix4::from_axis_angle(&Unit::new_unchecked(Vector3::new(1., 0., 0.)), angle);
         self.matrices.curr_matrix = m * self.matrices.curr_matrix;
     }
 
-	/// Apply a rotation matrix for a given angle around the y-axis to the current MVP
+	/// Apply a rotation matrix for a given angle around the y-axis to the current model
 	/// transformation matrix.
     pub fn rotate_y(&mut self, angle: f32) {
         let m = Matrix4::from_axis_angle(&Unit::new_unchecked(Vector3::new(0., 1., 0.)), angle);
         self.matrices.curr_matrix = m * self.matrices.curr_matrix;
     }
 
-	/// Apply a rotation matrix for a given angle around the z-axis to the current MVP
+	/// Apply a rotation matrix for a given angle around the z-axis to the current model
 	/// transformation matrix.
     pub fn rotate_z(&mut self, angle: f32) {
         let m = Matrix4::from_axis_angle(&Unit::new_unchecked(Vector3::new(0., 0., 1.)), angle);
@@ -119,7 +134,7 @@ impl<'a> Screen<'a> {
     }
 
 	/// Scale the scene along the x-, y-, and z-axes by applying a matrix derived from
-	/// these values to the current MVP transformation matrix.
+	/// these values to the current model transformation matrix.
     pub fn scale(&mut self, x: f32, y: f32, z: f32) {
         // let m = Matrix4::new(x, 0., 0., 0., 0., y, 0., 0., 0., 0., z, 0., 0., 0., 0., 1.);
 
@@ -129,7 +144,7 @@ impl<'a> Screen<'a> {
     }
 
 	/// Derive a matrix that applies shear for a given angle the scene about the x-axis
-	/// and apply it to the current MVP transformation matrix.
+	/// and apply it to the current model transformation matrix.
     pub fn shear_x(&mut self, angle: f32) {
         let m = Matrix4::new(
             1.,
@@ -154,7 +169,7 @@ impl<'a> Screen<'a> {
     }
 
 	/// Derive a matrix that applies shear for a given angle the scene about the y-axis
-	/// and apply it to the current MVP transformation matrix.
+	/// and apply it to the current model transformation matrix.
     pub fn shear_y(&mut self, angle: f32) {
         let m = Matrix4::new(
             1.,
@@ -179,15 +194,130 @@ impl<'a> Screen<'a> {
     }
 
 	/// Derive a translation matrix from the given (x, y, z) vector and apply it to the
-	/// current MVP transformation matrix.
+	/// current model transformation matrix.
     pub fn translate(&mut self, x: f32, y: f32, z: f32) {
         let m = Matrix4::new(1., 0., 0., x, 0., 1., 0., y, 0., 0., 1., z, 0., 0., 0., 1.);
 
         self.matrices.curr_matrix = m * self.matrices.curr_matrix;
     }
 
-	/// Print out the current MVP transformation matrix.
+	/// Print out the current model transformation matrix.
     pub fn print_matrix(&self) {
         println!("{:?}", self.matrices.curr_matrix);
     }
+
+	/// Use a perspective projection, in which things that are further away from the
+	/// camera look smaller. The field of view (fovy) is the angle, in radians, that
+	/// the camera sees from bottom to top, and aspect is the ratio of the width to the
+	/// height of what it sees. Shapes already squeeze their coordinates to keep the
+	/// aspect ratio of the screen (see screen.preserve_aspect_ratio), so in that case
+	/// an aspect of 1 is what you want. Nothing closer to the camera than near or
+	/// further away than far is drawn. The camera starts out at the origin, so you will
+	/// usually want to move it back with screen.camera(), e.g., to
+	/// (0, 0, 1 / tan(fovy / 2)), where the plane at z = 0 just fills the screen.
+    pub fn perspective(&mut self, fovy: f32, aspect: f32, near: f32, far: f32) {
+        self.matrices.projection_matrix = Matrix4::new_perspective(aspect, fovy, near, far);
+    }
+
+	/// Use an orthographic projection, in which things keep their size no matter how
+	/// far away they are. The box from (left, bottom, -near) to (right, top, -far), in
+	/// the coordinates of the camera, is stretched to fill the screen. Everything
+	/// outside of it is not drawn. The default projection is the same as
+	/// screen.ortho(-1, 1, -1, 1, 1, -1), i.e., nothing is changed at all.
+    pub fn ortho(&mut self, left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) {
+        self.matrices.projection_matrix = Matrix4::new_orthographic(left, right, bottom, top, near, far);
+    }
+
+	/// Use a perspective projection that is given by the sides of its frustum, i.e.,
+	/// of the pyramid that the camera sees, cut off at the near and far planes. Left,
+	/// right, bottom, and top are the sides of the frustum at the near plane. This is
+	/// the same as screen.perspective() when the frustum is symmetric, but also allows
+	/// for an off-center view.
+    pub fn frustum(&mut self, left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) {
+        self.matrices.projection_matrix = Matrix4::new(
+            2. * near / (right - left),
+            0.,
+            (right + left) / (right - left),
+            0.,
+            0.,
+            2. * near / (top - bottom),
+            (top + bottom) / (top - bottom),
+            0.,
+            0.,
+            0.,
+            -(far + near) / (far - near),
+            -2. * far * near / (far - near),
+            0.,
+            0.,
+            -1.,
+            0.,
+        );
+    }
+
+	/// Place the camera at (eye_x, eye_y, eye_z), looking at (center_x, center_y,
+	/// center_z), with the direction (up_x, up_y, up_z) pointing up on the screen. By
+	/// default, the camera sits at the origin and looks down the negative z-axis, with
+	/// the y-axis pointing up.
+    pub fn camera(
+        &mut self,
+        eye_x: f32,
+        eye_y: f32,
+        eye_z: f32,
+        center_x: f32,
+        center_y: f32,
+        center_z: f32,
+        up_x: f32,
+        up_y: f32,
+        up_z: f32,
+    ) {
+        self.matrices.view_matrix = Matrix4::look_at_rh(
+            &Point3::new(eye_x, eye_y, eye_z),
+            &Point3::new(center_x, center_y, center_z),
+            &Vector3::new(up_x, up_y, up_z),
+        );
+    }
+
+	/// Start moving the camera with the usual transformation functions, such as
+	/// screen.translate() and screen.rotate_y(). Until screen.end_camera() is called,
+	/// they act on the camera instead of on the shapes, so that, e.g., translating by
+	/// (0, 0, 2) moves the camera two units back.
+    pub fn begin_camera(&mut self) -> Result<(), ProcessingErr> {
+        if self.matrices.camera_saved.is_some() {
+            return Err(ProcessingErr::CameraAlreadyBegun);
+        }
+        let camera = self.matrices.view_matrix.try_inverse().ok_or(
+            ProcessingErr::CameraNotInvertible,
+        )?;
+        self.matrices.camera_saved = Some(self.matrices.curr_matrix);
+        self.matrices.curr_matrix = camera;
+
+        Ok(())
+    }
+
+	/// Stop moving the camera and go back to transforming shapes, with the model
+	/// matrix that was in effect when screen.begin_camera() was called.
+    pub fn end_camera(&mut self) -> Result<(), ProcessingErr> {
+        let saved = match self.matrices.camera_saved {
+            Some(m) => m,
+            None => return Err(ProcessingErr::CameraNotBegun),
+        };
+        let view = self.matrices.curr_matrix.try_inverse().ok_or(
+            ProcessingErr::CameraNotInvertible,
+        )?;
+        self.matrices.view_matrix = view;
+        self.matrices.curr_matrix = saved;
+        self.matrices.camera_saved = None;
+
+        Ok(())
+    }
+
+	/// Print out the current view matrix, which places the camera.
+    pub fn print_camera(&self) {
+        println!("{:?}", self.matrices.view_matrix);
+    }
+
+	/// Print out the current projection matrix.
+    pub fn print_projection(&self) {
+        println!("{:?}", self.matrices.projection_matrix);
+    }
 }